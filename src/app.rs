@@ -1,13 +1,25 @@
 use anyhow::Result;
+use ratatui::layout::Direction;
 use std::env;
+use std::fs;
 use std::path::{Path, PathBuf};
 
 use crate::{
+    cli::StartupOptions,
+    components::popup::PopupResult,
+    components::pty_screen::PtyScreen,
     components::{
+        main_widget::conflict_view::ConflictView,
         main_widget::editor::Editor,
+        main_widget::git_log::GitLog,
         main_widget::settings_editor::SettingsEditor,
+        main_widget::text_editor::TextEditor,
         main_widget::welcome_widget::WelcomeWidget,
-        panel::term::Term,
+        notification::{send_notification, NotificationType},
+        panel::{
+            panes::{Side, TermPanes},
+            term::Term,
+        },
         primary_sidebar::{
             component::PrimarySidebarComponent, file_view::FileView, git::GitWidget,
             search::SearchWidget,
@@ -15,11 +27,34 @@ use crate::{
         secondary_sidebar::help_widget::HelpWidget,
         top_bar::command_palette::CommandPalette,
     },
+    file_ops,
+    session::{Session, SessionTab, SessionTerminal},
     settings::Config,
     theme::Theme,
+    workspace::WorkspaceWalker,
     ActiveTarget, MainWidgetContent,
 };
 
+/// What waits on the answer to the unsaved changes popup.
+#[derive(Clone, Copy)]
+pub enum PendingClose {
+    /// Closing the main tab at this index.
+    Tab(usize),
+    Quit,
+}
+
+/// What the popup in [`App::save_prompt`] asks about.
+pub enum SavePrompt {
+    /// Whether to save before closing.
+    Close(PendingClose),
+    /// A file name for the built-in buffer in this main tab, which has none yet.
+    /// `then` goes on once it is saved.
+    SaveAs {
+        tab: usize,
+        then: Option<PendingClose>,
+    },
+}
+
 pub struct Tab<T> {
     pub title: String,
     pub content: T,
@@ -34,22 +69,36 @@ pub struct App {
     pub show_panel: bool,
     pub show_command_palette: bool,
     pub quit_popup: Option<crate::Popup>,
+    /// Asks whether to save files before closing their tab or quitting, or where
+    /// to save a new file.
+    pub save_prompt: Option<(crate::Popup, SavePrompt)>,
     pub should_quit: bool,
     pub main_tabs: Vec<Tab<MainWidgetContent>>,
     pub active_main_tab: usize,
-    pub terminals: Vec<Tab<Term>>,
+    pub terminals: Vec<Tab<TermPanes>>,
     pub active_terminal_tab: usize,
     pub primary_sidebar_components: Vec<Tab<PrimarySidebarComponent>>,
     pub active_primary_sidebar_tab: usize,
     pub secondary_sidebar_component: HelpWidget,
     pub command_palette: CommandPalette,
+    /// Root directory the app was started in; sessions are keyed by it.
+    pub workspace: PathBuf,
+    /// Whether the session is restored on launch and saved on quit.
+    pub session_enabled: bool,
 }
 
 impl App {
-    pub fn new() -> Result<Self> {
-        let config = Config::load()?;
-        let theme = Theme::from_config(&config.theme);
+    pub fn new(options: &StartupOptions) -> Result<Self> {
+        let config = Config::load_from(options.config_path.clone())?;
+        let theme = match &options.theme {
+            Some(preset) => Theme::from_config(&crate::settings::Theme {
+                preset: preset.clone(),
+                ..config.theme.clone()
+            }),
+            None => Theme::from_config(&config.theme),
+        };
         let initial_path = env::current_dir().unwrap_or_else(|_| PathBuf::from("/"));
+        let walker = WorkspaceWalker::new(initial_path.clone(), &config.files);
 
         let main_tabs = vec![Tab {
             title: "Welcome".to_string(),
@@ -59,25 +108,31 @@ impl App {
         let primary_sidebar_components = vec![
             Tab {
                 title: "Files".to_string(),
-                content: PrimarySidebarComponent::FileView(FileView::new(initial_path)),
+                content: PrimarySidebarComponent::FileView(Box::new(FileView::new(
+                    initial_path.clone(),
+                    walker.clone(),
+                ))),
             },
             Tab {
                 title: "Search".to_string(),
-                content: PrimarySidebarComponent::Search(SearchWidget::new()),
+                content: PrimarySidebarComponent::Search(Box::new(SearchWidget::new(
+                    walker.clone(),
+                ))),
             },
             Tab {
                 title: "Git".to_string(),
-                content: PrimarySidebarComponent::Git(GitWidget::new()),
+                content: PrimarySidebarComponent::Git(Box::default()),
             },
         ];
 
-        Ok(Self {
+        let mut app = Self {
             active_target: ActiveTarget::Editor,
-            show_primary_sidebar: true,
+            show_primary_sidebar: options.show_sidebar,
             show_secondary_sidebar: false,
             show_panel: false,
             show_command_palette: false,
             quit_popup: None,
+            save_prompt: None,
             should_quit: false,
             active_main_tab: 0,
             main_tabs,
@@ -87,9 +142,25 @@ impl App {
             primary_sidebar_components,
             secondary_sidebar_component: HelpWidget::new(),
             command_palette: CommandPalette::new(),
+            session_enabled: options.use_session && config.restore_session,
+            workspace: initial_path,
             config,
             theme,
-        })
+        };
+
+        app.command_palette.set_walker(walker);
+
+        // Explicitly requested files take precedence over the previous session.
+        if options.files.is_empty() {
+            if app.session_enabled {
+                app.restore_session();
+            }
+        } else {
+            for file in &options.files {
+                app.open_editor_at(&file.path, file.position());
+            }
+        }
+        Ok(app)
     }
 
     pub fn get_active_editor_mut(&mut self) -> Option<&mut Editor> {
@@ -122,14 +193,182 @@ impl App {
         self.active_target = ActiveTarget::Editor;
     }
 
-    pub fn open_editor(&mut self, path: &Path) {
+    pub fn add_text_editor_tab(&mut self, editor: TextEditor, title: String) {
         self.maybe_replace_welcome_tab();
-        let editor = Editor::with_file(path.to_path_buf());
+        self.main_tabs.push(Tab {
+            title,
+            content: MainWidgetContent::TextEditor(editor),
+        });
+        self.active_main_tab = self.main_tabs.len() - 1;
+        self.active_target = ActiveTarget::Editor;
+    }
+
+    pub fn open_editor(&mut self, path: &Path) {
+        self.open_editor_at(path, None);
+    }
+
+    /// Opens `path` in a new editor tab, optionally at a 1-based (line, column).
+    pub fn open_editor_at(&mut self, path: &Path, position: Option<(usize, usize)>) {
+        self.open_editor_of_kind(path, position, self.config.use_builtin_editor);
+    }
+
+    /// Like [`Self::open_editor_at`], in the built-in editor or the configured one
+    /// regardless of `use_builtin_editor`.
+    fn open_editor_of_kind(
+        &mut self,
+        path: &Path,
+        position: Option<(usize, usize)>,
+        builtin: bool,
+    ) {
         let title = match path.file_name() {
             Some(f) => f.to_string_lossy().to_string(),
             None => path.to_string_lossy().to_string(),
         };
-        self.add_editor_tab(editor, title);
+        if builtin {
+            match TextEditor::open(path.to_path_buf()) {
+                Ok(mut editor) => {
+                    if let Some((line, column)) = position {
+                        editor.goto(line, column);
+                    }
+                    self.add_text_editor_tab(editor, title);
+                    self.command_palette.note_recent_file(path);
+                }
+                Err(e) => send_notification(
+                    format!("Failed to open {}: {}", path.display(), e),
+                    NotificationType::Error,
+                ),
+            }
+        } else {
+            match Editor::with_file(&self.config, path, position) {
+                Ok(editor) => {
+                    self.add_editor_tab(editor, title);
+                    self.command_palette.note_recent_file(path);
+                }
+                Err(e) => send_notification(
+                    format!("Failed to open editor: {}", e),
+                    NotificationType::Error,
+                ),
+            }
+        }
+    }
+
+    /// Shows a 1-based (line, column) of `path`, focusing the file's tab if it is
+    /// already open. External editors cannot be moved after starting, so an open
+    /// `$EDITOR` tab is only focused.
+    pub fn show_location(&mut self, path: &Path, line: usize, column: usize) {
+        let canonical = fs::canonicalize(path).ok();
+        let existing = self.main_tabs.iter().position(|t| {
+            t.content.file_path().is_some_and(|p| {
+                p == path || (canonical.is_some() && fs::canonicalize(p).ok() == canonical)
+            })
+        });
+        let Some(index) = existing else {
+            self.open_editor_at(path, Some((line, column)));
+            return;
+        };
+        self.active_main_tab = index;
+        self.active_target = ActiveTarget::Editor;
+        if let MainWidgetContent::TextEditor(editor) = &mut self.main_tabs[index].content {
+            editor.goto(line, column);
+        }
+    }
+
+    /// Moves to the next (or previous) project search result and shows it in the
+    /// editor, leaving focus where it was.
+    pub fn step_search_result(&mut self, forward: bool) {
+        let location = self
+            .primary_sidebar_components
+            .iter_mut()
+            .find_map(|tab| match &mut tab.content {
+                PrimarySidebarComponent::Search(search) => Some(search.step_result(forward)),
+                _ => None,
+            })
+            .flatten();
+        let Some((path, line, column)) = location else {
+            send_notification("No search results".to_string(), NotificationType::Info);
+            return;
+        };
+        let target = self.active_target;
+        self.show_location(&path, line, column);
+        if target == ActiveTarget::PrimarySideBar {
+            self.active_target = target;
+        }
+    }
+
+    /// Opens an empty editor tab of the configured kind.
+    pub fn new_editor_tab(&mut self) {
+        if self.config.use_builtin_editor {
+            let title = format!("Untitled {}", self.main_tabs.len() + 1);
+            self.add_text_editor_tab(TextEditor::new(), title);
+            return;
+        }
+        match Editor::new(&self.config) {
+            Ok(editor) => {
+                let title = format!("Editor {}", self.main_tabs.len() + 1);
+                self.add_editor_tab(editor, title);
+            }
+            Err(e) => send_notification(
+                format!("Failed to open editor: {}", e),
+                NotificationType::Error,
+            ),
+        }
+    }
+
+    /// Saves the file shown in the active main tab.
+    pub fn save_active_tab(&mut self) {
+        match self
+            .main_tabs
+            .get_mut(self.active_main_tab)
+            .map(|tab| &mut tab.content)
+        {
+            Some(MainWidgetContent::Editor(editor)) => {
+                if let Err(e) = editor.save() {
+                    send_notification(format!("Error saving file: {}", e), NotificationType::Error);
+                }
+            }
+            Some(MainWidgetContent::TextEditor(editor)) if editor.path().is_none() => {
+                self.ask_save_as(self.active_main_tab, None);
+            }
+            Some(MainWidgetContent::TextEditor(editor)) => editor.save_with_notification(),
+            _ => {}
+        }
+    }
+
+    /// Asks for a file name for the new buffer in main tab `index`.
+    fn ask_save_as(&mut self, index: usize, then: Option<PendingClose>) {
+        let popup = crate::Popup::input(
+            "Save As".to_string(),
+            format!(
+                "Save {} as (relative to the workspace):",
+                self.main_tabs[index].title
+            ),
+            String::new(),
+        );
+        self.save_prompt = Some((popup, SavePrompt::SaveAs { tab: index, then }));
+    }
+
+    /// Creates `name` and saves the new buffer in main tab `index` there. Existing
+    /// files are not overwritten.
+    fn save_tab_as(&mut self, index: usize, name: &str) -> Result<()> {
+        if name.is_empty() {
+            return Err(anyhow::anyhow!("Enter a file name"));
+        }
+        let path = self.workspace.join(name);
+        let Some(tab) = self.main_tabs.get_mut(index) else {
+            return Ok(());
+        };
+        let MainWidgetContent::TextEditor(editor) = &mut tab.content else {
+            return Ok(());
+        };
+        file_ops::create_file(&path)?;
+        editor.set_path(path.clone());
+        editor.save()?;
+        if let Some(file_name) = path.file_name() {
+            tab.title = file_name.to_string_lossy().to_string();
+        }
+        self.command_palette.note_recent_file(&path);
+        send_notification(format!("Saved {}", name), NotificationType::Info);
+        Ok(())
     }
 
     pub fn close_active_main_tab(&mut self) {
@@ -153,10 +392,146 @@ impl App {
         }
     }
 
+    /// Closes the active main tab, asking first if it has unsaved changes.
+    pub fn request_close_main_tab(&mut self) {
+        let index = self.active_main_tab;
+        let Some(tab) = self.main_tabs.get(index) else {
+            return;
+        };
+        if !matches!(&tab.content, MainWidgetContent::TextEditor(editor) if editor.is_dirty()) {
+            self.remove_main_tab(index);
+            return;
+        }
+        self.save_prompt = Some((
+            crate::Popup::save_changes(
+                "Unsaved Changes".to_string(),
+                format!("Save changes to {} before closing?", tab.title),
+            ),
+            SavePrompt::Close(PendingClose::Tab(index)),
+        ));
+    }
+
+    /// Removes a main tab, opening an empty editor when it was the last one.
+    fn remove_main_tab(&mut self, index: usize) {
+        self.main_tabs.remove(index);
+        if self.main_tabs.is_empty() {
+            self.new_editor_tab();
+            if self.main_tabs.is_empty() {
+                self.show_welcome_screen();
+            }
+        } else if self.active_main_tab >= self.main_tabs.len() {
+            self.active_main_tab = self.main_tabs.len() - 1;
+        }
+    }
+
+    /// Whether the app can quit right away. If files have unsaved changes, asks
+    /// about them first and returns `false`.
+    pub fn request_quit(&mut self) -> bool {
+        let dirty = self.dirty_tabs();
+        let message = match dirty.as_slice() {
+            [] => return true,
+            [index] => format!(
+                "Save changes to {} before quitting?",
+                self.main_tabs[*index].title
+            ),
+            _ => format!(
+                "{} files have unsaved changes. Save them before quitting?",
+                dirty.len()
+            ),
+        };
+        self.save_prompt = Some((
+            crate::Popup::save_changes("Unsaved Changes".to_string(), message),
+            SavePrompt::Close(PendingClose::Quit),
+        ));
+        false
+    }
+
+    /// The main tabs with unsaved changes.
+    fn dirty_tabs(&self) -> Vec<usize> {
+        self.main_tabs
+            .iter()
+            .enumerate()
+            .filter(|(_, tab)| {
+                matches!(&tab.content, MainWidgetContent::TextEditor(editor) if editor.is_dirty())
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Carries out what the unsaved changes or Save As popup was asked for.
+    /// Returns `true` if the app should quit.
+    pub fn answer_save_prompt(&mut self, answer: PopupResult) -> bool {
+        if answer == PopupResult::None {
+            return false;
+        }
+        let Some((popup, prompt)) = self.save_prompt.take() else {
+            return false;
+        };
+        if answer == PopupResult::Cancel {
+            return false;
+        }
+        match prompt {
+            SavePrompt::Close(pending) => self.close_after(pending, answer == PopupResult::Confirm),
+            SavePrompt::SaveAs { tab, then } => {
+                let name = popup.input_value().unwrap_or_default().trim().to_string();
+                match self.save_tab_as(tab, &name) {
+                    Ok(()) => then.is_some_and(|pending| self.close_after(pending, true)),
+                    Err(e) => {
+                        send_notification(
+                            format!("Error saving: {:#}", e),
+                            NotificationType::Error,
+                        );
+                        self.save_prompt = Some((popup, SavePrompt::SaveAs { tab, then }));
+                        false
+                    }
+                }
+            }
+        }
+    }
+
+    /// Closes the tab or quits, saving the files with unsaved changes first if
+    /// `save`. New buffers are asked a file name for, and closing goes on once they
+    /// are saved. Returns `true` if the app should quit.
+    fn close_after(&mut self, pending: PendingClose, save: bool) -> bool {
+        if save {
+            let targets = match pending {
+                PendingClose::Tab(index) => vec![index],
+                PendingClose::Quit => self.dirty_tabs(),
+            };
+            for index in targets {
+                let tab = &mut self.main_tabs[index];
+                let MainWidgetContent::TextEditor(editor) = &mut tab.content else {
+                    continue;
+                };
+                if !editor.is_dirty() {
+                    continue;
+                }
+                if editor.path().is_none() {
+                    self.ask_save_as(index, Some(pending));
+                    return false;
+                }
+                if let Err(e) = editor.save() {
+                    send_notification(
+                        format!("Error saving {}: {}", tab.title, e),
+                        NotificationType::Error,
+                    );
+                    return false;
+                }
+            }
+        }
+        match pending {
+            PendingClose::Tab(index) => {
+                self.remove_main_tab(index);
+                false
+            }
+            PendingClose::Quit => true,
+        }
+    }
+
     pub fn add_terminal_tab(&mut self, term: Term, title: String) {
         self.terminals.push(Tab {
             title,
-            content: term,
+            content: TermPanes::new(term),
         });
         self.active_terminal_tab = self.terminals.len() - 1;
         self.active_target = ActiveTarget::Panel;
@@ -164,8 +539,17 @@ impl App {
     }
 
     pub fn open_new_terminal(&mut self) {
-        if let Ok(term) = Term::new(env::current_dir().ok()) {
-            self.add_terminal_tab(term, format!("Term {}", self.terminals.len() + 1));
+        self.open_terminal_in(env::current_dir().ok());
+    }
+
+    /// Opens a terminal tab, reporting spawn failures as a notification.
+    pub fn open_terminal_in(&mut self, cwd: Option<PathBuf>) {
+        match Term::new(cwd, &self.config) {
+            Ok(term) => self.add_terminal_tab(term, format!("Term {}", self.terminals.len() + 1)),
+            Err(e) => send_notification(
+                format!("Failed to open terminal: {}", e),
+                NotificationType::Error,
+            ),
         }
     }
 
@@ -187,6 +571,126 @@ impl App {
         self.active_target = ActiveTarget::Editor;
     }
 
+    /// Opens the commit history of the workspace repository, or focuses it if the
+    /// log tab is already open.
+    pub fn open_git_log(&mut self) {
+        if let Some(index) = self
+            .main_tabs
+            .iter()
+            .position(|tab| matches!(tab.content, MainWidgetContent::GitLog(_)))
+        {
+            if let MainWidgetContent::GitLog(log) = &mut self.main_tabs[index].content {
+                log.reload();
+            }
+            self.active_main_tab = index;
+            self.active_target = ActiveTarget::Editor;
+            return;
+        }
+        self.maybe_replace_welcome_tab();
+        self.main_tabs.push(Tab {
+            title: "Git Log".to_string(),
+            content: MainWidgetContent::GitLog(Box::new(GitLog::new(&self.workspace))),
+        });
+        self.active_main_tab = self.main_tabs.len() - 1;
+        self.active_target = ActiveTarget::Editor;
+    }
+
+    /// Shows or hides the blame column of the active built-in editor.
+    pub fn toggle_blame(&mut self) {
+        match self
+            .main_tabs
+            .get_mut(self.active_main_tab)
+            .map(|t| &mut t.content)
+        {
+            Some(MainWidgetContent::TextEditor(editor)) if editor.path().is_some() => {
+                editor.toggle_blame();
+            }
+            _ => send_notification(
+                "Blame needs a saved file open in the built-in editor.".to_string(),
+                NotificationType::Warning,
+            ),
+        }
+    }
+
+    /// Opens the Git log at the commit that last changed the cursor line.
+    pub fn show_blame_commit(&mut self) {
+        let id = match self.main_tabs.get(self.active_main_tab).map(|t| &t.content) {
+            Some(MainWidgetContent::TextEditor(editor)) => editor.blame_commit_at_cursor(),
+            _ => None,
+        };
+        let Some(id) = id else {
+            send_notification(
+                "Turn on blame and put the cursor on a committed line first.".to_string(),
+                NotificationType::Warning,
+            );
+            return;
+        };
+        self.open_git_log();
+        if let Some(MainWidgetContent::GitLog(log)) = self
+            .main_tabs
+            .get_mut(self.active_main_tab)
+            .map(|t| &mut t.content)
+        {
+            if !log.select_commit(id) {
+                send_notification(
+                    format!("Commit {} is not in the log.", id),
+                    NotificationType::Warning,
+                );
+            }
+        }
+    }
+
+    /// Opens the conflict resolution tab for `path`, relative to the repository root.
+    pub fn open_conflict(&mut self, path: &str) {
+        if let Some(index) = self.main_tabs.iter().position(
+            |tab| matches!(&tab.content, MainWidgetContent::Conflicts(view) if view.path() == path),
+        ) {
+            if let MainWidgetContent::Conflicts(view) = &mut self.main_tabs[index].content {
+                view.reload();
+            }
+            self.active_main_tab = index;
+            self.active_target = ActiveTarget::Editor;
+            return;
+        }
+        let name = Path::new(path)
+            .file_name()
+            .map_or(path.into(), |n| n.to_string_lossy());
+        self.maybe_replace_welcome_tab();
+        self.main_tabs.push(Tab {
+            title: format!("Conflicts: {}", name),
+            content: MainWidgetContent::Conflicts(Box::new(ConflictView::new(
+                &self.workspace,
+                path,
+            ))),
+        });
+        self.active_main_tab = self.main_tabs.len() - 1;
+        self.active_target = ActiveTarget::Editor;
+    }
+
+    /// Shows and focuses the Git sidebar, for commands that act through it.
+    pub fn focus_git_sidebar(&mut self) -> Option<&mut GitWidget> {
+        let index = self
+            .primary_sidebar_components
+            .iter()
+            .position(|tab| matches!(tab.content, PrimarySidebarComponent::Git(_)))?;
+        self.active_primary_sidebar_tab = index;
+        self.show_primary_sidebar = true;
+        self.active_target = ActiveTarget::PrimarySideBar;
+        match &mut self.primary_sidebar_components[index].content {
+            PrimarySidebarComponent::Git(git) => Some(git),
+            _ => None,
+        }
+    }
+
+    /// Re-reads the status shown by the Git sidebar, e.g. after resolving a conflict.
+    pub fn refresh_git(&mut self) {
+        for tab in &mut self.primary_sidebar_components {
+            if let PrimarySidebarComponent::Git(git) = &mut tab.content {
+                git.refresh();
+            }
+        }
+    }
+
     pub fn show_welcome_screen(&mut self) {
         self.main_tabs.push(Tab {
             title: "Welcome".to_string(),
@@ -229,6 +733,194 @@ impl App {
         }
     }
 
+    /// Shows the panel and opens the find bar of the active terminal.
+    pub fn find_in_terminal(&mut self) {
+        let Some(tab) = self.terminals.get_mut(self.active_terminal_tab) else {
+            send_notification("No terminal is open".to_string(), NotificationType::Info);
+            return;
+        };
+        tab.content.active_mut().open_find();
+        self.show_panel = true;
+        self.active_target = ActiveTarget::Panel;
+    }
+
+    /// Starts selecting text with the keyboard in the focused terminal pane or
+    /// external editor.
+    pub fn select_text(&mut self) {
+        let view: Option<&mut dyn PtyScreen> = match self.active_target {
+            ActiveTarget::Panel => self
+                .terminals
+                .get_mut(self.active_terminal_tab)
+                .map(|tab| tab.content.active_mut() as &mut dyn PtyScreen),
+            ActiveTarget::Editor => self
+                .get_active_editor_mut()
+                .map(|e| e as &mut dyn PtyScreen),
+            _ => None,
+        };
+        let Some(view) = view else {
+            send_notification(
+                "Text can be selected in terminals and external editors.".to_string(),
+                NotificationType::Info,
+            );
+            return;
+        };
+        view.start_selection();
+        send_notification(
+            "Selecting: arrows extend, Enter copies, Esc cancels.".to_string(),
+            NotificationType::Info,
+        );
+    }
+
+    /// Splits the active terminal pane, starting the new shell in the same
+    /// directory. Opens a terminal if there is none.
+    pub fn split_terminal(&mut self, direction: Direction) {
+        let Some(tab) = self.terminals.get_mut(self.active_terminal_tab) else {
+            self.open_new_terminal();
+            return;
+        };
+        match Term::new(tab.content.active().current_dir(), &self.config) {
+            Ok(term) => {
+                tab.content.split(direction, term);
+                self.show_panel = true;
+                self.active_target = ActiveTarget::Panel;
+            }
+            Err(e) => send_notification(
+                format!("Failed to open terminal: {}", e),
+                NotificationType::Error,
+            ),
+        }
+    }
+
+    /// Closes the focused terminal pane, and its tab with the last one.
+    pub fn close_terminal_pane(&mut self) {
+        let Some(tab) = self.terminals.get_mut(self.active_terminal_tab) else {
+            return;
+        };
+        tab.content.close_active();
+        if tab.content.is_empty() {
+            self.terminals.remove(self.active_terminal_tab);
+            if self.terminals.is_empty() {
+                self.show_panel = false;
+                self.active_target = ActiveTarget::Editor;
+            } else if self.active_terminal_tab >= self.terminals.len() {
+                self.active_terminal_tab = self.terminals.len() - 1;
+            }
+        }
+    }
+
+    /// Focuses the terminal pane next to the focused one on `side`.
+    pub fn focus_terminal_pane(&mut self, side: Side) {
+        if let Some(panes) = self.active_panes() {
+            panes.focus(side);
+        }
+    }
+
+    /// Moves the divider next to the focused terminal pane towards `side`.
+    pub fn resize_terminal_pane(&mut self, side: Side) {
+        if let Some(panes) = self.active_panes() {
+            panes.resize(side);
+        }
+    }
+
+    /// Shows the focused terminal pane over the whole panel, or all panes again.
+    pub fn toggle_maximized_pane(&mut self) {
+        if let Some(panes) = self.active_panes() {
+            panes.toggle_maximized();
+        }
+    }
+
+    /// The panes of the active terminal tab, if the panel is showing.
+    pub fn active_panes(&mut self) -> Option<&mut TermPanes> {
+        if !self.show_panel {
+            return None;
+        }
+        self.terminals
+            .get_mut(self.active_terminal_tab)
+            .map(|tab| &mut tab.content)
+    }
+
+    /// Rebuilds the file walker from `config.files` and hands it to every file list.
+    pub fn apply_files_config(&mut self) {
+        let walker = WorkspaceWalker::new(self.workspace.clone(), &self.config.files);
+        for tab in &mut self.primary_sidebar_components {
+            tab.content.set_walker(walker.clone());
+        }
+        self.command_palette.set_walker(walker);
+    }
+
+    /// Shows or hides dotfiles in the file view, search and quick open.
+    pub fn toggle_hidden_files(&mut self) {
+        self.config.files.show_hidden = !self.config.files.show_hidden;
+        self.apply_files_config();
+        let state = if self.config.files.show_hidden {
+            "shown"
+        } else {
+            "hidden"
+        };
+        send_notification(
+            format!("Hidden files are {}", state),
+            NotificationType::Info,
+        );
+    }
+
+    /// Shows or hides files matched by ignore files.
+    pub fn toggle_ignored_files(&mut self) {
+        self.config.files.show_ignored = !self.config.files.show_ignored;
+        self.apply_files_config();
+        let state = if self.config.files.show_ignored {
+            "shown"
+        } else {
+            "hidden"
+        };
+        send_notification(
+            format!("Ignored files are {}", state),
+            NotificationType::Info,
+        );
+    }
+
+    /// Expands the file tree to the file of the active editor tab and selects it.
+    pub fn reveal_active_file(&mut self) {
+        let Some(path) = self
+            .main_tabs
+            .get(self.active_main_tab)
+            .and_then(|tab| tab.content.file_path())
+            .map(Path::to_path_buf)
+        else {
+            send_notification(
+                "The active tab has no file to reveal".to_string(),
+                NotificationType::Warning,
+            );
+            return;
+        };
+        let path = path.canonicalize().unwrap_or(path);
+
+        let Some(index) = self
+            .primary_sidebar_components
+            .iter()
+            .position(|tab| matches!(tab.content, PrimarySidebarComponent::FileView(_)))
+        else {
+            return;
+        };
+        if let PrimarySidebarComponent::FileView(fv) =
+            &mut self.primary_sidebar_components[index].content
+        {
+            if !fv.reveal(&path) {
+                // Outside the current root: re-root at the workspace and try again.
+                fv.set_root(self.workspace.clone());
+                if !fv.reveal(&path) {
+                    send_notification(
+                        format!("{} is outside the workspace", path.display()),
+                        NotificationType::Warning,
+                    );
+                    return;
+                }
+            }
+        }
+        self.active_primary_sidebar_tab = index;
+        self.show_primary_sidebar = true;
+        self.active_target = ActiveTarget::PrimarySideBar;
+    }
+
     pub fn execute_command_palette_action(&mut self) {
         if let Some(action) = self.command_palette.get_selected_action() {
             action(self);
@@ -245,45 +937,263 @@ impl App {
 
     pub fn poll_file_watcher(&mut self) {
         // Assuming FileView is the first tab, but this could be more robust
-        if let Some(tab) = self.primary_sidebar_components.get_mut(0) {
-            // NOTE: The `if` condition was removed because `poll_file_changes` returns `()` not `bool`.
-            // Ideally, `poll_file_changes` should be modified to return `true` if changes were detected.
-            tab.content.poll_file_changes();
-            self.set_command_palette_file_view_changed();
+        let Some(tab) = self.primary_sidebar_components.get_mut(0) else {
+            return;
+        };
+        let changed = tab.content.poll_file_changes();
+        if changed.is_empty() {
+            return;
+        }
+        self.set_command_palette_file_view_changed();
+        for tab in &mut self.primary_sidebar_components {
+            if let PrimarySidebarComponent::Git(git) = &mut tab.content {
+                git.files_changed(&changed);
+            }
         }
     }
+
+    /// The Git summary shown in the bottom bar, from the sidebar's cached status.
+    pub fn git_summary(&self) -> Option<String> {
+        self.primary_sidebar_components
+            .iter()
+            .find_map(|tab| match &tab.content {
+                PrimarySidebarComponent::Git(git) => git.status_summary(),
+                _ => None,
+            })
+    }
     fn set_command_palette_file_view_changed(&mut self) {
         self.command_palette.set_file_view_changed();
     }
 
+    /// Captures the current tabs and layout.
+    pub fn capture_session(&self) -> Session {
+        let mut tabs = Vec::new();
+        let mut active_main_tab = 0;
+        for (i, tab) in self.main_tabs.iter().enumerate() {
+            let entry = match &tab.content {
+                MainWidgetContent::Editor(editor) => editor.path().map(|path| SessionTab::Editor {
+                    path: path.to_path_buf(),
+                    builtin: false,
+                    line: None,
+                    column: None,
+                }),
+                MainWidgetContent::TextEditor(editor) => editor.path().map(|path| {
+                    let (line, column) = editor.cursor_position();
+                    SessionTab::Editor {
+                        path: path.to_path_buf(),
+                        builtin: true,
+                        line: Some(line),
+                        column: Some(column),
+                    }
+                }),
+                MainWidgetContent::SettingsEditor(_) => Some(SessionTab::Settings),
+                MainWidgetContent::GitLog(_)
+                | MainWidgetContent::Conflicts(_)
+                | MainWidgetContent::Welcome(_) => None,
+            };
+            if let Some(entry) = entry {
+                if i == self.active_main_tab {
+                    active_main_tab = tabs.len();
+                }
+                tabs.push(entry);
+            }
+        }
+
+        Session {
+            active_main_tab,
+            active_terminal_tab: self.active_terminal_tab,
+            active_primary_sidebar_tab: self.active_primary_sidebar_tab,
+            show_primary_sidebar: self.show_primary_sidebar,
+            show_secondary_sidebar: self.show_secondary_sidebar,
+            show_panel: self.show_panel,
+            tabs,
+            terminals: self
+                .terminals
+                .iter()
+                .map(|tab| SessionTerminal {
+                    title: tab.title.clone(),
+                    cwd: tab.content.active().current_dir(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Saves the session for this workspace if sessions are enabled.
+    pub fn save_session(&self) {
+        if !self.session_enabled {
+            return;
+        }
+        if let Err(e) = self.capture_session().save(&self.workspace) {
+            send_notification(
+                format!("Failed to save session: {}", e),
+                NotificationType::Error,
+            );
+        }
+    }
+
+    /// Reopens the tabs, terminals and layout saved for this workspace.
+    pub fn restore_session(&mut self) {
+        let session = match Session::load(&self.workspace) {
+            Ok(Some(session)) => session,
+            Ok(None) => return,
+            Err(e) => {
+                send_notification(
+                    format!("Failed to load session: {}", e),
+                    NotificationType::Error,
+                );
+                return;
+            }
+        };
+
+        for tab in &session.tabs {
+            match tab {
+                SessionTab::Editor {
+                    path,
+                    builtin,
+                    line,
+                    column,
+                } => {
+                    let already_open = self
+                        .main_tabs
+                        .iter()
+                        .any(|t| t.content.file_path() == Some(path.as_path()));
+                    if already_open || !path.exists() {
+                        continue;
+                    }
+                    let position = line.map(|line| (line, column.unwrap_or(1)));
+                    self.open_editor_of_kind(path, position, *builtin);
+                }
+                SessionTab::Settings => self.add_settings_tab(),
+            }
+        }
+
+        if self.terminals.is_empty() {
+            for terminal in &session.terminals {
+                self.open_terminal_in(terminal.cwd.clone().filter(|cwd| cwd.is_dir()));
+                if let Some(tab) = self.terminals.last_mut() {
+                    tab.title = terminal.title.clone();
+                }
+            }
+        }
+
+        if session.active_main_tab < self.main_tabs.len() {
+            self.active_main_tab = session.active_main_tab;
+        }
+        if session.active_terminal_tab < self.terminals.len() {
+            self.active_terminal_tab = session.active_terminal_tab;
+        }
+        if session.active_primary_sidebar_tab < self.primary_sidebar_components.len() {
+            self.active_primary_sidebar_tab = session.active_primary_sidebar_tab;
+        }
+        self.show_primary_sidebar = session.show_primary_sidebar;
+        self.show_secondary_sidebar = session.show_secondary_sidebar;
+        self.show_panel = session.show_panel && !self.terminals.is_empty();
+        self.active_target = ActiveTarget::Editor;
+    }
+
+    /// Deletes the saved session and stops saving it for the rest of this run.
+    pub fn clear_session(&mut self) {
+        match Session::clear(&self.workspace) {
+            Ok(()) => {
+                self.session_enabled = false;
+                send_notification(
+                    "Session cleared; it will not be saved on exit.".to_string(),
+                    NotificationType::Info,
+                );
+            }
+            Err(e) => send_notification(
+                format!("Failed to clear session: {}", e),
+                NotificationType::Error,
+            ),
+        }
+    }
+
     /// Runs on every iteration of the main loop.
     /// Used for polling, background tasks, and state updates.
     pub fn tick(&mut self) {
         self.check_for_exited_terminals();
+        self.apply_moved_paths();
+        for tab in &mut self.primary_sidebar_components {
+            tab.content.poll_background();
+        }
+        for tab in &mut self.main_tabs {
+            match &mut tab.content {
+                MainWidgetContent::TextEditor(editor) => editor.poll_disk(),
+                MainWidgetContent::GitLog(log) => log.poll(),
+                _ => {}
+            }
+        }
+        if let Some(MainWidgetContent::TextEditor(editor)) = self
+            .main_tabs
+            .get_mut(self.active_main_tab)
+            .map(|t| &mut t.content)
+        {
+            editor.poll_git();
+        }
+    }
+
+    /// Points editor tabs at their new location after files were renamed or moved
+    /// from the file view.
+    fn apply_moved_paths(&mut self) {
+        let mut moved = Vec::new();
+        for tab in &mut self.primary_sidebar_components {
+            if let PrimarySidebarComponent::FileView(fv) = &mut tab.content {
+                moved.extend(fv.take_moved_paths());
+            }
+        }
+        for (from, to) in moved {
+            for tab in &mut self.main_tabs {
+                let Some(new_path) = tab
+                    .content
+                    .file_path()
+                    .and_then(|path| path.strip_prefix(&from).ok())
+                    .map(|rel| {
+                        if rel.as_os_str().is_empty() {
+                            to.clone()
+                        } else {
+                            to.join(rel)
+                        }
+                    })
+                else {
+                    continue;
+                };
+                if let Some(name) = new_path.file_name() {
+                    tab.title = name.to_string_lossy().to_string();
+                }
+                match &mut tab.content {
+                    MainWidgetContent::TextEditor(editor) => editor.set_path(new_path),
+                    MainWidgetContent::Editor(editor) => {
+                        send_notification(
+                            format!(
+                                "{} was moved; reopen it to save to the new location",
+                                new_path.display()
+                            ),
+                            NotificationType::Warning,
+                        );
+                        editor.set_path(new_path);
+                    }
+                    _ => {}
+                }
+            }
+        }
     }
 
-    /// Checks if any terminal processes have exited and removes their tabs.
+    /// Checks if any terminal processes have exited and removes their panes, and
+    /// the tabs left without any.
     fn check_for_exited_terminals(&mut self) {
         if self.terminals.is_empty() {
             return;
         }
 
-        let mut exited_indices = Vec::new();
-        for (i, term_tab) in self.terminals.iter_mut().enumerate() {
-            // NOTE: This assumes that `Term` has a method `is_running(&mut self) -> bool`
-            // which returns `false` if the child process has exited.
-            if term_tab.content.is_dead() {
-                exited_indices.push(i);
-            }
-        }
-
-        // Remove tabs in reverse order to avoid index shifting issues.
-        for &i in exited_indices.iter().rev() {
-            self.terminals.remove(i);
+        let mut exited = 0;
+        for term_tab in &mut self.terminals {
+            exited += term_tab.content.remove_dead();
         }
+        self.terminals
+            .retain(|term_tab| !term_tab.content.is_empty());
 
         // If any tabs were closed (because their process exited), update the state.
-        if !exited_indices.is_empty() {
+        if exited > 0 {
             if self.terminals.is_empty() {
                 // If no terminals are left, hide the panel and switch focus to the editor.
                 self.show_panel = false;
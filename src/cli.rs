@@ -0,0 +1,125 @@
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// Command-line arguments.
+#[derive(Parser, Debug, Default)]
+#[command(name = "inf-edit", version, about)]
+pub struct Cli {
+    /// Files to open, optionally suffixed with `:line` or `:line:column`, and at most
+    /// one directory to use as the workspace root.
+    #[arg(value_name = "PATH")]
+    pub paths: Vec<String>,
+
+    /// Use this settings file instead of the default one.
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Override the color theme preset (e.g. `dracula`, `nord`).
+    #[arg(long, value_name = "PRESET")]
+    pub theme: Option<String>,
+
+    /// Start with the primary sidebar hidden.
+    #[arg(long)]
+    pub no_sidebar: bool,
+
+    /// Neither restore nor save the workspace session.
+    #[arg(long)]
+    pub no_session: bool,
+}
+
+/// A file to open at startup, with an optional 1-based position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileLocation {
+    pub path: PathBuf,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+impl FileLocation {
+    /// Parses `path`, `path:line` or `path:line:column`. A path that exists as
+    /// written is never split, so file names containing `:` still work.
+    pub fn parse(arg: &str) -> Self {
+        let whole = Self {
+            path: PathBuf::from(arg),
+            line: None,
+            column: None,
+        };
+        if Path::new(arg).exists() {
+            return whole;
+        }
+
+        let mut parts = arg.rsplitn(3, ':');
+        let last = parts.next().and_then(|s| s.parse::<usize>().ok());
+        let middle = parts.next();
+        match (last, middle.map(|s| s.parse::<usize>().ok()), parts.next()) {
+            (Some(column), Some(Some(line)), Some(path)) if !path.is_empty() => Self {
+                path: PathBuf::from(path),
+                line: Some(line),
+                column: Some(column),
+            },
+            (Some(line), Some(_), _) => {
+                // `path:line`, where the path itself may contain a `:`.
+                let (path, _) = arg.rsplit_once(':').unwrap_or((arg, ""));
+                Self {
+                    path: PathBuf::from(path),
+                    line: Some(line),
+                    column: None,
+                }
+            }
+            _ => whole,
+        }
+    }
+
+    pub fn position(&self) -> Option<(usize, usize)> {
+        self.line.map(|line| (line, self.column.unwrap_or(1)))
+    }
+}
+
+/// Startup state derived from [`Cli`], with every path made absolute.
+#[derive(Debug, Default)]
+pub struct StartupOptions {
+    pub workspace: Option<PathBuf>,
+    pub files: Vec<FileLocation>,
+    pub config_path: Option<PathBuf>,
+    pub theme: Option<String>,
+    pub show_sidebar: bool,
+    pub use_session: bool,
+}
+
+impl Cli {
+    /// Resolves paths against the current directory and splits them into the
+    /// workspace root and files to open.
+    pub fn startup_options(&self) -> Result<StartupOptions> {
+        let cwd = env::current_dir()?;
+        let mut workspace = None;
+        let mut files = Vec::new();
+
+        for arg in &self.paths {
+            let mut location = FileLocation::parse(arg);
+            location.path = cwd.join(&location.path);
+            if location.path.is_dir() {
+                if workspace.is_none() {
+                    workspace = Some(location.path.canonicalize()?);
+                } else {
+                    return Err(anyhow!(
+                        "Only one directory can be opened as the workspace: {}",
+                        arg
+                    ));
+                }
+            } else {
+                files.push(location);
+            }
+        }
+
+        Ok(StartupOptions {
+            workspace,
+            files,
+            config_path: self.config.as_ref().map(|p| cwd.join(p)),
+            theme: self.theme.clone(),
+            show_sidebar: !self.no_sidebar,
+            use_session: !self.no_session,
+        })
+    }
+}
@@ -0,0 +1,104 @@
+//! Copying text to the system clipboard.
+
+use crate::command_line::{CommandLine, TemplateContext};
+use crate::components::notification::{send_notification, NotificationType};
+use crate::settings::Config;
+use anyhow::{anyhow, Result};
+use std::io::{self, Write};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Puts `text` on the clipboard with `clipboard_command` if one is configured, and
+/// otherwise asks the hosting terminal to do it with an OSC 52 escape sequence.
+pub fn copy(text: &str, config: &Config) -> Result<()> {
+    match config
+        .clipboard_command
+        .as_deref()
+        .filter(|c| !c.trim().is_empty())
+    {
+        Some(template) => copy_with_command(text, template),
+        None => copy_with_osc52(text),
+    }
+}
+
+/// Starts the command with `text` on its standard input. It is fed and waited
+/// for in the background, so a command that never exits cannot hang the editor;
+/// failures from then on are reported as notifications.
+fn copy_with_command(text: &str, template: &str) -> Result<()> {
+    let command = CommandLine::parse(template, &TemplateContext::default())?;
+    let child = Command::new(&command.program)
+        .args(&command.args)
+        .envs(command.env.iter().map(|(k, v)| (k, v)))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| anyhow!("Failed to run `{}`: {}", command.program, e))?;
+    let text = text.to_string();
+    thread::spawn(move || {
+        if let Err(e) = feed(child, &text, &command.program) {
+            send_notification(format!("Copy failed: {}", e), NotificationType::Error);
+        }
+    });
+    Ok(())
+}
+
+/// Writes `text` to the standard input of `child` and waits for it to exit.
+fn feed(mut child: Child, text: &str, program: &str) -> Result<()> {
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(anyhow!("`{}` exited with {}", program, status));
+    }
+    Ok(())
+}
+
+fn copy_with_osc52(text: &str) -> Result<()> {
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+    stdout.flush()?;
+    Ok(())
+}
+
+fn base64(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_matches_rfc_4648_vectors() {
+        let vectors = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (input, encoded) in vectors {
+            assert_eq!(base64(input.as_bytes()), encoded, "encoding {:?}", input);
+        }
+    }
+}
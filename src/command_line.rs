@@ -0,0 +1,227 @@
+use crate::settings::Config;
+use anyhow::{anyhow, Result};
+use portable_pty::CommandBuilder;
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// Values substituted into `{...}` placeholders of a configured command line.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TemplateContext<'a> {
+    pub file: Option<&'a Path>,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub workspace: Option<&'a Path>,
+}
+
+/// A program invocation parsed from a command line such as
+/// `EDITOR_MODE=tui nvim +{line} {file}` in `settings.toml`.
+#[derive(Debug, Clone)]
+pub struct CommandLine {
+    pub program: String,
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+    pub cwd: Option<PathBuf>,
+}
+
+impl CommandLine {
+    /// Parses a command line template.
+    ///
+    /// Leading `NAME=value` words become environment variables, `$VAR`/`${VAR}` and a
+    /// leading `~` are expanded, and `{file}`, `{line}`, `{column}`, `{dir}` and
+    /// `{workspace}` are replaced from `ctx`. When a file is given but the template
+    /// has no `{file}` placeholder, the file is appended as the last argument,
+    /// preceded by `+{line}` if a line is given and the template has no `{line}`.
+    pub fn parse(template: &str, ctx: &TemplateContext) -> Result<Self> {
+        let words = shell_words::split(template)
+            .map_err(|e| anyhow!("Invalid command line `{}`: {}", template, e))?;
+        let mut words = words.into_iter().peekable();
+
+        let mut env_vars = Vec::new();
+        while let Some((name, value)) = words.peek().and_then(|w| split_assignment(w)) {
+            env_vars.push((name, expand(&value, ctx)));
+            words.next();
+        }
+
+        let program = words
+            .next()
+            .map(|w| expand(&w, ctx))
+            .ok_or_else(|| anyhow!("Command line `{}` has no program", template))?;
+        // Without a file, words that refer to it are dropped rather than passed empty.
+        let mut args: Vec<String> = words
+            .filter(|w| ctx.file.is_some() || !w.contains("{file}"))
+            .map(|w| expand(&w, ctx))
+            .collect();
+        if let Some(file) = ctx.file {
+            if !template.contains("{file}") {
+                // `+N` before the file is understood by vi, Emacs, nano and most others.
+                if let Some(line) = ctx.line.filter(|_| !template.contains("{line}")) {
+                    args.push(format!("+{}", line));
+                }
+                args.push(file.to_string_lossy().to_string());
+            }
+        }
+
+        Ok(Self {
+            program,
+            args,
+            env: env_vars,
+            cwd: None,
+        })
+    }
+
+    /// Resolves the editor command: `Config.editor`, then `$VISUAL`/`$EDITOR`, then `vi`.
+    pub fn editor(config: &Config, ctx: &TemplateContext) -> Result<Self> {
+        let template = non_empty(Some(config.editor.as_str()))
+            .map(str::to_string)
+            .or_else(|| env::var("VISUAL").ok().filter(|v| !v.trim().is_empty()))
+            .or_else(|| env::var("EDITOR").ok().filter(|v| !v.trim().is_empty()))
+            .unwrap_or_else(|| "vi".to_string());
+        let mut command = Self::parse(&template, ctx)?;
+        command.cwd = match non_empty(config.editor_cwd.as_deref()) {
+            Some(cwd) => Some(PathBuf::from(expand(cwd, ctx))),
+            None => ctx.workspace.map(Path::to_path_buf),
+        };
+        Ok(command)
+    }
+
+    /// Resolves the terminal shell: `Config.terminal`, then `$SHELL`, then `/bin/sh`.
+    pub fn shell(config: &Config, ctx: &TemplateContext) -> Result<Self> {
+        let template = non_empty(config.terminal.as_deref())
+            .map(str::to_string)
+            .or_else(|| env::var("SHELL").ok().filter(|v| !v.trim().is_empty()))
+            .unwrap_or_else(|| "/bin/sh".to_string());
+        let mut command = Self::parse(&template, ctx)?;
+        command.cwd = match non_empty(config.terminal_cwd.as_deref()) {
+            Some(cwd) => Some(PathBuf::from(expand(cwd, ctx))),
+            None => ctx.workspace.map(Path::to_path_buf),
+        };
+        Ok(command)
+    }
+
+    /// Checks that the program can be found, so that a missing command is reported
+    /// with a readable message instead of a bare spawn error.
+    pub fn ensure_program_exists(&self) -> Result<()> {
+        let program = Path::new(&self.program);
+        let found = if program.components().count() > 1 {
+            program.is_file()
+        } else {
+            env::var_os("PATH")
+                .map(|paths| env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+                .unwrap_or(false)
+        };
+        if found {
+            Ok(())
+        } else {
+            Err(anyhow!("Command not found: {}", self.program))
+        }
+    }
+
+    pub fn to_command_builder(&self) -> CommandBuilder {
+        let mut cmd = CommandBuilder::new(&self.program);
+        cmd.args(&self.args);
+        for (name, value) in &self.env {
+            cmd.env(name, value);
+        }
+        if let Some(cwd) = &self.cwd {
+            cmd.cwd(cwd);
+        }
+        cmd
+    }
+}
+
+fn non_empty(s: Option<&str>) -> Option<&str> {
+    s.filter(|s| !s.trim().is_empty())
+}
+
+/// Splits a `NAME=value` word into its parts if `NAME` is a valid variable name.
+fn split_assignment(word: &str) -> Option<(String, String)> {
+    let (name, value) = word.split_once('=')?;
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    valid.then(|| (name.to_string(), value.to_string()))
+}
+
+/// Expands `~`, environment variables and `{...}` placeholders in a single word.
+fn expand(word: &str, ctx: &TemplateContext) -> String {
+    let word = match word.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => match env::var("HOME") {
+            Ok(home) => format!("{}{}", home, rest),
+            Err(_) => word.to_string(),
+        },
+        _ => word.to_string(),
+    };
+
+    let mut out = String::with_capacity(word.len());
+    let mut chars = word.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '$' => {
+                let braced = chars.peek() == Some(&'{');
+                if braced {
+                    chars.next();
+                }
+                let mut name = String::new();
+                while let Some(&n) = chars.peek() {
+                    if n.is_ascii_alphanumeric() || n == '_' {
+                        name.push(n);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                if braced && chars.peek() == Some(&'}') {
+                    chars.next();
+                }
+                if name.is_empty() {
+                    out.push('$');
+                } else {
+                    out.push_str(&env::var(&name).unwrap_or_default());
+                }
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                let mut closed = false;
+                for n in chars.by_ref() {
+                    if n == '}' {
+                        closed = true;
+                        break;
+                    }
+                    placeholder.push(n);
+                }
+                match (closed, placeholder_value(&placeholder, ctx)) {
+                    (true, Some(value)) => out.push_str(&value),
+                    _ => {
+                        out.push('{');
+                        out.push_str(&placeholder);
+                        if closed {
+                            out.push('}');
+                        }
+                    }
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn placeholder_value(name: &str, ctx: &TemplateContext) -> Option<String> {
+    let path_string = |p: &Path| p.to_string_lossy().to_string();
+    match name {
+        "file" => Some(ctx.file.map(path_string).unwrap_or_default()),
+        "line" => Some(ctx.line.unwrap_or(1).to_string()),
+        "column" => Some(ctx.column.unwrap_or(1).to_string()),
+        "dir" => Some(
+            ctx.file
+                .and_then(Path::parent)
+                .or(ctx.workspace)
+                .map(path_string)
+                .unwrap_or_default(),
+        ),
+        "workspace" => Some(ctx.workspace.map(path_string).unwrap_or_default()),
+        _ => None,
+    }
+}
@@ -1,8 +1,10 @@
 pub mod bottom_bar;
+pub mod diff;
 pub mod main_widget;
 pub mod notification;
 pub mod panel;
 pub mod popup;
 pub mod primary_sidebar;
+pub mod pty_screen;
 pub mod secondary_sidebar;
 pub mod top_bar;
@@ -1,3 +1,6 @@
+pub mod item;
+
+use self::item::{BottomBarItem, GitInfoItem};
 use crate::app::App;
 use ratatui::{
     prelude::*,
@@ -17,6 +20,23 @@ impl BottomBar {
         Self {}
     }
 
+    /// Draws the status on the left and the Git summary on the right.
+    pub fn render(&self, f: &mut Frame, area: Rect, app: &App) {
+        f.render_widget(self.get_status_widget(app), area);
+        let summary = app.git_summary();
+        let width = summary
+            .as_deref()
+            .map_or(0, |s| s.chars().count() as u16 + 1);
+        let git_area = Rect {
+            x: area.right().saturating_sub(width),
+            width: width.min(area.width),
+            ..area
+        };
+        if summary.is_some() {
+            GitInfoItem::new(summary).render_item(f, git_area, &app.theme);
+        }
+    }
+
     pub fn get_status_widget<'a>(&self, app: &'a App) -> impl Widget + 'a {
         let status_text = format!("Active: {:?}", app.active_target);
         Paragraph::new(status_text)
@@ -1,11 +1,11 @@
+use crate::theme::Theme;
+use chrono::{DateTime, Local};
 use ratatui::{
     prelude::*, // Keep for Span::styled
     text::Line,
     widgets::Paragraph,
 };
-use crate::theme::Theme;
-use chrono::{DateTime, Local};
-use std::{process::Command, time::SystemTime};
+use std::time::SystemTime;
 
 pub trait BottomBarItem {
     fn render_item(&self, f: &mut Frame, area: Rect, theme: &Theme);
@@ -30,56 +30,28 @@ impl BottomBarItem for TextItem {
     }
 }
 
-/// Renders Git status information.
+/// Renders Git status information from the status cached by the Git sidebar.
 pub struct GitInfoItem {
-    // No fields needed, as it fetches data dynamically
-}
-
-impl Default for GitInfoItem {
-    fn default() -> Self {
-        Self::new()
-    }
+    summary: Option<String>,
 }
 
 impl GitInfoItem {
-    pub fn new() -> Self {
-        Self {}
-    }
-
-    fn get_git_info(&self) -> String {
-        let output = Command::new("git")
-            .args(["status", "--porcelain", "--branch"])
-            .output();
-
-        match output {
-            Ok(output) if output.status.success() => {
-                let s = String::from_utf8_lossy(&output.stdout);
-                let lines: Vec<&str> = s.lines().collect();
-                let branch_line = lines
-                    .iter()
-                    .find(|line| line.starts_with("##"))
-                    .unwrap_or(&"");
-                let branch = branch_line.split(" ").nth(1).unwrap_or("No branch info");
-                let changes = lines.len().saturating_sub(1);
-                format!(
-                    "Git: {} ({})",
-                    branch,
-                    if changes > 0 {
-                        format!("{} changes", changes)
-                    } else {
-                        "clean".into()
-                    }
-                )
-            }
-            _ => "Git: Not a git repository or git not found".to_string(),
-        }
+    pub fn new(summary: Option<String>) -> Self {
+        Self { summary }
     }
 }
 
 impl BottomBarItem for GitInfoItem {
     fn render_item(&self, f: &mut Frame, area: Rect, theme: &Theme) {
-        let text = self.get_git_info();
-        let paragraph = Paragraph::new(Line::from(Span::styled(text, Style::default().fg(theme.highlight_fg)))).alignment(Alignment::Left);
+        let text = self
+            .summary
+            .as_deref()
+            .unwrap_or("Git: Not a git repository");
+        let paragraph = Paragraph::new(Line::from(Span::styled(
+            text,
+            Style::default().fg(theme.highlight_fg),
+        )))
+        .alignment(Alignment::Right);
         f.render_widget(paragraph, area);
     }
 }
@@ -94,7 +66,9 @@ impl Default for CurrentTimeItem {
 }
 
 impl CurrentTimeItem {
-    pub fn new() -> Self { Self {} }
+    pub fn new() -> Self {
+        Self {}
+    }
     fn get_current_time(&self) -> String {
         let now: DateTime<Local> = SystemTime::now().into();
         now.format("Time: %Y-%m-%d %H:%M:%S").to_string()
@@ -104,7 +78,11 @@ impl CurrentTimeItem {
 impl BottomBarItem for CurrentTimeItem {
     fn render_item(&self, f: &mut Frame, area: Rect, theme: &Theme) {
         let text = self.get_current_time();
-        let paragraph = Paragraph::new(Line::from(Span::styled(text, Style::default().fg(theme.text_fg)))).alignment(Alignment::Center);
+        let paragraph = Paragraph::new(Line::from(Span::styled(
+            text,
+            Style::default().fg(theme.text_fg),
+        )))
+        .alignment(Alignment::Center);
         f.render_widget(paragraph, area);
     }
 }
@@ -119,7 +97,9 @@ impl Default for ResourceUsageItem {
 }
 
 impl ResourceUsageItem {
-    pub fn new() -> Self { Self {} }
+    pub fn new() -> Self {
+        Self {}
+    }
     fn get_resource_usage(&self) -> String {
         "CPU: 25% Mem: 60%".to_string() // Placeholder
     }
@@ -128,7 +108,11 @@ impl ResourceUsageItem {
 impl BottomBarItem for ResourceUsageItem {
     fn render_item(&self, f: &mut Frame, area: Rect, theme: &Theme) {
         let text = self.get_resource_usage();
-        let paragraph = Paragraph::new(Line::from(Span::styled(text, Style::default().fg(theme.text_fg)))).alignment(Alignment::Right);
+        let paragraph = Paragraph::new(Line::from(Span::styled(
+            text,
+            Style::default().fg(theme.text_fg),
+        )))
+        .alignment(Alignment::Right);
         f.render_widget(paragraph, area);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,37 @@
+//! Rendering of git patches, shared by the Git sidebar and the log view.
+
+use git2::{Diff, DiffFormat};
+use ratatui::prelude::*;
+
+/// Style and prefix for one patch line, based on its origin.
+pub fn line_style(origin: char) -> (Style, &'static str) {
+    match origin {
+        '+' => (Style::default().fg(Color::Green), "+"),
+        '-' => (Style::default().fg(Color::Red), "-"),
+        ' ' => (Style::default().fg(Color::DarkGray), " "),
+        _ => (Style::default(), " "), // File headers, etc.
+    }
+}
+
+/// Formats one line of patch text the way every diff pane shows it.
+pub fn styled_line(origin: char, text: &str) -> Line<'static> {
+    let (style, prefix) = line_style(origin);
+    Line::from(vec![
+        Span::styled(prefix, style),
+        Span::raw(" "),
+        Span::styled(text.to_string(), style),
+    ])
+}
+
+/// The whole patch of `diff` as colored lines.
+pub fn patch_lines(diff: &Diff) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    let _ = diff.print(DiffFormat::Patch, |_delta, _hunk, line| {
+        // File headers arrive as one multi-line chunk.
+        for text in String::from_utf8_lossy(line.content()).lines() {
+            lines.push(styled_line(line.origin(), text));
+        }
+        true
+    });
+    lines
+}
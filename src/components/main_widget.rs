@@ -3,8 +3,11 @@ use ratatui::{
     style::{Modifier, Style},
     widgets::{Block, Borders, Tabs},
 };
+pub mod conflict_view;
 pub mod editor;
+pub mod git_log;
 pub mod settings_editor;
+pub mod text_editor;
 pub mod welcome_widget;
 use crate::{app::App, MainWidgetContent};
 
@@ -28,7 +31,16 @@ impl MainWidget {
             .constraints([Constraint::Length(1), Constraint::Min(0)])
             .split(area);
 
-        let tab_titles: Vec<String> = app.main_tabs.iter().map(|t| t.title.clone()).collect();
+        let tab_titles: Vec<String> = app
+            .main_tabs
+            .iter()
+            .map(|t| match &t.content {
+                MainWidgetContent::TextEditor(editor) if editor.is_dirty() => {
+                    format!("{} ●", t.title)
+                }
+                _ => t.title.clone(),
+            })
+            .collect();
         let tabs = Tabs::new(tab_titles)
             .block(Block::default().bg(app.theme.primary_bg))
             .select(app.active_main_tab)
@@ -59,6 +71,27 @@ impl MainWidget {
                         .bg(app.theme.primary_bg);
                     editor.render_with_block(f, content_area, content_block);
                 }
+                MainWidgetContent::TextEditor(editor) => {
+                    let content_block = Block::default()
+                        .borders(Borders::ALL)
+                        .border_style(border_style)
+                        .bg(app.theme.primary_bg);
+                    editor.render_with_block(f, content_area, content_block, &app.theme);
+                }
+                MainWidgetContent::GitLog(log) => {
+                    let content_block = Block::default()
+                        .borders(Borders::ALL)
+                        .border_style(border_style)
+                        .bg(app.theme.primary_bg);
+                    log.render_with_block(f, content_area, content_block, &app.theme);
+                }
+                MainWidgetContent::Conflicts(view) => {
+                    let content_block = Block::default()
+                        .borders(Borders::ALL)
+                        .border_style(border_style)
+                        .bg(app.theme.primary_bg);
+                    view.render_with_block(f, content_area, content_block, &app.theme);
+                }
                 MainWidgetContent::Welcome(welcome_widget) => {
                     welcome_widget.render(f, content_area, &app.theme);
                 }
@@ -0,0 +1,649 @@
+//! Resolving the merge conflicts of one file, block by block.
+
+use crate::components::notification::{send_notification, NotificationType};
+use crate::components::primary_sidebar::git::merge::{self, PullOutcome};
+use crate::file_ops;
+use crate::theme::Theme;
+use crossterm::event::{KeyCode, KeyEvent};
+use git2::{IndexConflict, MergeFileOptions, Repository};
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Paragraph},
+};
+use std::path::{Path, PathBuf};
+
+/// Length of the conflict markers asked of libgit2, long enough that no line of
+/// real content looks like one.
+const MARKER_SIZE: u16 = 32;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Choice {
+    Ours,
+    Theirs,
+    /// Ours followed by theirs.
+    Both,
+    Base,
+}
+
+enum Segment {
+    Clean(Vec<String>),
+    Conflict {
+        ours: Vec<String>,
+        base: Vec<String>,
+        theirs: Vec<String>,
+        choice: Option<Choice>,
+    },
+}
+
+pub struct ConflictView {
+    repo_path: PathBuf,
+    /// Path relative to the repository root, as stored in the index.
+    path: String,
+    segments: Vec<Segment>,
+    /// Indices into `segments` of the conflict blocks.
+    conflicts: Vec<usize>,
+    /// The selected entry of `conflicts`.
+    current: usize,
+    trailing_newline: bool,
+    /// `"\r\n"` for files with Windows line endings, kept when writing the result.
+    line_ending: &'static str,
+    /// The sides without the file, when one side deleted it or both added it.
+    /// Choosing one of them deletes the file.
+    missing: Vec<Choice>,
+    error: Option<String>,
+}
+
+impl ConflictView {
+    /// Loads the conflict of `path` (relative to the root of the repository that
+    /// contains `workspace`).
+    pub fn new(workspace: &Path, path: &str) -> Self {
+        let mut view = Self {
+            repo_path: workspace.to_path_buf(),
+            path: path.to_string(),
+            segments: Vec::new(),
+            conflicts: Vec::new(),
+            current: 0,
+            trailing_newline: true,
+            line_ending: "\n",
+            missing: Vec::new(),
+            error: None,
+        };
+        view.reload();
+        view
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Reads the conflict from the index again, dropping choices made so far.
+    pub fn reload(&mut self) {
+        self.segments.clear();
+        self.missing.clear();
+        self.current = 0;
+        self.error = match Repository::discover(&self.repo_path).and_then(|repo| self.load(&repo)) {
+            Ok(()) => None,
+            Err(e) => Some(e.message().to_string()),
+        };
+        self.conflicts = self
+            .segments
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| matches!(s, Segment::Conflict { .. }))
+            .map(|(i, _)| i)
+            .collect();
+    }
+
+    fn load(&mut self, repo: &Repository) -> Result<(), git2::Error> {
+        let index = repo.index()?;
+        let mut found = None;
+        for conflict in index.conflicts()? {
+            let conflict = conflict?;
+            let entry = [&conflict.our, &conflict.their, &conflict.ancestor]
+                .into_iter()
+                .flatten()
+                .next();
+            if entry.is_some_and(|e| e.path == self.path.as_bytes()) {
+                found = Some(conflict);
+                break;
+            }
+        }
+        let Some(conflict) = found else {
+            return Err(git2::Error::from_str(&format!(
+                "{} has no conflicts",
+                self.path
+            )));
+        };
+
+        if let IndexConflict {
+            ancestor: Some(ancestor),
+            our: Some(ours),
+            their: Some(theirs),
+        } = &conflict
+        {
+            let mut opts = MergeFileOptions::new();
+            opts.style_diff3(true)
+                .our_label("ours")
+                .their_label("theirs")
+                .ancestor_label("base")
+                .marker_size(MARKER_SIZE);
+            for entry in [ancestor, ours, theirs] {
+                self.as_text(repo.find_blob(entry.id)?.content())?;
+            }
+            let merged = repo.merge_file_from_index(ancestor, ours, theirs, Some(&mut opts))?;
+            let text = self.as_text(merged.content())?;
+            self.trailing_newline = text.ends_with('\n') || text.is_empty();
+            self.line_ending = line_ending(text);
+            self.segments = parse_markers(text);
+        } else {
+            // Added on both sides or deleted on one: the whole file is one block.
+            let mut endings = Vec::new();
+            let mut read = |entry: &Option<git2::IndexEntry>| -> Result<Vec<String>, git2::Error> {
+                let Some(entry) = entry else {
+                    return Ok(Vec::new());
+                };
+                let blob = repo.find_blob(entry.id)?;
+                let text = self.as_text(blob.content())?;
+                endings.push(line_ending(text));
+                Ok(text.lines().map(str::to_string).collect())
+            };
+            let (ours, base, theirs) = (
+                read(&conflict.our)?,
+                read(&conflict.ancestor)?,
+                read(&conflict.their)?,
+            );
+            self.line_ending = endings.into_iter().next().unwrap_or("\n");
+            self.missing = [
+                (Choice::Ours, &conflict.our),
+                (Choice::Base, &conflict.ancestor),
+                (Choice::Theirs, &conflict.their),
+            ]
+            .into_iter()
+            .filter(|(_, entry)| entry.is_none())
+            .map(|(side, _)| side)
+            .collect();
+            self.segments = vec![Segment::Conflict {
+                ours,
+                base,
+                theirs,
+                choice: None,
+            }];
+        }
+        Ok(())
+    }
+
+    /// `content` as text, or an error for binary and non-UTF-8 files, which
+    /// cannot be merged line by line here.
+    fn as_text<'a>(&self, content: &'a [u8]) -> Result<&'a str, git2::Error> {
+        let text = std::str::from_utf8(content)
+            .ok()
+            .filter(|t| !t.contains('\0'));
+        text.ok_or_else(|| {
+            git2::Error::from_str(&format!(
+                "{0} is binary or not UTF-8 text. Keep one side with `git checkout --ours -- {0}` \
+                 (or --theirs) in a terminal, then stage it from the Git file list.",
+                self.path
+            ))
+        })
+    }
+
+    fn unresolved(&self) -> usize {
+        self.segments
+            .iter()
+            .filter(|s| matches!(s, Segment::Conflict { choice: None, .. }))
+            .count()
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let count = self.conflicts.len();
+        match key.code {
+            KeyCode::Down | KeyCode::Char('n') if count > 0 => {
+                self.current = (self.current + 1) % count;
+            }
+            KeyCode::Up | KeyCode::Char('p') if count > 0 => {
+                self.current = (self.current + count - 1) % count;
+            }
+            KeyCode::Char('o') => self.choose(Some(Choice::Ours)),
+            KeyCode::Char('t') => self.choose(Some(Choice::Theirs)),
+            KeyCode::Char('b') => self.choose(Some(Choice::Both)),
+            KeyCode::Char('a') => self.choose(Some(Choice::Base)),
+            KeyCode::Char('x') => self.choose(None),
+            KeyCode::Enter => self.mark_resolved(),
+            KeyCode::Char('C') => self.finish(),
+            KeyCode::Char('R') => self.reload(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Picks a side for the selected block and moves on to the next open one.
+    fn choose(&mut self, new_choice: Option<Choice>) {
+        let Some(&index) = self.conflicts.get(self.current) else {
+            return;
+        };
+        if let Segment::Conflict { choice, .. } = &mut self.segments[index] {
+            *choice = new_choice;
+        }
+        if new_choice.is_none() {
+            return;
+        }
+        let count = self.conflicts.len();
+        if let Some(next) = (1..count)
+            .map(|step| (self.current + step) % count)
+            .find(|&i| {
+                matches!(
+                    self.segments[self.conflicts[i]],
+                    Segment::Conflict { choice: None, .. }
+                )
+            })
+        {
+            self.current = next;
+        }
+    }
+
+    /// Whether the chosen result is a side that does not have the file.
+    fn resolves_to_deletion(&self) -> bool {
+        self.segments.iter().any(|s| {
+            matches!(s, Segment::Conflict { choice: Some(choice), .. } if self.missing.contains(choice))
+        })
+    }
+
+    /// Writes the chosen result to the file and stages it, or deletes the file
+    /// if the chosen side deleted it.
+    fn mark_resolved(&mut self) {
+        if self.error.is_some() {
+            return;
+        }
+        let open = self.unresolved();
+        if open > 0 {
+            send_notification(
+                format!("{} conflict(s) in {} still need a choice.", open, self.path),
+                NotificationType::Warning,
+            );
+            return;
+        }
+        let mut text = self.result_lines().join(self.line_ending);
+        if self.trailing_newline && !text.is_empty() {
+            text.push_str(self.line_ending);
+        }
+        let delete = self.resolves_to_deletion();
+        let result = Repository::discover(&self.repo_path)
+            .map_err(anyhow::Error::from)
+            .and_then(|repo| {
+                let workdir = repo
+                    .workdir()
+                    .ok_or_else(|| anyhow::anyhow!("bare repository"))?;
+                let file = workdir.join(&self.path);
+                let mut index = repo.index()?;
+                if delete {
+                    match std::fs::remove_file(&file) {
+                        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                        _ => {}
+                    }
+                    index.remove_path(Path::new(&self.path))?;
+                } else {
+                    file_ops::write_atomic(&file, &text)?;
+                    index.add_path(Path::new(&self.path))?;
+                }
+                index.write()?;
+                Ok(merge::conflicted_paths(&repo)?.len())
+            });
+        match result {
+            Ok(0) => send_notification(
+                format!(
+                    "Resolved {}. No conflicts left; press C to finish.",
+                    self.path
+                ),
+                NotificationType::Info,
+            ),
+            Ok(left) => send_notification(
+                format!("Resolved {}. {} conflicted file(s) left.", self.path, left),
+                NotificationType::Info,
+            ),
+            Err(e) => send_notification(
+                format!("Failed to resolve {}: {}", self.path, e),
+                NotificationType::Error,
+            ),
+        }
+        self.reload();
+    }
+
+    /// Creates the merge commit, or continues the rebase.
+    fn finish(&mut self) {
+        let result =
+            Repository::discover(&self.repo_path).and_then(|repo| merge::finish(&repo, ""));
+        match result {
+            Ok(PullOutcome::Conflicts(count)) => send_notification(
+                format!("Rebase stopped again: {} conflicted file(s).", count),
+                NotificationType::Warning,
+            ),
+            Ok(PullOutcome::Rebased) => {
+                send_notification("Rebase finished.".to_string(), NotificationType::Info)
+            }
+            Ok(_) => send_notification("Merge committed.".to_string(), NotificationType::Info),
+            Err(e) => send_notification(
+                format!("Git Error: Failed to finish: {}", e.message()),
+                NotificationType::Error,
+            ),
+        }
+        self.reload();
+    }
+
+    fn result_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Clean(clean) => lines.extend(clean.iter().cloned()),
+                Segment::Conflict {
+                    ours,
+                    base,
+                    theirs,
+                    choice,
+                } => match choice {
+                    Some(Choice::Ours) => lines.extend(ours.iter().cloned()),
+                    Some(Choice::Theirs) => lines.extend(theirs.iter().cloned()),
+                    Some(Choice::Both) => {
+                        lines.extend(ours.iter().cloned());
+                        lines.extend(theirs.iter().cloned());
+                    }
+                    Some(Choice::Base) => lines.extend(base.iter().cloned()),
+                    None => {}
+                },
+            }
+        }
+        lines
+    }
+
+    pub fn render_with_block(&mut self, f: &mut Frame, area: Rect, block: Block, theme: &Theme) {
+        let title = format!(
+            "Conflicts — {} ({}/{} resolved)",
+            self.path,
+            self.conflicts.len() - self.unresolved(),
+            self.conflicts.len()
+        );
+        let block = block.title(title);
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        if let Some(error) = &self.error {
+            let lines = vec![
+                Line::styled(error.clone(), Style::default().fg(Color::Red)),
+                Line::from("Press R to reload, or C to finish the merge or rebase."),
+            ];
+            f.render_widget(Paragraph::new(lines), inner);
+            return;
+        }
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Percentage(55),
+                Constraint::Min(0),
+                Constraint::Length(1),
+            ])
+            .split(inner);
+
+        // The merged file, with open blocks shown as conflict markers.
+        let marker = Style::default().fg(Color::Yellow);
+        let ours_style = Style::default().fg(Color::Green);
+        let theirs_style = Style::default().fg(Color::Blue);
+        let base_style = Style::default().fg(Color::DarkGray);
+        let current_index = self.conflicts.get(self.current).copied();
+        let mut lines = Vec::new();
+        let mut current_row = 0;
+        for (i, segment) in self.segments.iter().enumerate() {
+            match segment {
+                Segment::Clean(clean) => {
+                    lines.extend(clean.iter().map(|l| Line::raw(l.clone())));
+                }
+                Segment::Conflict {
+                    ours,
+                    base,
+                    theirs,
+                    choice,
+                } => {
+                    let is_current = Some(i) == current_index;
+                    if is_current {
+                        current_row = lines.len();
+                    }
+                    let start = lines.len();
+                    match choice {
+                        None => {
+                            lines.push(Line::styled("<<<<<<< ours", marker));
+                            lines.extend(ours.iter().map(|l| Line::styled(l.clone(), ours_style)));
+                            lines.push(Line::styled("||||||| base", marker));
+                            lines.extend(base.iter().map(|l| Line::styled(l.clone(), base_style)));
+                            lines.push(Line::styled("=======", marker));
+                            lines.extend(
+                                theirs.iter().map(|l| Line::styled(l.clone(), theirs_style)),
+                            );
+                            lines.push(Line::styled(">>>>>>> theirs", marker));
+                        }
+                        Some(choice) => {
+                            let chosen: Vec<&String> = match choice {
+                                Choice::Ours => ours.iter().collect(),
+                                Choice::Theirs => theirs.iter().collect(),
+                                Choice::Both => ours.iter().chain(theirs).collect(),
+                                Choice::Base => base.iter().collect(),
+                            };
+                            lines.extend(chosen.into_iter().map(|l| {
+                                Line::styled(l.clone(), Style::default().bg(theme.secondary_bg))
+                            }));
+                        }
+                    }
+                    if is_current {
+                        for line in &mut lines[start..] {
+                            line.spans.insert(0, Span::styled("▶ ", marker));
+                        }
+                    }
+                }
+            }
+        }
+        let height = chunks[0].height.saturating_sub(2) as usize;
+        let scroll = current_row.saturating_sub(height / 3) as u16;
+        let preview = Paragraph::new(lines)
+            .scroll((scroll, 0))
+            .block(Block::default().title("Result").borders(Borders::ALL));
+        f.render_widget(preview, chunks[0]);
+
+        // The three versions of the selected block.
+        let sides = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Ratio(1, 3); 3])
+            .split(chunks[1]);
+        if let Some(Segment::Conflict {
+            ours,
+            base,
+            theirs,
+            choice,
+        }) = current_index.map(|i| &self.segments[i])
+        {
+            let panes = [
+                ("Ours (o)", ours, ours_style, Choice::Ours),
+                ("Base (a)", base, base_style, Choice::Base),
+                ("Theirs (t)", theirs, theirs_style, Choice::Theirs),
+            ];
+            for (area, (title, text, style, side)) in sides.iter().zip(panes) {
+                let picked = *choice == Some(side)
+                    || (*choice == Some(Choice::Both) && side != Choice::Base);
+                let border = if picked {
+                    Style::default().fg(theme.highlight_fg)
+                } else {
+                    Style::default().fg(theme.text_fg)
+                };
+                let mut title = title.to_string();
+                if self.missing.contains(&side) {
+                    title.push_str(" — deleted");
+                }
+                if picked {
+                    title.push_str(" ✓");
+                }
+                let pane = Paragraph::new(
+                    text.iter()
+                        .map(|l| Line::styled(l.clone(), style))
+                        .collect::<Vec<_>>(),
+                )
+                .block(
+                    Block::default()
+                        .title(title)
+                        .borders(Borders::ALL)
+                        .border_style(border),
+                );
+                f.render_widget(pane, *area);
+            }
+        }
+
+        let help = Paragraph::new(
+            "n/p: next/prev block  o/t/b/a: ours/theirs/both/base  x: undo  Enter: mark resolved  C: finish merge",
+        )
+        .style(Style::default().fg(Color::DarkGray));
+        f.render_widget(help, chunks[2]);
+    }
+}
+
+/// `"\r\n"` if `text` uses Windows line endings, `"\n"` otherwise.
+fn line_ending(text: &str) -> &'static str {
+    if text.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    }
+}
+
+/// Whether `line` is a conflict marker made of `c`, optionally followed by a label.
+fn is_marker(line: &str, c: char) -> bool {
+    let size = MARKER_SIZE as usize;
+    let bytes = line.as_bytes();
+    bytes.len() >= size
+        && bytes[..size].iter().all(|&b| b == c as u8)
+        && matches!(bytes.get(size), None | Some(b' '))
+}
+
+/// Splits diff3-style merge output into clean runs and conflict blocks.
+fn parse_markers(text: &str) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut clean = Vec::new();
+    // While inside a block: which part is being read (ours, base, theirs) and the parts.
+    let mut block: Option<(usize, [Vec<String>; 3])> = None;
+    for line in text.lines() {
+        match &mut block {
+            None if is_marker(line, '<') => {
+                if !clean.is_empty() {
+                    segments.push(Segment::Clean(std::mem::take(&mut clean)));
+                }
+                block = Some((0, Default::default()));
+            }
+            None => clean.push(line.to_string()),
+            Some((part, _)) if is_marker(line, '|') => *part = 1,
+            Some((part, _)) if is_marker(line, '=') => *part = 2,
+            Some(_) if is_marker(line, '>') => {
+                if let Some((_, [ours, base, theirs])) = block.take() {
+                    segments.push(Segment::Conflict {
+                        ours,
+                        base,
+                        theirs,
+                        choice: None,
+                    });
+                }
+            }
+            Some((part, parts)) => parts[*part].push(line.to_string()),
+        }
+    }
+    if !clean.is_empty() {
+        segments.push(Segment::Clean(clean));
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{Oid, Signature};
+    use std::fs;
+    use tempfile::TempDir;
+
+    const FILE: &str = "f.txt";
+
+    /// A repository whose index holds a conflict on `FILE` between `ours` and
+    /// `theirs`, both changed from `base`.
+    fn conflicted(base: &[u8], ours: &[u8], theirs: &[u8]) -> TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let sig = Signature::now("Test", "test@example.com").unwrap();
+        let commit = |content: &[u8], parents: &[Oid]| -> Oid {
+            let mut builder = repo.treebuilder(None).unwrap();
+            builder
+                .insert(FILE, repo.blob(content).unwrap(), 0o100644)
+                .unwrap();
+            let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+            let parents: Vec<_> = parents
+                .iter()
+                .map(|&id| repo.find_commit(id).unwrap())
+                .collect();
+            let parents: Vec<_> = parents.iter().collect();
+            repo.commit(None, &sig, &sig, "c", &tree, &parents).unwrap()
+        };
+        let base = commit(base, &[]);
+        let ours = commit(ours, &[base]);
+        let theirs = commit(theirs, &[base]);
+        repo.reference("refs/heads/main", ours, true, "ours")
+            .unwrap();
+        repo.set_head("refs/heads/main").unwrap();
+        repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+            .unwrap();
+        let theirs = repo.find_annotated_commit(theirs).unwrap();
+        repo.merge(&[&theirs], None, None).unwrap();
+        assert!(repo.index().unwrap().has_conflicts());
+        dir
+    }
+
+    fn resolve(dir: &TempDir, keys: &str) -> ConflictView {
+        let mut view = ConflictView::new(dir.path(), FILE);
+        for c in keys.chars() {
+            view.handle_key(KeyEvent::from(KeyCode::Char(c)));
+        }
+        view.handle_key(KeyEvent::from(KeyCode::Enter));
+        view
+    }
+
+    #[test]
+    fn keeps_windows_line_endings() {
+        let dir = conflicted(
+            b"a\r\nb\r\nc\r\n",
+            b"a\r\nours\r\nc\r\n",
+            b"a\r\ntheirs\r\nc\r\n",
+        );
+        resolve(&dir, "b");
+        let written = fs::read(dir.path().join(FILE)).unwrap();
+        assert_eq!(written, b"a\r\nours\r\ntheirs\r\nc\r\n");
+    }
+
+    #[test]
+    fn content_that_looks_like_a_marker_stays_content() {
+        let dir = conflicted(
+            b"a\n=======\n||||||| x\nb\n",
+            b"a\n=======\n||||||| x\nours\n",
+            b"a\n=======\n||||||| x\ntheirs\n",
+        );
+        assert_eq!(ConflictView::new(dir.path(), FILE).conflicts.len(), 1);
+        resolve(&dir, "t");
+        let written = fs::read_to_string(dir.path().join(FILE)).unwrap();
+        assert_eq!(written, "a\n=======\n||||||| x\ntheirs\n");
+    }
+
+    #[test]
+    fn refuses_binary_conflicts() {
+        let dir = conflicted(b"a\0b", b"a\0ours", b"a\0theirs");
+        let view = resolve(&dir, "o");
+        assert!(view.error.as_deref().is_some_and(|e| e.contains("binary")));
+        let repo = Repository::open(dir.path()).unwrap();
+        assert!(repo.index().unwrap().has_conflicts());
+    }
+
+    #[test]
+    fn refuses_text_that_is_not_utf8() {
+        let dir = conflicted(b"caf\xe9\n", b"caf\xe9 ours\n", b"caf\xe9 theirs\n");
+        let view = ConflictView::new(dir.path(), FILE);
+        assert!(view.error.is_some());
+    }
+}
@@ -1,12 +1,18 @@
 use crate::{
+    command_line::{CommandLine, TemplateContext},
     components::notification::{send_notification, NotificationType},
+    components::pty_screen::{PtyScreen, Selection},
     event_handler::PtyInput,
+    settings::Config,
 };
-use portable_pty::{native_pty_system, CommandBuilder, MasterPty, PtySize};
+use anyhow::{anyhow, Result};
+use portable_pty::{native_pty_system, MasterPty, PtySize};
+use ratatui::layout::Rect;
 use std::env;
 use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::thread;
 use tui_term::vt100::Parser;
 use tui_term::widget::PseudoTerminal;
@@ -16,6 +22,10 @@ pub struct Editor {
     writer: Arc<Mutex<Box<dyn Write + Send>>>,
     dead: Arc<AtomicBool>,
     _pty: Box<dyn MasterPty + Send>, // 保持しておくことでdropされないように
+    path: Option<PathBuf>,
+    /// Where the screen was last drawn, for mouse hit testing.
+    area: Rect,
+    selection: Option<Selection>,
 }
 
 type PtyResources = (
@@ -26,36 +36,28 @@ type PtyResources = (
 );
 
 /// Helper function to initialize a PTY and spawn an editor process.
-fn init_pty(path: Option<std::path::PathBuf>) -> PtyResources {
-    let editor = env::var("EDITOR").unwrap_or_else(|_| "editor".to_string());
+fn init_pty(command: &CommandLine) -> Result<PtyResources> {
+    command.ensure_program_exists()?;
     let pty_system = native_pty_system();
-    let pty_pair = pty_system
-        .openpty(PtySize {
-            rows: 24,
-            cols: 80,
-            pixel_width: 0,
-            pixel_height: 0,
-        })
-        .expect("Failed to open PTY");
+    let pty_pair = pty_system.openpty(PtySize {
+        rows: 24,
+        cols: 80,
+        pixel_width: 0,
+        pixel_height: 0,
+    })?;
 
-    let mut cmd = CommandBuilder::new(editor);
-    if let Some(p) = path {
-        cmd.arg(p);
-    }
     let _child = pty_pair
         .slave
-        .spawn_command(cmd)
-        .expect("Failed to spawn editor");
+        .spawn_command(command.to_command_builder())
+        .map_err(|e| anyhow!("Failed to spawn editor `{}`: {}", command.program, e))?;
 
     let parser = Arc::new(Mutex::new(Parser::new(24, 80, 0)));
-    let writer = Arc::new(Mutex::new(
-        pty_pair.master.take_writer().expect("clone writer"),
-    ));
+    let writer = Arc::new(Mutex::new(pty_pair.master.take_writer()?));
     let dead = Arc::new(AtomicBool::new(false));
 
     // Thread to stream PTY output to the parser
     let parser_clone = Arc::clone(&parser);
-    let mut reader = pty_pair.master.try_clone_reader().expect("clone reader");
+    let mut reader = pty_pair.master.try_clone_reader()?;
     let dead_clone = dead.clone();
     thread::spawn(move || {
         let mut buf = [0u8; 4096];
@@ -68,34 +70,57 @@ fn init_pty(path: Option<std::path::PathBuf>) -> PtyResources {
         }
     });
 
-    (parser, writer, dead, pty_pair.master)
+    Ok((parser, writer, dead, pty_pair.master))
 }
 
-impl Default for Editor {
-    fn default() -> Self {
-        Self::new()
+impl Editor {
+    /// Spawns the configured editor without a file.
+    pub fn new(config: &Config) -> Result<Self> {
+        Self::spawn(config, None, None)
     }
-}
 
-impl Editor {
-    pub fn new() -> Self {
-        let (parser, writer, dead, _pty) = init_pty(None);
-        Self {
-            parser,
-            writer,
-            dead,
-            _pty,
-        }
+    /// Spawns the configured editor for `path`, optionally at a 1-based (line, column).
+    pub fn with_file(
+        config: &Config,
+        path: &Path,
+        position: Option<(usize, usize)>,
+    ) -> Result<Self> {
+        Self::spawn(config, Some(path), position)
     }
 
-    pub fn with_file(path: std::path::PathBuf) -> Self {
-        let (parser, writer, dead, _pty) = init_pty(Some(path));
-        Self {
+    fn spawn(
+        config: &Config,
+        path: Option<&Path>,
+        position: Option<(usize, usize)>,
+    ) -> Result<Self> {
+        let workspace = env::current_dir().ok();
+        let ctx = TemplateContext {
+            file: path,
+            line: position.map(|(line, _)| line),
+            column: position.map(|(_, column)| column),
+            workspace: workspace.as_deref(),
+        };
+        let command = CommandLine::editor(config, &ctx)?;
+        let (parser, writer, dead, _pty) = init_pty(&command)?;
+        Ok(Self {
             parser,
             writer,
             dead,
             _pty,
-        }
+            path: path.map(Path::to_path_buf),
+            area: Rect::default(),
+            selection: None,
+        })
+    }
+
+    /// The file this editor was opened with, if any.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Records that the file was renamed. The editor process itself is not told.
+    pub fn set_path(&mut self, path: PathBuf) {
+        self.path = Some(path);
     }
 
     pub fn render_with_block(
@@ -109,9 +134,13 @@ impl Editor {
         let rows = inner_area.height.max(1); // Ensure at least 1 row
         let cols = inner_area.width.max(1); // Ensure at least 1 col
 
+        self.area = inner_area;
         {
             let mut parser = self.parser.lock().unwrap();
-            parser.set_size(rows, cols);
+            // Resizing forgets which rows wrapped, even to the same size.
+            if parser.screen().size() != (rows, cols) {
+                parser.set_size(rows, cols);
+            }
         }
         let _ = self._pty.resize(portable_pty::PtySize {
             rows,
@@ -123,6 +152,9 @@ impl Editor {
         let parser = self.parser.lock().unwrap();
         let pseudo_term = PseudoTerminal::new(parser.screen()).block(block);
         f.render_widget(pseudo_term, area);
+        if let Some(selection) = &self.selection {
+            selection.highlight(f.buffer_mut(), inner_area, 0);
+        }
         let (cur_y, cur_x) = parser.screen().cursor_position(); // This is 1-based (y, x)
         let cursor_x = inner_area.x + cur_x;
         let cursor_y = inner_area.y + cur_y;
@@ -156,6 +188,24 @@ impl Editor {
     }
 }
 
+impl PtyScreen for Editor {
+    fn parser(&self) -> MutexGuard<'_, Parser> {
+        self.parser.lock().unwrap()
+    }
+
+    fn screen_area(&self) -> Rect {
+        self.area
+    }
+
+    fn selection(&self) -> Option<&Selection> {
+        self.selection.as_ref()
+    }
+
+    fn set_selection(&mut self, selection: Option<Selection>) {
+        self.selection = selection;
+    }
+}
+
 impl PtyInput for Editor {
     /// 入力をエディタプロセスに送る
     fn send_input(&self, input: &[u8]) {
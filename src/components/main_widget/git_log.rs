@@ -0,0 +1,662 @@
+//! Commit history of the workspace repository, with per-commit changed files and
+//! patches.
+
+use crate::components::diff;
+use crate::theme::Theme;
+use chrono::{DateTime, Local};
+use crossterm::event::{KeyCode, KeyEvent};
+use git2::{Commit, DiffOptions, Oid, Repository, Sort};
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
+};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// At most this many commits are walked, so huge histories stay responsive.
+const MAX_COMMITS: usize = 5000;
+/// How often a background walk hands over the commits it found so far.
+const BATCH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Batches of commits from a background walk; an error ends it.
+type WalkBatch = Result<Vec<LogEntry>, git2::Error>;
+
+struct LogEntry {
+    id: Oid,
+    /// Lane drawing for the commit graph; empty while a filter is active.
+    graph: String,
+    summary: String,
+    author: String,
+    time: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum LogFocus {
+    Commits,
+    Files,
+    Patch,
+}
+
+#[derive(Clone, Copy)]
+enum FilterField {
+    Path,
+    Author,
+}
+
+pub struct GitLog {
+    repo_path: PathBuf,
+    entries: Vec<LogEntry>,
+    list_state: ListState,
+    path_filter: String,
+    author_filter: String,
+    /// The filter being typed, shown in place of the title until Enter or Esc.
+    editing: Option<(FilterField, String)>,
+    focus: LogFocus,
+    /// Header of the selected commit: id, author, date and full message.
+    details: Vec<Line<'static>>,
+    /// Files changed by the selected commit, with a one-letter status.
+    files: Vec<(char, String)>,
+    files_state: ListState,
+    patch: Vec<Line<'static>>,
+    patch_scroll: u16,
+    error: Option<String>,
+    /// A path-filtered walk still running in the background. Dropping it stops
+    /// the walk.
+    loading: Option<Receiver<WalkBatch>>,
+    /// The commit to select again once a background walk delivers it.
+    reselect: Option<Oid>,
+}
+
+impl GitLog {
+    /// Opens the log of the repository containing `path`.
+    pub fn new(path: &Path) -> Self {
+        let mut log = Self {
+            repo_path: path.to_path_buf(),
+            entries: Vec::new(),
+            list_state: ListState::default(),
+            path_filter: String::new(),
+            author_filter: String::new(),
+            editing: None,
+            focus: LogFocus::Commits,
+            details: Vec::new(),
+            files: Vec::new(),
+            files_state: ListState::default(),
+            patch: Vec::new(),
+            patch_scroll: 0,
+            error: None,
+            loading: None,
+            reselect: None,
+        };
+        log.reload();
+        log
+    }
+
+    fn open_repo(&self) -> Result<Repository, git2::Error> {
+        Repository::discover(&self.repo_path)
+    }
+
+    /// Walks the history again, applying the current filters. Checking which
+    /// commits touch a path means diffing each of them, so that walk runs in the
+    /// background and fills the list as commits are found.
+    pub fn reload(&mut self) {
+        let selected = self
+            .list_state
+            .selected()
+            .and_then(|i| self.entries.get(i))
+            .map(|e| e.id);
+        self.loading = None;
+        self.entries.clear();
+        self.error = None;
+        if !self.path_filter.is_empty() {
+            let (tx, rx) = mpsc::channel();
+            let (repo_path, path_filter, author_filter) = (
+                self.repo_path.clone(),
+                self.path_filter.clone(),
+                self.author_filter.clone(),
+            );
+            thread::spawn(move || {
+                let result = Repository::discover(&repo_path).and_then(|repo| {
+                    walk(&repo, &path_filter, &author_filter, |batch| {
+                        tx.send(Ok(batch)).is_ok()
+                    })
+                });
+                if let Err(e) = result {
+                    let _ = tx.send(Err(e));
+                }
+            });
+            self.loading = Some(rx);
+            self.reselect = selected;
+            self.list_state.select(None);
+            self.load_details();
+            return;
+        }
+
+        let mut entries = Vec::new();
+        let result = self.open_repo().and_then(|repo| {
+            walk(&repo, "", &self.author_filter, |batch| {
+                entries.extend(batch);
+                true
+            })
+        });
+        match result {
+            Ok(()) => self.entries = entries,
+            Err(e) => self.error = Some(format!("Git Error: {}", e.message())),
+        }
+        let index = selected
+            .and_then(|id| self.entries.iter().position(|e| e.id == id))
+            .or((!self.entries.is_empty()).then_some(0));
+        self.list_state.select(index);
+        self.load_details();
+    }
+
+    /// Takes in the commits a background walk found since the last call.
+    pub fn poll(&mut self) {
+        let Some(rx) = &self.loading else {
+            return;
+        };
+        let mut done = false;
+        loop {
+            match rx.try_recv() {
+                Ok(Ok(batch)) => self.entries.extend(batch),
+                Ok(Err(e)) => self.error = Some(format!("Git Error: {}", e.message())),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    done = true;
+                    break;
+                }
+            }
+        }
+        if done {
+            self.loading = None;
+        }
+        if self.list_state.selected().is_some() || self.entries.is_empty() {
+            return;
+        }
+        // Wait for the previously selected commit until the walk ends.
+        let index = match self.reselect {
+            Some(id) => self.entries.iter().position(|e| e.id == id),
+            None => Some(0),
+        };
+        if let Some(index) = index.or(done.then_some(0)) {
+            self.list_state.select(Some(index));
+            self.reselect = None;
+            self.load_details();
+        }
+    }
+
+    /// Selects commit `id`, clearing the filters if they hide it. Returns whether
+    /// the commit is in the log.
+    pub fn select_commit(&mut self, id: Oid) -> bool {
+        if !self.entries.iter().any(|e| e.id == id)
+            && (!self.path_filter.is_empty() || !self.author_filter.is_empty())
+        {
+            self.path_filter.clear();
+            self.author_filter.clear();
+            self.reload();
+        }
+        let Some(index) = self.entries.iter().position(|e| e.id == id) else {
+            return false;
+        };
+        self.list_state.select(Some(index));
+        self.focus = LogFocus::Commits;
+        self.load_details();
+        true
+    }
+
+    /// Loads the header and changed files of the selected commit.
+    fn load_details(&mut self) {
+        self.details.clear();
+        self.files.clear();
+        self.files_state.select(None);
+        self.patch.clear();
+        self.patch_scroll = 0;
+        let Some(id) = self
+            .list_state
+            .selected()
+            .and_then(|i| self.entries.get(i))
+            .map(|e| e.id)
+        else {
+            return;
+        };
+        let Ok(repo) = self.open_repo() else {
+            return;
+        };
+        let Ok(commit) = repo.find_commit(id) else {
+            return;
+        };
+
+        let author = commit.author();
+        self.details.push(Line::styled(
+            format!("commit {}", commit.id()),
+            Style::default().fg(Color::Yellow),
+        ));
+        self.details.push(Line::from(format!(
+            "Author: {} <{}>",
+            author.name().unwrap_or(""),
+            author.email().unwrap_or("")
+        )));
+        self.details.push(Line::from(format!(
+            "Date:   {}",
+            format_time(&commit.time())
+        )));
+        self.details.push(Line::default());
+        for line in commit.message().unwrap_or("").lines() {
+            self.details.push(Line::from(format!("    {}", line)));
+        }
+
+        if let Ok(diff) = commit_diff(&repo, &commit, None) {
+            for delta in diff.deltas() {
+                let path = delta
+                    .new_file()
+                    .path()
+                    .or_else(|| delta.old_file().path())
+                    .map(|p| p.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let status = match delta.status() {
+                    git2::Delta::Added => 'A',
+                    git2::Delta::Deleted => 'D',
+                    git2::Delta::Renamed => 'R',
+                    git2::Delta::Copied => 'C',
+                    git2::Delta::Typechange => 'T',
+                    _ => 'M',
+                };
+                self.files.push((status, path));
+            }
+        }
+        if !self.files.is_empty() {
+            self.files_state.select(Some(0));
+        }
+        self.load_patch();
+    }
+
+    /// Loads the patch of the selected file of the selected commit.
+    fn load_patch(&mut self) {
+        self.patch.clear();
+        self.patch_scroll = 0;
+        let (Some(entry), Some((_, path))) = (
+            self.list_state.selected().and_then(|i| self.entries.get(i)),
+            self.files_state.selected().and_then(|i| self.files.get(i)),
+        ) else {
+            return;
+        };
+        let Ok(repo) = self.open_repo() else {
+            return;
+        };
+        let patch = repo
+            .find_commit(entry.id)
+            .and_then(|commit| commit_diff(&repo, &commit, Some(path)))
+            .map(|diff| diff::patch_lines(&diff));
+        if let Ok(patch) = patch {
+            self.patch = patch;
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if let Some((field, input)) = &mut self.editing {
+            match key.code {
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Esc => self.editing = None,
+                KeyCode::Enter => {
+                    let value = input.trim().to_string();
+                    match field {
+                        FilterField::Path => self.path_filter = value,
+                        FilterField::Author => self.author_filter = value,
+                    }
+                    self.editing = None;
+                    self.list_state.select(None);
+                    self.reload();
+                }
+                _ => return false,
+            }
+            return true;
+        }
+
+        match key.code {
+            KeyCode::Char('/') => {
+                self.editing = Some((FilterField::Path, self.path_filter.clone()));
+                return true;
+            }
+            KeyCode::Char('a') => {
+                self.editing = Some((FilterField::Author, self.author_filter.clone()));
+                return true;
+            }
+            KeyCode::Char('R') => {
+                self.reload();
+                return true;
+            }
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    LogFocus::Commits => LogFocus::Files,
+                    LogFocus::Files => LogFocus::Patch,
+                    LogFocus::Patch => LogFocus::Commits,
+                };
+                return true;
+            }
+            KeyCode::Esc if self.focus != LogFocus::Commits => {
+                self.focus = LogFocus::Commits;
+                return true;
+            }
+            _ => {}
+        }
+
+        match self.focus {
+            LogFocus::Commits => match key.code {
+                KeyCode::Down | KeyCode::Char('j') => self.move_commit(1),
+                KeyCode::Up | KeyCode::Char('k') => self.move_commit(-1),
+                KeyCode::PageDown => self.move_commit(20),
+                KeyCode::PageUp => self.move_commit(-20),
+                KeyCode::Enter | KeyCode::Right if !self.files.is_empty() => {
+                    self.focus = LogFocus::Files;
+                }
+                _ => return false,
+            },
+            LogFocus::Files => match key.code {
+                KeyCode::Down | KeyCode::Char('j') => self.move_file(1),
+                KeyCode::Up | KeyCode::Char('k') => self.move_file(-1),
+                KeyCode::Enter | KeyCode::Right => self.focus = LogFocus::Patch,
+                KeyCode::Left => self.focus = LogFocus::Commits,
+                _ => return false,
+            },
+            LogFocus::Patch => match key.code {
+                KeyCode::Down | KeyCode::Char('j') => self.scroll_patch(1),
+                KeyCode::Up | KeyCode::Char('k') => self.scroll_patch(-1),
+                KeyCode::PageDown => self.scroll_patch(20),
+                KeyCode::PageUp => self.scroll_patch(-20),
+                KeyCode::Left => self.focus = LogFocus::Files,
+                _ => return false,
+            },
+        }
+        true
+    }
+
+    fn move_commit(&mut self, delta: isize) {
+        if self.entries.is_empty() {
+            return;
+        }
+        let current = self.list_state.selected().unwrap_or(0) as isize;
+        let next = (current + delta).clamp(0, self.entries.len() as isize - 1) as usize;
+        if Some(next) != self.list_state.selected() {
+            self.list_state.select(Some(next));
+            self.load_details();
+        }
+    }
+
+    fn move_file(&mut self, delta: isize) {
+        if self.files.is_empty() {
+            return;
+        }
+        let current = self.files_state.selected().unwrap_or(0) as isize;
+        let next = (current + delta).clamp(0, self.files.len() as isize - 1) as usize;
+        self.files_state.select(Some(next));
+        self.load_patch();
+    }
+
+    fn scroll_patch(&mut self, delta: i32) {
+        let max = self.patch.len().saturating_sub(1) as i32;
+        self.patch_scroll = (self.patch_scroll as i32 + delta).clamp(0, max) as u16;
+    }
+
+    pub fn render_with_block(&mut self, f: &mut Frame, area: Rect, block: Block, theme: &Theme) {
+        let title = match &self.editing {
+            Some((FilterField::Path, input)) => format!("Log — path filter: {}▏", input),
+            Some((FilterField::Author, input)) => format!("Log — author filter: {}▏", input),
+            None => {
+                let mut filters = Vec::new();
+                if !self.path_filter.is_empty() {
+                    filters.push(format!("path: {}", self.path_filter));
+                }
+                if !self.author_filter.is_empty() {
+                    filters.push(format!("author: {}", self.author_filter));
+                }
+                let count = if self.loading.is_some() {
+                    format!("{} commits, searching…", self.entries.len())
+                } else {
+                    format!("{} commits", self.entries.len())
+                };
+                if filters.is_empty() {
+                    format!("Log ({})", count)
+                } else {
+                    format!("Log ({}, {})", count, filters.join(", "))
+                }
+            }
+        };
+        let block = block.title(title);
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        if let Some(error) = &self.error {
+            let error_p = Paragraph::new(error.as_str()).style(Style::default().fg(Color::Red));
+            f.render_widget(error_p, inner);
+            return;
+        }
+
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(inner);
+        let right = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(8),
+                Constraint::Percentage(30),
+                Constraint::Min(0),
+            ])
+            .split(chunks[1]);
+
+        let pane = |title: &'static str, focus: LogFocus| {
+            let border = if self.focus == focus {
+                Style::default().fg(theme.highlight_fg)
+            } else {
+                Style::default().fg(theme.text_fg)
+            };
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(border)
+        };
+        let highlight = Style::default().bg(theme.highlight_bg).fg(theme.text_fg);
+
+        let items: Vec<ListItem> = self
+            .entries
+            .iter()
+            .map(|e| {
+                let short = e.id.to_string()[..7].to_string();
+                ListItem::new(Line::from(vec![
+                    Span::styled(e.graph.clone(), Style::default().fg(Color::Magenta)),
+                    Span::styled(short, Style::default().fg(Color::Yellow)),
+                    Span::raw(" "),
+                    Span::raw(e.summary.clone()),
+                    Span::styled(format!("  {}", e.author), Style::default().fg(Color::Cyan)),
+                    Span::styled(
+                        format!("  {}", e.time),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                ]))
+            })
+            .collect();
+        let commits = List::new(items)
+            .block(pane("Commits", LogFocus::Commits))
+            .highlight_style(highlight);
+        f.render_stateful_widget(commits, chunks[0], &mut self.list_state);
+
+        let details = Paragraph::new(self.details.clone())
+            .wrap(Wrap { trim: false })
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(details, right[0]);
+
+        let files: Vec<ListItem> = self
+            .files
+            .iter()
+            .map(|(status, path)| {
+                let color = match status {
+                    'A' => Color::Green,
+                    'D' => Color::Red,
+                    _ => Color::Yellow,
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{} ", status), Style::default().fg(color)),
+                    Span::raw(path.clone()),
+                ]))
+            })
+            .collect();
+        let files = List::new(files)
+            .block(pane("Changed Files", LogFocus::Files))
+            .highlight_style(highlight);
+        f.render_stateful_widget(files, right[1], &mut self.files_state);
+
+        let patch = Paragraph::new(self.patch.clone())
+            .scroll((self.patch_scroll, 0))
+            .block(pane("Diff", LogFocus::Patch));
+        f.render_widget(patch, right[2]);
+    }
+}
+
+/// Walks the history from HEAD and hands the commits matching the filters to
+/// `deliver`, in batches at least every [`BATCH_INTERVAL`] and once at the end.
+/// Stops early when `deliver` returns `false`.
+fn walk(
+    repo: &Repository,
+    path_filter: &str,
+    author_filter: &str,
+    mut deliver: impl FnMut(Vec<LogEntry>) -> bool,
+) -> Result<(), git2::Error> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    if revwalk.push_head().is_err() {
+        // An empty repository has no history yet.
+        return Ok(());
+    }
+    let author_filter = author_filter.to_lowercase();
+    let filtered = !path_filter.is_empty() || !author_filter.is_empty();
+    let mut lanes: Vec<Option<Oid>> = Vec::new();
+    let mut batch = Vec::new();
+    let mut last_delivery = Instant::now();
+    for id in revwalk.take(MAX_COMMITS) {
+        if last_delivery.elapsed() >= BATCH_INTERVAL {
+            last_delivery = Instant::now();
+            if !deliver(std::mem::take(&mut batch)) {
+                return Ok(());
+            }
+        }
+        let commit = repo.find_commit(id?)?;
+        let author = commit.author();
+        let name = author.name().unwrap_or("").to_string();
+        if !author_filter.is_empty() {
+            let email = author.email().unwrap_or("");
+            if !name.to_lowercase().contains(&author_filter)
+                && !email.to_lowercase().contains(&author_filter)
+            {
+                continue;
+            }
+        }
+        if !path_filter.is_empty() && !touches_path(repo, &commit, path_filter)? {
+            continue;
+        }
+        let graph = if filtered {
+            String::new()
+        } else {
+            let parents: Vec<Oid> = commit.parent_ids().collect();
+            graph_row(&mut lanes, commit.id(), &parents)
+        };
+        batch.push(LogEntry {
+            id: commit.id(),
+            graph,
+            summary: commit.summary().unwrap_or("").to_string(),
+            author: name,
+            time: format_time(&commit.time()),
+        });
+    }
+    deliver(batch);
+    Ok(())
+}
+
+/// Diff of a commit against its first parent (or an empty tree for a root commit),
+/// optionally limited to one path.
+fn commit_diff<'r>(
+    repo: &'r Repository,
+    commit: &Commit,
+    path: Option<&str>,
+) -> Result<git2::Diff<'r>, git2::Error> {
+    let tree = commit.tree()?;
+    let parent_tree = match commit.parent(0) {
+        Ok(parent) => Some(parent.tree()?),
+        Err(_) => None,
+    };
+    let mut opts = DiffOptions::new();
+    if let Some(path) = path {
+        opts.pathspec(path);
+    }
+    repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), Some(&mut opts))
+}
+
+fn touches_path(repo: &Repository, commit: &Commit, path: &str) -> Result<bool, git2::Error> {
+    Ok(commit_diff(repo, commit, Some(path))?.deltas().len() > 0)
+}
+
+fn format_time(time: &git2::Time) -> String {
+    DateTime::from_timestamp(time.seconds(), 0)
+        .map(|t| t.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+/// Draws one row of the commit graph and advances `lanes`, which holds the commit
+/// each column is waiting for.
+fn graph_row(lanes: &mut Vec<Option<Oid>>, id: Oid, parents: &[Oid]) -> String {
+    let column = lanes
+        .iter()
+        .position(|l| *l == Some(id))
+        .or_else(|| lanes.iter().position(Option::is_none))
+        .unwrap_or_else(|| {
+            lanes.push(None);
+            lanes.len() - 1
+        });
+    // Other columns that were waiting for this commit end here.
+    let merged: Vec<usize> = (0..lanes.len())
+        .filter(|&i| i != column && lanes[i] == Some(id))
+        .collect();
+    for &i in &merged {
+        lanes[i] = None;
+    }
+    lanes[column] = parents.first().copied();
+    // Further parents of a merge continue in a column of their own.
+    let mut forks = Vec::new();
+    for parent in parents.iter().skip(1) {
+        if let Some(i) = lanes.iter().position(|l| *l == Some(*parent)) {
+            forks.push(i);
+            continue;
+        }
+        let slot = lanes.iter().position(Option::is_none).unwrap_or_else(|| {
+            lanes.push(None);
+            lanes.len() - 1
+        });
+        lanes[slot] = Some(*parent);
+        forks.push(slot);
+    }
+
+    let width = lanes.len().max(column + 1);
+    let mut row = String::new();
+    for i in 0..width {
+        let c = if i == column {
+            '●'
+        } else if forks.contains(&i) {
+            '╮'
+        } else if merged.contains(&i) {
+            '╯'
+        } else if lanes.get(i).is_some_and(Option::is_some) {
+            '│'
+        } else {
+            ' '
+        };
+        row.push(c);
+        row.push(' ');
+    }
+    while lanes.last().is_some_and(Option::is_none) {
+        lanes.pop();
+    }
+    row
+}
@@ -1,13 +1,26 @@
-use crate::{app::App, settings::Config, theme::Theme};
+use crate::{
+    app::App,
+    components::notification::{send_notification, NotificationType},
+    settings::Config,
+    theme::Theme,
+};
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{
     prelude::*,
     widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
 };
 
+/// Number of editable rows in the [General] section.
+const GENERAL_ITEMS: usize = 5;
+
 /// Represents which specific field is currently being edited.
 #[derive(Debug, Clone)]
 enum EditableField {
+    Editor,
+    UseBuiltinEditor,
+    Terminal,
+    EditorCwd,
+    TerminalCwd,
     Keybinding(String), // The key of the keybinding
     ThemePreset,
     ThemePrimaryBg,
@@ -50,6 +63,34 @@ impl SettingsEditor {
         let theme = &app.theme;
         let mut items = Vec::new();
 
+        items.push(ListItem::new(Line::from(Span::styled(
+            "[General]",
+            Style::default()
+                .fg(theme.highlight_fg)
+                .add_modifier(Modifier::BOLD),
+        ))));
+        items.push(ListItem::new(format!(
+            "editor:             {}",
+            config.editor
+        )));
+        items.push(ListItem::new(format!(
+            "use_builtin_editor: {}",
+            config.use_builtin_editor
+        )));
+        items.push(ListItem::new(format!(
+            "terminal:           {}",
+            config.terminal.as_deref().unwrap_or("")
+        )));
+        items.push(ListItem::new(format!(
+            "editor_cwd:         {}",
+            config.editor_cwd.as_deref().unwrap_or("")
+        )));
+        items.push(ListItem::new(format!(
+            "terminal_cwd:       {}",
+            config.terminal_cwd.as_deref().unwrap_or("")
+        )));
+
+        items.push(ListItem::new("")); // Spacer
         items.push(ListItem::new(Line::from(Span::styled(
             "[Keybindings]",
             Style::default()
@@ -153,6 +194,17 @@ impl SettingsEditor {
                 KeyCode::Enter => {
                     let new_value = editing_state.input_buffer.clone();
                     match &editing_state.field {
+                        EditableField::Editor => app.config.editor = new_value,
+                        EditableField::UseBuiltinEditor => match new_value.trim().parse::<bool>() {
+                            Ok(v) => app.config.use_builtin_editor = v,
+                            Err(_) => send_notification(
+                                "use_builtin_editor must be `true` or `false`".to_string(),
+                                NotificationType::Warning,
+                            ),
+                        },
+                        EditableField::Terminal => app.config.terminal = optional(new_value),
+                        EditableField::EditorCwd => app.config.editor_cwd = optional(new_value),
+                        EditableField::TerminalCwd => app.config.terminal_cwd = optional(new_value),
                         EditableField::Keybinding(k) => {
                             if let Some(v) = app.config.keybindings.global.get_mut(k) {
                                 *v = new_value;
@@ -175,8 +227,10 @@ impl SettingsEditor {
                     app.theme = Theme::from_config(&app.config.theme);
                     // Save the updated config to file
                     if let Err(e) = app.config.save() {
-                        // In a real app, you'd want to show this error to the user
-                        eprintln!("Failed to save config: {}", e);
+                        send_notification(
+                            format!("Failed to save config: {}", e),
+                            NotificationType::Error,
+                        );
                     }
                     self.editing_state = None;
                 }
@@ -193,6 +247,19 @@ impl SettingsEditor {
                         if let Some(field) = self.index_to_field(selected_index, &app.config) {
                             // Get the current value and enter editing mode
                             let current_value = match &field {
+                                EditableField::Editor => app.config.editor.clone(),
+                                EditableField::UseBuiltinEditor => {
+                                    app.config.use_builtin_editor.to_string()
+                                }
+                                EditableField::Terminal => {
+                                    app.config.terminal.clone().unwrap_or_default()
+                                }
+                                EditableField::EditorCwd => {
+                                    app.config.editor_cwd.clone().unwrap_or_default()
+                                }
+                                EditableField::TerminalCwd => {
+                                    app.config.terminal_cwd.clone().unwrap_or_default()
+                                }
                                 EditableField::Keybinding(k) => app
                                     .config
                                     .keybindings
@@ -229,7 +296,17 @@ impl SettingsEditor {
 
     /// Converts a list index to a specific editable field.
     fn index_to_field(&self, index: usize, config: &Config) -> Option<EditableField> {
-        let keybinding_start = 1;
+        match index {
+            1 => return Some(EditableField::Editor),
+            2 => return Some(EditableField::UseBuiltinEditor),
+            3 => return Some(EditableField::Terminal),
+            4 => return Some(EditableField::EditorCwd),
+            5 => return Some(EditableField::TerminalCwd),
+            _ => {}
+        }
+
+        // [General] header + its items + spacer + [Keybindings] header
+        let keybinding_start = 1 + GENERAL_ITEMS + 2;
         let keybinding_end = keybinding_start + config.keybindings.global.len();
         let theme_header_index = keybinding_end + 1;
 
@@ -253,7 +330,10 @@ impl SettingsEditor {
 
     /// Gets the total number of visible items in the settings list.
     fn get_total_items(&self, config: &Config) -> usize {
-        1  // [Keybindings] header
+        1  // [General] header
+        + GENERAL_ITEMS
+        + 1  // Spacer
+        + 1  // [Keybindings] header
         + config.keybindings.global.len()
         + 1  // Spacer
         + 1  // [Theme] header
@@ -284,3 +364,12 @@ impl SettingsEditor {
         self.state.select(Some(i));
     }
 }
+
+/// Maps an empty input to `None` for optional settings.
+fn optional(value: String) -> Option<String> {
+    if value.trim().is_empty() {
+        None
+    } else {
+        Some(value)
+    }
+}
@@ -0,0 +1,571 @@
+pub mod buffer;
+pub mod git_info;
+
+use self::buffer::TextBuffer;
+use self::git_info::{GitInfo, LineChange};
+use crate::{
+    components::notification::{send_notification, NotificationType},
+    theme::Theme,
+};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use git2::Oid;
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Paragraph},
+};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use unicode_width::UnicodeWidthChar;
+
+const TAB_WIDTH: usize = 4;
+/// Width of the blame column: short id, author and date.
+const BLAME_WIDTH: usize = 32;
+/// How often the file is checked for changes made by other programs.
+const DISK_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// An editor that owns its text buffer in-process, as opposed to the PTY-wrapped `$EDITOR`.
+pub struct TextEditor {
+    buffer: TextBuffer,
+    /// Cursor position as a char index into the buffer.
+    cursor: usize,
+    /// The other end of the selection, if any.
+    anchor: Option<usize>,
+    /// Column to return to when moving vertically across shorter lines.
+    preferred_col: Option<usize>,
+    scroll_row: usize,
+    scroll_col: usize,
+    viewport_height: usize,
+    clipboard: String,
+    git: Box<GitInfo>,
+    /// The user was told the file changed on disk under unsaved edits.
+    disk_change_warned: bool,
+    last_disk_check: Option<Instant>,
+}
+
+impl Default for TextEditor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TextEditor {
+    pub fn new() -> Self {
+        Self::with_buffer(TextBuffer::new())
+    }
+
+    pub fn open(path: PathBuf) -> io::Result<Self> {
+        Ok(Self::with_buffer(TextBuffer::from_file(path)?))
+    }
+
+    fn with_buffer(buffer: TextBuffer) -> Self {
+        Self {
+            buffer,
+            cursor: 0,
+            anchor: None,
+            preferred_col: None,
+            scroll_row: 0,
+            scroll_col: 0,
+            viewport_height: 1,
+            clipboard: String::new(),
+            git: Box::default(),
+            disk_change_warned: false,
+            last_disk_check: None,
+        }
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.buffer.path()
+    }
+
+    /// Points the buffer at a new file, e.g. after the file was renamed on disk.
+    pub fn set_path(&mut self, path: PathBuf) {
+        self.buffer.set_path(path);
+    }
+
+    pub fn text(&self) -> String {
+        self.buffer.text()
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.buffer.is_dirty()
+    }
+
+    pub fn save(&mut self) -> io::Result<()> {
+        self.buffer.save()?;
+        self.disk_change_warned = false;
+        Ok(())
+    }
+
+    /// Picks up changes other tools wrote to the file. Unsaved edits are kept and
+    /// the user is warned that saving will overwrite the file.
+    pub fn poll_disk(&mut self) {
+        if self
+            .last_disk_check
+            .is_some_and(|checked| checked.elapsed() < DISK_CHECK_INTERVAL)
+        {
+            return;
+        }
+        self.last_disk_check = Some(Instant::now());
+        if !self.buffer.changed_on_disk() {
+            return;
+        }
+        if self.is_dirty() {
+            self.warn_disk_change();
+            return;
+        }
+        match self.buffer.reload() {
+            Ok(()) => {
+                self.cursor = self.cursor.min(self.buffer.len_chars());
+                self.anchor = None;
+                self.preferred_col = None;
+            }
+            Err(e) => send_notification(
+                format!("Failed to reload {}: {}", self.display_path(), e),
+                NotificationType::Error,
+            ),
+        }
+    }
+
+    fn warn_disk_change(&mut self) {
+        if self.disk_change_warned {
+            return;
+        }
+        self.disk_change_warned = true;
+        send_notification(
+            format!(
+                "{} changed on disk. Saving overwrites it; close without saving to keep the new version.",
+                self.display_path()
+            ),
+            NotificationType::Warning,
+        );
+    }
+
+    fn display_path(&self) -> String {
+        self.path()
+            .map(|p| p.display().to_string())
+            .unwrap_or_default()
+    }
+
+    /// Returns the 1-based (line, column) of the cursor.
+    pub fn cursor_position(&self) -> (usize, usize) {
+        let line = self.buffer.char_to_line(self.cursor);
+        (line + 1, self.cursor - self.buffer.line_to_char(line) + 1)
+    }
+
+    /// Moves the cursor to a 1-based line and column, clamped to the buffer.
+    pub fn goto(&mut self, line: usize, column: usize) {
+        let line = line
+            .saturating_sub(1)
+            .min(self.buffer.len_lines().saturating_sub(1));
+        let col = column.saturating_sub(1).min(self.buffer.line_len(line));
+        self.anchor = None;
+        self.preferred_col = None;
+        self.cursor = self.buffer.line_to_char(line) + col;
+        // Center the target line on the next render.
+        self.scroll_row = line.saturating_sub(self.viewport_height / 2);
+    }
+
+    /// Updates the change markers and blame once the file or HEAD changed.
+    pub fn poll_git(&mut self) {
+        if let Some(path) = self.buffer.path() {
+            self.git.refresh_if_stale(path);
+        }
+    }
+
+    /// Shows or hides the blame column. Returns whether it is now shown.
+    pub fn toggle_blame(&mut self) -> bool {
+        let Some(path) = self.buffer.path() else {
+            return false;
+        };
+        let enabled = self.git.blame.is_none();
+        self.git.set_blame(path, enabled);
+        enabled
+    }
+
+    /// The commit that last changed the cursor line, while blame is shown.
+    pub fn blame_commit_at_cursor(&self) -> Option<Oid> {
+        let line = self.buffer.char_to_line(self.cursor);
+        self.git
+            .blame
+            .as_ref()?
+            .get(line)
+            .map(|b| b.id)
+            .filter(|id| !id.is_zero())
+    }
+
+    /// Inserts text at the cursor, replacing the selection.
+    pub fn insert_text(&mut self, text: &str) {
+        let before = self.cursor;
+        let (start, end) = self.selection_range().unwrap_or((self.cursor, self.cursor));
+        self.cursor = if start < end {
+            self.buffer.replace(start, end, text, before)
+        } else {
+            self.buffer.insert(start, text, before)
+        };
+        self.anchor = None;
+        self.preferred_col = None;
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+
+        if ctrl {
+            match key.code {
+                KeyCode::Char('s') => self.save_with_notification(),
+                KeyCode::Char('z') if shift => self.redo(),
+                KeyCode::Char('Z') => self.redo(),
+                KeyCode::Char('z') => self.undo(),
+                KeyCode::Char('y') => self.redo(),
+                KeyCode::Char('a') => {
+                    self.anchor = Some(0);
+                    self.cursor = self.buffer.len_chars();
+                }
+                KeyCode::Char('c') => self.copy_selection(),
+                KeyCode::Char('x') => {
+                    self.copy_selection();
+                    self.delete_selection();
+                }
+                KeyCode::Char('v') => {
+                    let text = self.clipboard.clone();
+                    self.insert_text(&text);
+                }
+                KeyCode::Left => self.move_to(self.word_left(), shift),
+                KeyCode::Right => self.move_to(self.word_right(), shift),
+                KeyCode::Home => self.move_to(0, shift),
+                KeyCode::End => self.move_to(self.buffer.len_chars(), shift),
+                _ => return false,
+            }
+            return true;
+        }
+
+        match key.code {
+            KeyCode::Left => {
+                let target = match self.selection_range() {
+                    Some((start, _)) if !shift => start,
+                    _ => self.cursor.saturating_sub(1),
+                };
+                self.move_to(target, shift);
+            }
+            KeyCode::Right => {
+                let target = match self.selection_range() {
+                    Some((_, end)) if !shift => end,
+                    _ => (self.cursor + 1).min(self.buffer.len_chars()),
+                };
+                self.move_to(target, shift);
+            }
+            KeyCode::Up => self.move_vertically(-1, shift),
+            KeyCode::Down => self.move_vertically(1, shift),
+            KeyCode::PageUp => self.move_vertically(-(self.viewport_height as isize), shift),
+            KeyCode::PageDown => self.move_vertically(self.viewport_height as isize, shift),
+            KeyCode::Home => {
+                let line = self.buffer.char_to_line(self.cursor);
+                self.move_to(self.buffer.line_to_char(line), shift);
+            }
+            KeyCode::End => {
+                let line = self.buffer.char_to_line(self.cursor);
+                self.move_to(
+                    self.buffer.line_to_char(line) + self.buffer.line_len(line),
+                    shift,
+                );
+            }
+            KeyCode::Char(c) => self.insert_text(&c.to_string()),
+            KeyCode::Enter => self.insert_text("\n"),
+            KeyCode::Tab => self.insert_text(&" ".repeat(TAB_WIDTH)),
+            KeyCode::Backspace => {
+                if !self.delete_selection() && self.cursor > 0 {
+                    let before = self.cursor;
+                    self.cursor = self.buffer.remove(self.cursor - 1, self.cursor, before);
+                }
+            }
+            KeyCode::Delete => {
+                if !self.delete_selection() && self.cursor < self.buffer.len_chars() {
+                    let before = self.cursor;
+                    self.cursor = self.buffer.remove(self.cursor, self.cursor + 1, before);
+                }
+            }
+            KeyCode::Esc => self.anchor = None,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Saves, unless the file changed on disk and the user has not been warned
+    /// yet; then only warns, so the next save overwrites it.
+    pub fn save_with_notification(&mut self) {
+        if self.buffer.changed_on_disk() && !self.disk_change_warned {
+            self.warn_disk_change();
+            return;
+        }
+        match self.save() {
+            Ok(()) => send_notification(
+                format!("Saved {}", self.display_path()),
+                NotificationType::Info,
+            ),
+            Err(e) => {
+                send_notification(format!("Error saving file: {}", e), NotificationType::Error)
+            }
+        }
+    }
+
+    fn undo(&mut self) {
+        if let Some(cursor) = self.buffer.undo() {
+            self.cursor = cursor.min(self.buffer.len_chars());
+            self.anchor = None;
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(cursor) = self.buffer.redo() {
+            self.cursor = cursor.min(self.buffer.len_chars());
+            self.anchor = None;
+        }
+    }
+
+    /// Returns the selection as an ordered `(start, end)` char range, if non-empty.
+    fn selection_range(&self) -> Option<(usize, usize)> {
+        let anchor = self.anchor?;
+        if anchor == self.cursor {
+            return None;
+        }
+        Some((anchor.min(self.cursor), anchor.max(self.cursor)))
+    }
+
+    fn copy_selection(&mut self) {
+        if let Some((start, end)) = self.selection_range() {
+            self.clipboard = self.buffer.slice(start, end);
+        }
+    }
+
+    /// Deletes the selected text. Returns `false` if nothing was selected.
+    fn delete_selection(&mut self) -> bool {
+        match self.selection_range() {
+            Some((start, end)) => {
+                let before = self.cursor;
+                self.cursor = self.buffer.remove(start, end, before);
+                self.anchor = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn move_to(&mut self, target: usize, extend_selection: bool) {
+        if extend_selection {
+            if self.anchor.is_none() {
+                self.anchor = Some(self.cursor);
+            }
+        } else {
+            self.anchor = None;
+        }
+        self.cursor = target.min(self.buffer.len_chars());
+        self.preferred_col = None;
+    }
+
+    fn move_vertically(&mut self, delta: isize, extend_selection: bool) {
+        let line = self.buffer.char_to_line(self.cursor);
+        let col = self
+            .preferred_col
+            .unwrap_or(self.cursor - self.buffer.line_to_char(line));
+        let last_line = self.buffer.len_lines().saturating_sub(1) as isize;
+        let target_line = (line as isize + delta).clamp(0, last_line) as usize;
+        let target =
+            self.buffer.line_to_char(target_line) + col.min(self.buffer.line_len(target_line));
+        self.move_to(target, extend_selection);
+        self.preferred_col = Some(col);
+    }
+
+    fn word_left(&self) -> usize {
+        let mut i = self.cursor;
+        while i > 0 && self.buffer.char_at(i - 1).is_some_and(char::is_whitespace) {
+            i -= 1;
+        }
+        while i > 0 && self.buffer.char_at(i - 1).is_some_and(is_word_char) {
+            i -= 1;
+        }
+        if i == self.cursor {
+            self.cursor.saturating_sub(1)
+        } else {
+            i
+        }
+    }
+
+    fn word_right(&self) -> usize {
+        let len = self.buffer.len_chars();
+        let mut i = self.cursor;
+        while i < len && self.buffer.char_at(i).is_some_and(char::is_whitespace) {
+            i += 1;
+        }
+        while i < len && self.buffer.char_at(i).is_some_and(is_word_char) {
+            i += 1;
+        }
+        if i == self.cursor {
+            (self.cursor + 1).min(len)
+        } else {
+            i
+        }
+    }
+
+    pub fn render_with_block(&mut self, f: &mut Frame, area: Rect, block: Block, theme: &Theme) {
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+        if inner.width == 0 || inner.height == 0 {
+            return;
+        }
+
+        let line_count = self.buffer.len_lines();
+        let blame_width = if self.git.blame.is_some() {
+            BLAME_WIDTH
+        } else {
+            0
+        };
+        let gutter_width = blame_width + line_count.to_string().len() + 1;
+        let text_width = (inner.width as usize)
+            .saturating_sub(gutter_width + 1)
+            .max(1);
+        self.viewport_height = inner.height as usize;
+
+        // Keep the cursor inside the viewport.
+        let cursor_line = self.buffer.char_to_line(self.cursor);
+        let cursor_line_text = self.buffer.line_text(cursor_line);
+        let cursor_col = self.cursor - self.buffer.line_to_char(cursor_line);
+        let cursor_x = display_width(cursor_line_text.chars().take(cursor_col));
+        if cursor_line < self.scroll_row {
+            self.scroll_row = cursor_line;
+        } else if cursor_line >= self.scroll_row + self.viewport_height {
+            self.scroll_row = cursor_line + 1 - self.viewport_height;
+        }
+        if cursor_x < self.scroll_col {
+            self.scroll_col = cursor_x;
+        } else if cursor_x >= self.scroll_col + text_width {
+            self.scroll_col = cursor_x + 1 - text_width;
+        }
+
+        let selection = self.selection_range();
+        let selection_style = Style::default().bg(theme.highlight_bg);
+        let text_style = Style::default().fg(theme.text_fg);
+        let gutter_style = Style::default().fg(Color::DarkGray);
+
+        let mut lines = Vec::with_capacity(self.viewport_height);
+        for line_idx in self.scroll_row..(self.scroll_row + self.viewport_height).min(line_count) {
+            let line_start = self.buffer.line_to_char(line_idx);
+            let mut spans = Vec::new();
+            if let Some(blame) = &self.git.blame {
+                spans.push(blame_span(blame, line_idx, line_idx == self.scroll_row));
+            }
+            spans.push(Span::styled(
+                format!(
+                    "{:>width$} ",
+                    line_idx + 1,
+                    width = gutter_width - blame_width - 1
+                ),
+                if line_idx == cursor_line {
+                    gutter_style.fg(theme.highlight_fg)
+                } else {
+                    gutter_style
+                },
+            ));
+            spans.push(match self.git.changes.get(line_idx).copied().flatten() {
+                Some(LineChange::Added) => Span::styled("▎", Style::default().fg(Color::Green)),
+                Some(LineChange::Modified) => Span::styled("▎", Style::default().fg(Color::Yellow)),
+                Some(LineChange::Deleted) => Span::styled("▁", Style::default().fg(Color::Red)),
+                None => Span::raw(" "),
+            });
+
+            let mut x = 0;
+            let mut current = String::new();
+            let mut current_selected = false;
+            for (i, c) in self.buffer.line_text(line_idx).chars().enumerate() {
+                let (glyph, width) = if c == '\t' {
+                    (
+                        " ".repeat(TAB_WIDTH - x % TAB_WIDTH),
+                        TAB_WIDTH - x % TAB_WIDTH,
+                    )
+                } else {
+                    (c.to_string(), c.width().unwrap_or(0))
+                };
+                let start_x = x;
+                x += width;
+                if start_x < self.scroll_col || x > self.scroll_col + text_width {
+                    continue;
+                }
+                let selected = selection.is_some_and(|(s, e)| (s..e).contains(&(line_start + i)));
+                if selected != current_selected && !current.is_empty() {
+                    spans.push(styled_chunk(
+                        std::mem::take(&mut current),
+                        current_selected,
+                        text_style,
+                        selection_style,
+                    ));
+                }
+                current_selected = selected;
+                current.push_str(&glyph);
+            }
+            if !current.is_empty() {
+                spans.push(styled_chunk(
+                    current,
+                    current_selected,
+                    text_style,
+                    selection_style,
+                ));
+            }
+            lines.push(Line::from(spans));
+        }
+
+        f.render_widget(Paragraph::new(lines).bg(theme.primary_bg), inner);
+        f.set_cursor_position((
+            inner.x + (gutter_width + 1 + cursor_x - self.scroll_col) as u16,
+            inner.y + (cursor_line - self.scroll_row) as u16,
+        ));
+    }
+}
+
+/// The blame annotation of one line; only the first line of a run from the same
+/// commit (or the top visible line) is labelled.
+fn blame_span(blame: &[git_info::BlameLine], line_idx: usize, top: bool) -> Span<'static> {
+    let Some(line) = blame.get(line_idx) else {
+        return Span::raw(" ".repeat(BLAME_WIDTH));
+    };
+    let continued =
+        !top && line_idx > 0 && blame.get(line_idx - 1).is_some_and(|p| p.id == line.id);
+    if continued {
+        return Span::styled(
+            format!("{:<width$}", "│", width = BLAME_WIDTH),
+            Style::default().fg(Color::DarkGray),
+        );
+    }
+    let short = if line.id.is_zero() {
+        "0000000".to_string()
+    } else {
+        line.id.to_string()[..7].to_string()
+    };
+    let author: String = line.author.chars().take(12).collect();
+    Span::styled(
+        format!("{} {:<12} {:<10} ", short, author, line.date),
+        Style::default().fg(Color::DarkGray),
+    )
+}
+
+fn styled_chunk(text: String, selected: bool, normal: Style, selection: Style) -> Span<'static> {
+    if selected {
+        Span::styled(text, selection)
+    } else {
+        Span::styled(text, normal)
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Width in terminal cells of a run of chars, expanding tabs.
+fn display_width(chars: impl Iterator<Item = char>) -> usize {
+    chars.fold(0, |x, c| {
+        if c == '\t' {
+            x + TAB_WIDTH - x % TAB_WIDTH
+        } else {
+            x + c.width().unwrap_or(0)
+        }
+    })
+}
@@ -0,0 +1,404 @@
+use crate::file_ops::write_atomic;
+use ropey::Rope;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// A single reversible change to the buffer.
+#[derive(Debug, Clone)]
+enum EditOp {
+    Insert { at: usize, text: String },
+    Remove { at: usize, text: String },
+}
+
+/// One undo step. Consecutive typed characters are merged into a single step.
+#[derive(Debug, Clone)]
+struct Edit {
+    ops: Vec<EditOp>,
+    cursor_before: usize,
+    cursor_after: usize,
+}
+
+/// Rope-backed text buffer with undo/redo history and dirty tracking.
+pub struct TextBuffer {
+    rope: Rope,
+    path: Option<PathBuf>,
+    undo_stack: Vec<Edit>,
+    redo_stack: Vec<Edit>,
+    /// Depth of the undo stack at the last save, `None` if that state is unreachable.
+    saved_at: Option<usize>,
+    /// Modification time of the file when it was last loaded or saved.
+    disk_mtime: Option<SystemTime>,
+}
+
+impl Default for TextBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TextBuffer {
+    pub fn new() -> Self {
+        Self {
+            rope: Rope::new(),
+            path: None,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            saved_at: Some(0),
+            disk_mtime: None,
+        }
+    }
+
+    /// Loads a file into a new buffer. A missing file yields an empty buffer bound to `path`.
+    pub fn from_file(path: PathBuf) -> io::Result<Self> {
+        let rope = match fs::File::open(&path) {
+            Ok(file) => Rope::from_reader(io::BufReader::new(file))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Rope::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            rope,
+            disk_mtime: modified(&path),
+            path: Some(path),
+            ..Self::new()
+        })
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn set_path(&mut self, path: PathBuf) {
+        self.path = Some(path);
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.saved_at != Some(self.undo_stack.len())
+    }
+
+    pub fn save(&mut self) -> io::Result<()> {
+        let path = self
+            .path
+            .clone()
+            .ok_or_else(|| io::Error::other("Buffer has no file path"))?;
+        write_atomic(&path, &self.rope.to_string()).map_err(io::Error::other)?;
+        self.saved_at = Some(self.undo_stack.len());
+        self.disk_mtime = modified(&path);
+        Ok(())
+    }
+
+    /// Whether something else wrote the file since it was loaded or saved. A file
+    /// that was deleted does not count, as there is nothing to load.
+    pub fn changed_on_disk(&self) -> bool {
+        self.path
+            .as_deref()
+            .and_then(modified)
+            .is_some_and(|mtime| Some(mtime) != self.disk_mtime)
+    }
+
+    /// Replaces the whole contents with what is currently on disk, dropping history.
+    pub fn reload(&mut self) -> io::Result<()> {
+        if let Some(path) = self.path.clone() {
+            *self = Self::from_file(path)?;
+        }
+        Ok(())
+    }
+
+    pub fn len_chars(&self) -> usize {
+        self.rope.len_chars()
+    }
+
+    pub fn len_lines(&self) -> usize {
+        self.rope.len_lines()
+    }
+
+    pub fn char_to_line(&self, char_idx: usize) -> usize {
+        self.rope.char_to_line(char_idx)
+    }
+
+    pub fn line_to_char(&self, line_idx: usize) -> usize {
+        self.rope.line_to_char(line_idx)
+    }
+
+    pub fn char_at(&self, char_idx: usize) -> Option<char> {
+        self.rope.get_char(char_idx)
+    }
+
+    /// Returns the line's text without its trailing line break.
+    pub fn line_text(&self, line_idx: usize) -> String {
+        if line_idx >= self.rope.len_lines() {
+            return String::new();
+        }
+        let line = self.rope.line(line_idx).to_string();
+        line.trim_end_matches(['\n', '\r']).to_string()
+    }
+
+    /// Number of chars on a line, excluding the line break.
+    pub fn line_len(&self, line_idx: usize) -> usize {
+        self.line_text(line_idx).chars().count()
+    }
+
+    pub fn slice(&self, start: usize, end: usize) -> String {
+        self.rope.slice(start..end).to_string()
+    }
+
+    pub fn text(&self) -> String {
+        self.rope.to_string()
+    }
+
+    /// Inserts `text` at `at`. Single-character inserts directly following the
+    /// previous one are coalesced into the same undo step.
+    pub fn insert(&mut self, at: usize, text: &str, cursor_before: usize) -> usize {
+        if text.is_empty() {
+            return at;
+        }
+        self.rope.insert(at, text);
+        let cursor_after = at + text.chars().count();
+        let op = EditOp::Insert {
+            at,
+            text: text.to_string(),
+        };
+        let coalesce = text.chars().count() == 1
+            && !text.contains('\n')
+            && self.saved_at != Some(self.undo_stack.len())
+            && self.redo_stack.is_empty()
+            && matches!(
+                self.undo_stack.last(),
+                Some(Edit { ops, cursor_after: prev_after, .. })
+                    if ops.len() == 1
+                        && *prev_after == at
+                        && matches!(&ops[0], EditOp::Insert { text, .. } if !text.ends_with('\n'))
+            );
+        if coalesce {
+            let last = self.undo_stack.last_mut().unwrap();
+            if let EditOp::Insert { text: prev, .. } = &mut last.ops[0] {
+                prev.push_str(text);
+            }
+            last.cursor_after = cursor_after;
+        } else {
+            self.push_edit(Edit {
+                ops: vec![op],
+                cursor_before,
+                cursor_after,
+            });
+        }
+        cursor_after
+    }
+
+    /// Removes the chars in `start..end`.
+    pub fn remove(&mut self, start: usize, end: usize, cursor_before: usize) -> usize {
+        if start >= end {
+            return start;
+        }
+        let text = self.slice(start, end);
+        self.rope.remove(start..end);
+        self.push_edit(Edit {
+            ops: vec![EditOp::Remove { at: start, text }],
+            cursor_before,
+            cursor_after: start,
+        });
+        start
+    }
+
+    /// Replaces `start..end` with `text` as a single undo step.
+    pub fn replace(&mut self, start: usize, end: usize, text: &str, cursor_before: usize) -> usize {
+        let mut ops = Vec::new();
+        if start < end {
+            let removed = self.slice(start, end);
+            self.rope.remove(start..end);
+            ops.push(EditOp::Remove {
+                at: start,
+                text: removed,
+            });
+        }
+        if !text.is_empty() {
+            self.rope.insert(start, text);
+            ops.push(EditOp::Insert {
+                at: start,
+                text: text.to_string(),
+            });
+        }
+        let cursor_after = start + text.chars().count();
+        if !ops.is_empty() {
+            self.push_edit(Edit {
+                ops,
+                cursor_before,
+                cursor_after,
+            });
+        }
+        cursor_after
+    }
+
+    fn push_edit(&mut self, edit: Edit) {
+        if matches!(self.saved_at, Some(depth) if depth > self.undo_stack.len()) {
+            // The saved state lived in the redo history we are about to discard.
+            self.saved_at = None;
+        }
+        self.redo_stack.clear();
+        self.undo_stack.push(edit);
+    }
+
+    /// Reverts the last edit and returns the cursor position before it.
+    pub fn undo(&mut self) -> Option<usize> {
+        let edit = self.undo_stack.pop()?;
+        for op in edit.ops.iter().rev() {
+            match op {
+                EditOp::Insert { at, text } => {
+                    self.rope.remove(*at..*at + text.chars().count());
+                }
+                EditOp::Remove { at, text } => self.rope.insert(*at, text),
+            }
+        }
+        let cursor = edit.cursor_before;
+        self.redo_stack.push(edit);
+        Some(cursor)
+    }
+
+    /// Re-applies the last undone edit and returns the cursor position after it.
+    pub fn redo(&mut self) -> Option<usize> {
+        let edit = self.redo_stack.pop()?;
+        for op in &edit.ops {
+            match op {
+                EditOp::Insert { at, text } => self.rope.insert(*at, text),
+                EditOp::Remove { at, text } => {
+                    self.rope.remove(*at..*at + text.chars().count());
+                }
+            }
+        }
+        let cursor = edit.cursor_after;
+        self.undo_stack.push(edit);
+        Some(cursor)
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Types `text` one char at a time from `at`, like the editor does.
+    fn type_text(buffer: &mut TextBuffer, mut at: usize, text: &str) -> usize {
+        for c in text.chars() {
+            at = buffer.insert(at, &c.to_string(), at);
+        }
+        at
+    }
+
+    #[test]
+    fn typed_chars_are_one_undo_step_until_a_newline() {
+        let mut buffer = TextBuffer::new();
+        let cursor = type_text(&mut buffer, 0, "ab\ncd");
+        assert_eq!(cursor, 5);
+        assert_eq!(buffer.undo(), Some(3));
+        assert_eq!(buffer.text(), "ab\n");
+        assert_eq!(buffer.undo(), Some(2));
+        assert_eq!(buffer.text(), "ab");
+        assert_eq!(buffer.undo(), Some(0));
+        assert_eq!(buffer.text(), "");
+        assert_eq!(buffer.undo(), None);
+    }
+
+    #[test]
+    fn typing_elsewhere_starts_a_new_step() {
+        let mut buffer = TextBuffer::new();
+        type_text(&mut buffer, 0, "ac");
+        type_text(&mut buffer, 1, "b");
+        assert_eq!(buffer.text(), "abc");
+        buffer.undo();
+        assert_eq!(buffer.text(), "ac");
+    }
+
+    #[test]
+    fn redo_reapplies_and_a_new_edit_drops_it() {
+        let mut buffer = TextBuffer::new();
+        type_text(&mut buffer, 0, "hello");
+        let cursor = buffer.replace(0, 5, "bye", 5);
+        assert_eq!((buffer.text().as_str(), cursor), ("bye", 3));
+        assert_eq!(buffer.undo(), Some(5));
+        assert_eq!(buffer.text(), "hello");
+        assert_eq!(buffer.redo(), Some(3));
+        assert_eq!(buffer.text(), "bye");
+
+        buffer.undo();
+        buffer.remove(0, 1, 1);
+        assert_eq!(buffer.text(), "ello");
+        assert_eq!(buffer.redo(), None);
+    }
+
+    #[test]
+    fn dirty_follows_the_saved_point_through_undo_and_redo() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        let mut buffer = TextBuffer::from_file(path.clone()).unwrap();
+        assert!(!buffer.is_dirty());
+
+        type_text(&mut buffer, 0, "one");
+        assert!(buffer.is_dirty());
+        buffer.save().unwrap();
+        assert!(!buffer.is_dirty());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one");
+
+        // Typing after a save is a step of its own, so undo gets back to it.
+        type_text(&mut buffer, 3, "two");
+        assert!(buffer.is_dirty());
+        buffer.undo();
+        assert!(!buffer.is_dirty());
+        buffer.redo();
+        assert!(buffer.is_dirty());
+        buffer.undo();
+        buffer.undo();
+        assert!(buffer.is_dirty());
+
+        // Editing from there discards the history containing the saved state.
+        type_text(&mut buffer, 0, "x");
+        buffer.undo();
+        assert_eq!(buffer.text(), "");
+        assert!(buffer.is_dirty());
+    }
+
+    #[test]
+    fn positions_count_chars_not_bytes() {
+        let mut buffer = TextBuffer::new();
+        let cursor = buffer.insert(0, "héllo\n日本語", 0);
+        assert_eq!(cursor, 9);
+        assert_eq!(buffer.line_len(0), 5);
+        assert_eq!(buffer.line_text(1), "日本語");
+
+        buffer.remove(1, 2, 2);
+        assert_eq!(buffer.line_text(0), "hllo");
+        let cursor = buffer.replace(6, 7, "🦀", 7);
+        assert_eq!(cursor, 7);
+        assert_eq!(buffer.text(), "hllo\n日🦀語");
+        assert_eq!(buffer.char_at(6), Some('🦀'));
+
+        buffer.undo();
+        buffer.undo();
+        assert_eq!(buffer.text(), "héllo\n日本語");
+    }
+
+    #[test]
+    fn notices_writes_by_other_programs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        fs::write(&path, "old").unwrap();
+        let mut buffer = TextBuffer::from_file(path.clone()).unwrap();
+        assert!(!buffer.changed_on_disk());
+
+        let later = SystemTime::now() + std::time::Duration::from_secs(5);
+        fs::write(&path, "new").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        assert!(buffer.changed_on_disk());
+        buffer.reload().unwrap();
+        assert_eq!(buffer.text(), "new");
+        assert!(!buffer.changed_on_disk());
+    }
+}
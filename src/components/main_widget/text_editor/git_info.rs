@@ -0,0 +1,257 @@
+//! Git annotations for the built-in editor: per-line change markers relative to
+//! HEAD and an optional blame column. Both describe the file as saved on disk and
+//! are computed on a background thread, replacing the shown ones once ready.
+
+use chrono::{DateTime, Local};
+use git2::{BlameOptions, DiffOptions, Oid, Patch, Repository};
+use std::collections::{hash_map::Entry, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the file and HEAD are checked for changes.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum LineChange {
+    Added,
+    Modified,
+    /// Lines were deleted right after this one.
+    Deleted,
+}
+
+pub struct BlameLine {
+    /// Zero for lines that are not committed yet.
+    pub id: Oid,
+    pub author: String,
+    pub date: String,
+}
+
+impl BlameLine {
+    fn uncommitted() -> Self {
+        Self {
+            id: Oid::zero(),
+            author: "Uncommitted".to_string(),
+            date: String::new(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct GitInfo {
+    /// Indexed by 0-based line; empty outside a repository.
+    pub changes: Vec<Option<LineChange>>,
+    /// One entry per line while the blame column is shown.
+    pub blame: Option<Vec<BlameLine>>,
+    /// File modification time and HEAD the annotations were computed for.
+    stamp: Option<(Option<SystemTime>, Option<Oid>)>,
+    last_check: Option<Instant>,
+    /// The computation in flight; a newer one replaces it.
+    pending: Option<Receiver<Annotations>>,
+}
+
+/// What one background computation produced.
+struct Annotations {
+    changes: Vec<Option<LineChange>>,
+    /// `None` when blame was not asked for.
+    blame: Option<Vec<BlameLine>>,
+}
+
+impl GitInfo {
+    /// Takes in finished annotations and starts recomputing them when the file or
+    /// HEAD changed since last time.
+    pub fn refresh_if_stale(&mut self, path: &Path) {
+        self.receive();
+        if self
+            .last_check
+            .is_some_and(|checked| checked.elapsed() < CHECK_INTERVAL)
+        {
+            return;
+        }
+        self.last_check = Some(Instant::now());
+        let mtime = fs::metadata(path).and_then(|m| m.modified()).ok();
+        let head = Repository::discover(path.parent().unwrap_or(path))
+            .ok()
+            .and_then(|r| r.head().ok().and_then(|h| h.target()));
+        let stamp = Some((mtime, head));
+        if stamp == self.stamp {
+            return;
+        }
+        self.stamp = stamp;
+        self.spawn(path);
+    }
+
+    /// Turns the blame column on or off. The column stays empty until the
+    /// background blame finishes.
+    pub fn set_blame(&mut self, path: &Path, enabled: bool) {
+        self.blame = enabled.then(Vec::new);
+        if enabled {
+            self.spawn(path);
+        }
+    }
+
+    fn spawn(&mut self, path: &Path) {
+        let (tx, rx) = mpsc::channel();
+        self.pending = Some(rx);
+        let path = path.to_path_buf();
+        let with_blame = self.blame.is_some();
+        thread::spawn(move || {
+            let _ = tx.send(compute(&path, with_blame));
+        });
+    }
+
+    fn receive(&mut self) {
+        let Some(rx) = &self.pending else {
+            return;
+        };
+        let annotations = match rx.try_recv() {
+            Ok(annotations) => annotations,
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                self.pending = None;
+                return;
+            }
+        };
+        self.pending = None;
+        self.changes = annotations.changes;
+        // Blame turned off in the meantime stays off.
+        if let (Some(blame), Some(computed)) = (&mut self.blame, annotations.blame) {
+            *blame = computed;
+        }
+    }
+}
+
+fn compute(path: &Path, with_blame: bool) -> Annotations {
+    let mut annotations = Annotations {
+        changes: Vec::new(),
+        blame: with_blame.then(Vec::new),
+    };
+    let Some((repo, relative)) = repo_and_relative(path) else {
+        return annotations;
+    };
+    let contents = fs::read(path).unwrap_or_default();
+    let (changes, origins) = diff_head(&repo, &relative, &contents).unwrap_or_default();
+    annotations.changes = changes;
+    if let Some(blame) = &mut annotations.blame {
+        *blame = blame_lines(&repo, &relative, &origins).unwrap_or_default();
+    }
+    annotations
+}
+
+/// The repository containing `path` and the path relative to its working tree.
+fn repo_and_relative(path: &Path) -> Option<(Repository, PathBuf)> {
+    let repo = Repository::discover(path.parent().unwrap_or(path)).ok()?;
+    let workdir = repo.workdir()?.canonicalize().ok()?;
+    let absolute = path.canonicalize().ok()?;
+    let relative = absolute.strip_prefix(&workdir).ok()?.to_path_buf();
+    Some((repo, relative))
+}
+
+/// Change markers and HEAD line origins, see [`diff_head`].
+type HeadDiff = (Vec<Option<LineChange>>, Vec<Option<usize>>);
+
+/// Compares `contents` with the file in HEAD. Returns a marker per changed line and,
+/// for every line, the 0-based line in HEAD it is unchanged from.
+fn diff_head(repo: &Repository, relative: &Path, contents: &[u8]) -> Result<HeadDiff, git2::Error> {
+    let line_count = contents.split(|&b| b == b'\n').count();
+    let mut changes = vec![None; line_count];
+    if repo.is_path_ignored(relative)? {
+        return Ok((Vec::new(), Vec::new()));
+    }
+    let head_blob = repo
+        .head()
+        .and_then(|h| h.peel_to_tree())
+        .and_then(|tree| tree.get_path(relative))
+        .and_then(|entry| repo.find_blob(entry.id()));
+    let Ok(head_blob) = head_blob else {
+        // Not in HEAD yet: everything is new.
+        changes.fill(Some(LineChange::Added));
+        return Ok((changes, vec![None; line_count]));
+    };
+
+    let mut opts = DiffOptions::new();
+    opts.context_lines(0);
+    let patch = Patch::from_blob_and_buffer(&head_blob, None, contents, None, Some(&mut opts))?;
+    let mut origins = Vec::with_capacity(line_count);
+    let mut old_line = 0;
+    for h in 0..patch.num_hunks() {
+        let (hunk, _) = patch.hunk(h)?;
+        let (old_lines, new_lines) = (hunk.old_lines() as usize, hunk.new_lines() as usize);
+        // With no context, an empty range names the line before the change.
+        let first = if new_lines == 0 {
+            hunk.new_start() as usize
+        } else {
+            hunk.new_start() as usize - 1
+        };
+        while origins.len() < first {
+            origins.push(Some(old_line));
+            old_line += 1;
+        }
+        old_line += old_lines;
+        if new_lines == 0 {
+            if let Some(marker) = changes.get_mut(first.saturating_sub(1)) {
+                *marker = Some(LineChange::Deleted);
+            }
+            continue;
+        }
+        let kind = if old_lines == 0 {
+            LineChange::Added
+        } else {
+            LineChange::Modified
+        };
+        for marker in changes.iter_mut().skip(first).take(new_lines) {
+            *marker = Some(kind);
+        }
+        origins.resize(first + new_lines, None);
+    }
+    while origins.len() < line_count {
+        origins.push(Some(old_line));
+        old_line += 1;
+    }
+    Ok((changes, origins))
+}
+
+/// Who last changed each line, given the line in HEAD each one comes from.
+fn blame_lines(
+    repo: &Repository,
+    relative: &Path,
+    origins: &[Option<usize>],
+) -> Result<Vec<BlameLine>, git2::Error> {
+    let blame = repo.blame_file(relative, Some(&mut BlameOptions::new()))?;
+    // Blame hunks may carry no signature, so authors come from the commits.
+    let mut authors: HashMap<Oid, (String, String)> = HashMap::new();
+    let mut lines = Vec::with_capacity(origins.len());
+    for origin in origins {
+        let Some(id) = origin
+            .and_then(|line| blame.get_line(line + 1))
+            .map(|hunk| hunk.final_commit_id())
+            .filter(|id| !id.is_zero())
+        else {
+            lines.push(BlameLine::uncommitted());
+            continue;
+        };
+        let (author, date) = match authors.entry(id) {
+            Entry::Occupied(entry) => entry.get().clone(),
+            Entry::Vacant(entry) => {
+                let commit = repo.find_commit(id)?;
+                let author = commit.author();
+                entry
+                    .insert((
+                        author.name().unwrap_or("").to_string(),
+                        format_date(&author.when()),
+                    ))
+                    .clone()
+            }
+        };
+        lines.push(BlameLine { id, author, date });
+    }
+    Ok(lines)
+}
+
+fn format_date(time: &git2::Time) -> String {
+    DateTime::from_timestamp(time.seconds(), 0)
+        .map(|t| t.with_timezone(&Local).format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
@@ -12,6 +12,8 @@ pub enum NotificationType {
     Info,
     Warning,
     Error,
+    /// Ongoing work, updated in place until it is cleared.
+    Progress,
 }
 
 #[derive(Debug, Clone)]
@@ -19,6 +21,8 @@ struct Notification {
     message: String,
     ntype: NotificationType,
     created_at: Instant,
+    /// Identifies a progress notification so updates replace it.
+    key: Option<&'static str>,
 }
 
 impl Notification {
@@ -27,6 +31,7 @@ impl Notification {
             message,
             ntype,
             created_at: Instant::now(),
+            key: None,
         }
     }
 }
@@ -47,10 +52,27 @@ impl NotificationManager {
         self.notifications.push(Notification::new(message, ntype));
     }
 
+    fn set_progress(&mut self, key: &'static str, message: String) {
+        if let Some(existing) = self.notifications.iter_mut().find(|n| n.key == Some(key)) {
+            existing.message = message;
+            return;
+        }
+        self.add(message, NotificationType::Progress);
+        if let Some(added) = self.notifications.last_mut() {
+            added.key = Some(key);
+        }
+    }
+
+    fn clear_progress(&mut self, key: &'static str) {
+        self.notifications.retain(|n| n.key != Some(key));
+    }
+
     fn purge_old(&mut self) {
         let now = Instant::now();
-        self.notifications
-            .retain(|n| now.duration_since(n.created_at) < Self::NOTIFICATION_LIFETIME);
+        // Progress stays until cleared, however long the work takes.
+        self.notifications.retain(|n| {
+            n.key.is_some() || now.duration_since(n.created_at) < Self::NOTIFICATION_LIFETIME
+        });
     }
 
     fn render(&mut self, f: &mut Frame, area: Rect, theme: &Theme) {
@@ -61,7 +83,7 @@ impl NotificationManager {
 
         for (i, notification) in self.notifications.iter().enumerate() {
             let block_color = match notification.ntype {
-                NotificationType::Info => theme.highlight_fg,
+                NotificationType::Info | NotificationType::Progress => theme.highlight_fg,
                 NotificationType::Warning => Color::Yellow,
                 NotificationType::Error => Color::Red,
             };
@@ -104,3 +126,17 @@ pub fn render_notifications(f: &mut Frame, area: Rect, theme: &Theme) {
         manager.render(f, area, theme);
     }
 }
+
+/// Shows or updates the progress notification identified by `key`.
+pub fn send_progress(key: &'static str, message: String) {
+    if let Ok(mut manager) = NOTIFICATION_MANAGER.lock() {
+        manager.set_progress(key, message);
+    }
+}
+
+/// Removes the progress notification identified by `key`.
+pub fn clear_progress(key: &'static str) {
+    if let Ok(mut manager) = NOTIFICATION_MANAGER.lock() {
+        manager.clear_progress(key);
+    }
+}
@@ -4,8 +4,10 @@ use ratatui::{
     widgets::{Block, Borders, List, ListItem, ListState},
 };
 
+pub mod panes;
 pub mod term;
-use crate::app::App;
+use crate::{app::App, theme::Theme};
+use term::find::TermFind;
 
 pub struct Panel;
 
@@ -40,7 +42,10 @@ impl Panel {
             let tab_titles: Vec<ListItem> = app
                 .terminals
                 .iter()
-                .map(|tab| ListItem::new(tab.title.clone()))
+                .map(|tab| match tab.content.len() {
+                    1 => ListItem::new(tab.title.clone()),
+                    panes => ListItem::new(format!("{} [{}]", tab.title, panes)),
+                })
                 .collect();
 
             let mut list_state = ListState::default();
@@ -63,19 +68,57 @@ impl Panel {
 
         // ターミナルコンテンツを描画
         if let Some(active_term_tab) = app.terminals.get_mut(app.active_terminal_tab) {
-            let border_style = if is_active {
-                Style::default().fg(app.theme.highlight_fg)
-            } else {
-                Style::default().fg(app.theme.text_fg)
-            };
-            let content_block = Block::default()
-                .borders(Borders::ALL)
-                .border_style(border_style)
-                .bg(app.theme.primary_bg)
-                .title("Terminal");
-            active_term_tab
-                .content
-                .render_with_block(f, content_area, content_block);
+            let panes = &mut active_term_tab.content;
+            let count = panes.len();
+            let focused = panes.active_index();
+            let maximized = panes.is_maximized();
+            let mut layout = panes.layout(content_area);
+            // The focused pane goes last so that its cursor is the one shown.
+            layout.sort_by_key(|(i, _)| *i == focused);
+            for (i, pane_area) in layout {
+                let is_focused = is_active && i == focused;
+                let border_style = if is_focused {
+                    Style::default().fg(app.theme.highlight_fg)
+                } else {
+                    Style::default().fg(app.theme.text_fg)
+                };
+                let title = match (count, maximized) {
+                    (1, _) => "Terminal".to_string(),
+                    (_, false) => format!("Terminal {}/{}", i + 1, count),
+                    (_, true) => format!("Terminal {}/{} (maximized)", i + 1, count),
+                };
+                let mut content_block = Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(border_style)
+                    .bg(app.theme.primary_bg)
+                    .title(title);
+                let term = panes.term_mut(i);
+                // Show that the view is not following the output.
+                let offset = term.scroll_offset();
+                if offset > 0 {
+                    content_block = content_block.title(
+                        Line::styled(
+                            format!(" ↑ {} lines back (Shift+PageDown) ", offset),
+                            Style::default()
+                                .fg(app.theme.highlight_fg)
+                                .add_modifier(Modifier::BOLD),
+                        )
+                        .right_aligned(),
+                    );
+                }
+                let find_bar = term.find().map(|find| find_bar(find, &app.theme));
+                if let Some((line, _)) = &find_bar {
+                    content_block = content_block.title_bottom(line.clone());
+                }
+                term.render_with_block(f, pane_area, content_block);
+                if let Some((_, cursor_x)) = find_bar.filter(|_| is_focused) {
+                    // Typing goes to the find bar, so the cursor is drawn there.
+                    f.set_cursor_position((
+                        pane_area.x + 1 + cursor_x,
+                        pane_area.bottom().saturating_sub(1),
+                    ));
+                }
+            }
         } else {
             // 表示するターミナルがない場合のプレースホルダー
             let block = Block::default()
@@ -86,3 +129,44 @@ impl Panel {
         }
     }
 }
+
+/// The find bar shown in the bottom border, and the column of its text cursor.
+fn find_bar(find: &TermFind, theme: &Theme) -> (Line<'static>, u16) {
+    let query = find.query();
+    let toggle = |label: &'static str, on: bool| {
+        let style = if on {
+            Style::default()
+                .fg(theme.highlight_fg)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+        Span::styled(format!("[{}]", label), style)
+    };
+    let status = match (find.error(), find.current()) {
+        // Regex errors point at the pattern over several lines; the last says what is wrong.
+        (Some(error), _) => Span::styled(
+            format!(" {} ", error.lines().last().unwrap_or(error)),
+            Style::default().fg(Color::Red),
+        ),
+        _ if query.pattern.is_empty() => Span::raw(" Esc to close "),
+        (None, _) if find.match_count() == 0 => Span::raw(" No results "),
+        (None, Some(i)) => Span::raw(format!(" {} of {} ", i + 1, find.match_count())),
+        (None, None) => Span::raw(format!(" {} results ", find.match_count())),
+    };
+    let mut spans = vec![
+        Span::raw(" Find "),
+        toggle(".*", query.use_regex),
+        toggle("Aa", query.case_sensitive),
+        toggle("ab", query.whole_word),
+        Span::raw(" "),
+        Span::styled(
+            query.pattern.clone(),
+            Style::default().fg(theme.text_fg).bg(theme.secondary_bg),
+        ),
+    ];
+    let cursor_x = spans.iter().map(|s| s.width()).sum::<usize>() as u16;
+    spans.push(Span::raw(" "));
+    spans.push(status);
+    (Line::from(spans), cursor_x)
+}
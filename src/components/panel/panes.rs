@@ -0,0 +1,336 @@
+//! Terminals of one panel tab, split side by side or stacked.
+
+use super::term::Term;
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+
+/// How far one resize step moves a divider, in percent.
+const RESIZE_STEP: u16 = 5;
+/// The smallest share a pane can be resized to, in percent.
+const MIN_PERCENT: u16 = 10;
+
+/// A direction to move focus or a divider in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl Side {
+    fn direction(self) -> Direction {
+        match self {
+            Side::Left | Side::Right => Direction::Horizontal,
+            Side::Up | Side::Down => Direction::Vertical,
+        }
+    }
+}
+
+/// How the panes divide the area. Leaves index into `TermPanes::terms`.
+enum Node {
+    Leaf(usize),
+    Split {
+        direction: Direction,
+        /// The share of the first child.
+        percent: u16,
+        children: Box<[Node; 2]>,
+    },
+}
+
+impl Node {
+    fn contains(&self, index: usize) -> bool {
+        match self {
+            Node::Leaf(i) => *i == index,
+            Node::Split { children, .. } => children.iter().any(|c| c.contains(index)),
+        }
+    }
+
+    fn split(&mut self, index: usize, direction: Direction, new: usize) {
+        match self {
+            Node::Leaf(i) if *i == index => {
+                *self = Node::Split {
+                    direction,
+                    percent: 50,
+                    children: Box::new([Node::Leaf(index), Node::Leaf(new)]),
+                };
+            }
+            Node::Leaf(_) => {}
+            Node::Split { children, .. } => children
+                .iter_mut()
+                .for_each(|c| c.split(index, direction, new)),
+        }
+    }
+
+    /// Drops the leaf for `index`, letting the other side of its split take its
+    /// place.
+    fn remove(&mut self, index: usize) {
+        if let Node::Split { children, .. } = self {
+            match children
+                .iter()
+                .position(|c| matches!(c, Node::Leaf(i) if *i == index))
+            {
+                Some(side) => *self = std::mem::replace(&mut children[1 - side], Node::Leaf(0)),
+                None => children.iter_mut().for_each(|c| c.remove(index)),
+            }
+        }
+    }
+
+    fn renumber(&mut self, removed: usize) {
+        match self {
+            Node::Leaf(i) if *i > removed => *i -= 1,
+            Node::Leaf(_) => {}
+            Node::Split { children, .. } => children.iter_mut().for_each(|c| c.renumber(removed)),
+        }
+    }
+
+    /// Moves the divider of the split nearest to `index` that runs across
+    /// `side`. Returns `false` if there is none.
+    fn resize(&mut self, index: usize, side: Side) -> bool {
+        if !self.contains(index) {
+            return false;
+        }
+        let Node::Split {
+            direction,
+            percent,
+            children,
+        } = self
+        else {
+            return false;
+        };
+        if children.iter_mut().any(|c| c.resize(index, side)) {
+            return true;
+        }
+        if *direction != side.direction() {
+            return false;
+        }
+        *percent = match side {
+            Side::Left | Side::Up => percent.saturating_sub(RESIZE_STEP),
+            Side::Right | Side::Down => *percent + RESIZE_STEP,
+        }
+        .clamp(MIN_PERCENT, 100 - MIN_PERCENT);
+        true
+    }
+
+    fn layout(&self, area: Rect, out: &mut Vec<(usize, Rect)>) {
+        match self {
+            Node::Leaf(i) => out.push((*i, area)),
+            Node::Split {
+                direction,
+                percent,
+                children,
+            } => {
+                let chunks = Layout::default()
+                    .direction(*direction)
+                    .constraints([Constraint::Percentage(*percent), Constraint::Min(0)])
+                    .split(area);
+                children[0].layout(chunks[0], out);
+                children[1].layout(chunks[1], out);
+            }
+        }
+    }
+}
+
+/// The terminals shown together in one panel tab.
+pub struct TermPanes {
+    terms: Vec<Term>,
+    layout: Node,
+    active: usize,
+    /// Only the active pane is shown, over the whole panel.
+    maximized: bool,
+    /// Where the panes were last drawn, for moving focus.
+    area: Rect,
+}
+
+impl TermPanes {
+    pub fn new(term: Term) -> Self {
+        Self {
+            terms: vec![term],
+            layout: Node::Leaf(0),
+            active: 0,
+            maximized: false,
+            area: Rect::default(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.terms.len()
+    }
+
+    /// Whether every pane has been removed.
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    pub fn active_index(&self) -> usize {
+        self.active
+    }
+
+    pub fn active(&self) -> &Term {
+        &self.terms[self.active]
+    }
+
+    pub fn active_mut(&mut self) -> &mut Term {
+        &mut self.terms[self.active]
+    }
+
+    /// The panes on screen.
+    pub fn visible_mut(&mut self) -> impl Iterator<Item = &mut Term> {
+        let only = self.maximized.then_some(self.active);
+        self.terms
+            .iter_mut()
+            .enumerate()
+            .filter(move |(i, _)| only.is_none_or(|active| active == *i))
+            .map(|(_, term)| term)
+    }
+
+    pub fn is_maximized(&self) -> bool {
+        self.maximized
+    }
+
+    /// Puts `term` next to the active pane, to the right of it or below, and
+    /// focuses it.
+    pub fn split(&mut self, direction: Direction, term: Term) {
+        let new = self.terms.len();
+        self.terms.push(term);
+        self.layout.split(self.active, direction, new);
+        self.active = new;
+        self.maximized = false;
+    }
+
+    /// Closes the active pane, focusing the one before it.
+    pub fn close_active(&mut self) {
+        self.remove(self.active);
+    }
+
+    /// Removes the panes whose shell has exited and returns how many there were.
+    pub fn remove_dead(&mut self) -> usize {
+        let mut removed = 0;
+        for i in (0..self.terms.len()).rev() {
+            if self.terms[i].is_dead() {
+                self.remove(i);
+                removed += 1;
+            }
+        }
+        removed
+    }
+
+    fn remove(&mut self, index: usize) {
+        self.terms.remove(index);
+        if self.terms.is_empty() {
+            return;
+        }
+        self.layout.remove(index);
+        self.layout.renumber(index);
+        if self.active >= index {
+            self.active = self.active.saturating_sub(1);
+        }
+        if self.terms.len() == 1 {
+            self.maximized = false;
+        }
+    }
+
+    pub fn toggle_maximized(&mut self) {
+        self.maximized = !self.maximized && self.terms.len() > 1;
+    }
+
+    /// Moves the divider nearest to the active pane that runs across `side`.
+    pub fn resize(&mut self, side: Side) {
+        self.layout.resize(self.active, side);
+    }
+
+    /// Focuses the pane next to the active one on `side`, preferring the one
+    /// that overlaps it the most.
+    pub fn focus(&mut self, side: Side) {
+        let rects = self.layout_in(self.area);
+        let Some(&(_, from)) = rects.iter().find(|(i, _)| *i == self.active) else {
+            return;
+        };
+        let overlap = |a: u16, a_len: u16, b: u16, b_len: u16| {
+            (a + a_len).min(b + b_len).saturating_sub(a.max(b))
+        };
+        let next = rects
+            .iter()
+            .filter(|(_, r)| match side {
+                Side::Left => r.right() <= from.left(),
+                Side::Right => r.left() >= from.right(),
+                Side::Up => r.bottom() <= from.top(),
+                Side::Down => r.top() >= from.bottom(),
+            })
+            .filter_map(|&(i, r)| {
+                let (distance, shared) = match side {
+                    Side::Left => (
+                        from.left() - r.right(),
+                        overlap(r.y, r.height, from.y, from.height),
+                    ),
+                    Side::Right => (
+                        r.left() - from.right(),
+                        overlap(r.y, r.height, from.y, from.height),
+                    ),
+                    Side::Up => (
+                        from.top() - r.bottom(),
+                        overlap(r.x, r.width, from.x, from.width),
+                    ),
+                    Side::Down => (
+                        r.top() - from.bottom(),
+                        overlap(r.x, r.width, from.x, from.width),
+                    ),
+                };
+                (shared > 0).then_some((distance, std::cmp::Reverse(shared), i))
+            })
+            .min()
+            .map(|(_, _, i)| i);
+        if let Some(i) = next {
+            self.active = i;
+            self.maximized = false;
+        }
+    }
+
+    /// Focuses the pane at `(column, row)`. Returns `false` if there is none.
+    pub fn focus_at(&mut self, column: u16, row: u16) -> bool {
+        match self.index_at(column, row) {
+            Some(i) => {
+                self.active = i;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The pane whose screen is at `(column, row)`.
+    pub fn term_at(&self, column: u16, row: u16) -> Option<&Term> {
+        self.index_at(column, row).map(|i| &self.terms[i])
+    }
+
+    fn index_at(&self, column: u16, row: u16) -> Option<usize> {
+        self.visible()
+            .into_iter()
+            .find(|&i| self.terms[i].contains(column, row))
+    }
+
+    fn visible(&self) -> Vec<usize> {
+        if self.maximized {
+            vec![self.active]
+        } else {
+            (0..self.terms.len()).collect()
+        }
+    }
+
+    /// Where each visible pane goes in `area`, remembering `area` for moving focus.
+    pub fn layout(&mut self, area: Rect) -> Vec<(usize, Rect)> {
+        self.area = area;
+        if self.maximized {
+            return vec![(self.active, area)];
+        }
+        self.layout_in(area)
+    }
+
+    fn layout_in(&self, area: Rect) -> Vec<(usize, Rect)> {
+        let mut out = Vec::with_capacity(self.terms.len());
+        self.layout.layout(area, &mut out);
+        out
+    }
+
+    pub fn term_mut(&mut self, index: usize) -> &mut Term {
+        &mut self.terms[index]
+    }
+}
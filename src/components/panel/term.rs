@@ -1,34 +1,50 @@
-use crate::event_handler::PtyInput;
-use anyhow::Result;
-use portable_pty::{native_pty_system, CommandBuilder, MasterPty, PtySize};
-use std::env;
+use crate::{
+    command_line::{CommandLine, TemplateContext},
+    components::pty_screen::{self, PtyScreen, Selection},
+    event_handler::PtyInput,
+    settings::Config,
+};
+use anyhow::{anyhow, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use portable_pty::{native_pty_system, MasterPty, PtySize};
+use ratatui::layout::{Position, Rect};
 use std::io::{Read, Write};
 use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::thread;
 use tui_term::vt100::Parser;
-use tui_term::widget::PseudoTerminal;
+use tui_term::widget::{Cursor, PseudoTerminal};
+
+pub mod find;
+use find::{Match, TermFind};
+
+/// Upper bound for `terminal_scrollback`; the view is drawn with `u16` rows.
+const MAX_SCROLLBACK: usize = u16::MAX as usize;
 
 pub struct Term {
     parser: Arc<Mutex<Parser>>,
     writer: Arc<Mutex<Box<dyn Write + Send>>>, // 追加
     _pty: Box<dyn MasterPty + Send>,           // 保持しておくことでdropされないように
     dead: Arc<AtomicBool>,                     // ← 追加
-}
-
-impl Default for Term {
-    fn default() -> Self {
-        Self::new(None).expect("Failed to create default Term") // Or handle error appropriately
-    }
+    cwd: Option<PathBuf>,
+    /// Where the screen was last drawn, for mouse hit testing and paging.
+    area: Rect,
+    selection: Option<Selection>,
+    find: Option<TermFind>,
 }
 
 impl Term {
-    pub fn new(cwd: Option<PathBuf>) -> Result<Self> {
-        // Changed to anyhow::Result
+    /// Spawns the configured shell, falling back to `$SHELL` and `/bin/sh`.
+    pub fn new(cwd: Option<PathBuf>, config: &Config) -> Result<Self> {
         // SHELL取得
-        let shell = env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
+        let ctx = TemplateContext {
+            workspace: cwd.as_deref(),
+            ..Default::default()
+        };
+        let shell = CommandLine::shell(config, &ctx)?;
+        shell.ensure_program_exists()?;
         let pty_system = native_pty_system();
         let pty_pair = pty_system.openpty(PtySize {
             rows: 24,
@@ -38,14 +54,14 @@ impl Term {
         })?;
 
         // シェル起動
-        let mut cmd = CommandBuilder::new(shell);
-        if let Some(path) = cwd {
-            cmd.cwd(path);
-        }
-        let _child = pty_pair.slave.spawn_command(cmd)?;
+        let _child = pty_pair
+            .slave
+            .spawn_command(shell.to_command_builder())
+            .map_err(|e| anyhow!("Failed to spawn shell `{}`: {}", shell.program, e))?;
 
         // vt100パーサ
-        let parser = Arc::new(Mutex::new(Parser::new(24, 80, 0)));
+        let scrollback = config.terminal_scrollback.min(MAX_SCROLLBACK);
+        let parser = Arc::new(Mutex::new(Parser::new(24, 80, scrollback)));
         let dead = Arc::new(AtomicBool::new(false)); // ← ここで毎回新規
 
         // PTYからの出力をパーサに流し込むスレッド
@@ -72,6 +88,10 @@ impl Term {
             writer, // 追加
             _pty: pty_pair.master,
             dead, // ← 追加
+            cwd: shell.cwd,
+            area: Rect::default(),
+            selection: None,
+            find: None,
         })
     }
 
@@ -86,8 +106,11 @@ impl Term {
         let rows = inner_area.height.max(1); // Ensure at least 1 row
         let cols = inner_area.width.max(1); // Ensure at least 1 col
 
-        {
-            let mut parser = self.parser.lock().unwrap();
+        self.area = inner_area;
+
+        let mut parser = self.parser.lock().unwrap();
+        // Resizing forgets which rows wrapped, even to the same size.
+        if parser.screen().size() != (rows, cols) {
             parser.set_size(rows, cols);
         }
         let _ = self._pty.resize(portable_pty::PtySize {
@@ -97,13 +120,138 @@ impl Term {
             pixel_height: 0,
         });
 
-        let parser = self.parser.lock().unwrap();
-        let pseudo_term = PseudoTerminal::new(parser.screen()).block(block);
-        f.render_widget(pseudo_term, area); // area: このウィジェットのRect
-        let (cur_y, cur_x) = parser.screen().cursor_position(); // This is 1-based (y, x)
-        let cursor_x = inner_area.x + cur_x;
-        let cursor_y = inner_area.y + cur_y;
-        f.set_cursor_position((cursor_x, cursor_y));
+        let offset = parser.screen().scrollback();
+        // While scrolled back the cursor belongs to the live screen, so it is hidden.
+        pty_screen::with_view(&mut parser, offset, rows, |screen| {
+            let mut pseudo_term = PseudoTerminal::new(screen).block(block);
+            if offset > 0 {
+                let mut cursor = Cursor::default();
+                cursor.hide();
+                pseudo_term = pseudo_term.cursor(cursor);
+            }
+            f.render_widget(pseudo_term, area); // area: このウィジェットのRect
+        });
+        if let Some(find) = &mut self.find {
+            find.refresh(&mut parser);
+            find.highlight(f.buffer_mut(), inner_area, offset);
+        }
+        if let Some(selection) = &self.selection {
+            selection.highlight(f.buffer_mut(), inner_area, offset);
+        }
+        if offset == 0 {
+            let (cur_y, cur_x) = parser.screen().cursor_position(); // This is 1-based (y, x)
+            let cursor_x = inner_area.x + cur_x;
+            let cursor_y = inner_area.y + cur_y;
+            f.set_cursor_position((cursor_x, cursor_y));
+        }
+    }
+
+    /// How many lines the view is scrolled back from the live screen.
+    pub fn scroll_offset(&self) -> usize {
+        self.parser.lock().unwrap().screen().scrollback()
+    }
+
+    /// Scrolls the view `lines` towards older output, or newer output when negative.
+    /// The offset is clamped to the scrollback that exists.
+    pub fn scroll_by(&self, lines: isize) {
+        let mut parser = self.parser.lock().unwrap();
+        let offset = parser.screen().scrollback().saturating_add_signed(lines);
+        parser.set_scrollback(offset);
+    }
+
+    /// Scrolls by one screen, keeping a line of overlap.
+    pub fn scroll_page(&self, up: bool) {
+        let page = self.area.height.saturating_sub(1).max(1) as isize;
+        self.scroll_by(if up { page } else { -page });
+    }
+
+    /// Opens the find bar, keeping the previous query if it is already open.
+    pub fn open_find(&mut self) {
+        self.find.get_or_insert_with(TermFind::default);
+    }
+
+    pub fn find(&self) -> Option<&TermFind> {
+        self.find.as_ref()
+    }
+
+    /// Handles a key while the find bar is open: typing edits the query, Enter
+    /// and Up go to the match above, Down to the one below, Alt+R/C/W toggle
+    /// regex, case and whole word matching, and Esc closes the bar. Returns
+    /// `false` for keys meant for the shell.
+    pub fn handle_find_key(&mut self, key: KeyEvent) -> bool {
+        let Some(find) = &mut self.find else {
+            return false;
+        };
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+        let edited = match key.code {
+            KeyCode::Esc => {
+                self.find = None;
+                return true;
+            }
+            KeyCode::Enter if shift => return self.step_find(false),
+            KeyCode::Enter | KeyCode::Up => return self.step_find(true),
+            KeyCode::Down => return self.step_find(false),
+            KeyCode::Char(c) if alt => find.edit(|q| {
+                let toggled = match c {
+                    'r' => &mut q.use_regex,
+                    'c' => &mut q.case_sensitive,
+                    'w' => &mut q.whole_word,
+                    _ => return false,
+                };
+                *toggled = !*toggled;
+                true
+            }),
+            KeyCode::Char(c) if !ctrl => find.edit(|q| {
+                q.pattern.push(c);
+                true
+            }),
+            KeyCode::Backspace => find.edit(|q| q.pattern.pop().is_some()),
+            _ => return false,
+        };
+        if edited {
+            // Search as you type, jumping to the nearest match above the view's bottom.
+            find.refresh(&mut self.parser.lock().unwrap());
+            self.step_find(true);
+        }
+        true
+    }
+
+    /// Scrolls to the match above (`older`) or below the current one.
+    fn step_find(&mut self, older: bool) -> bool {
+        let Some(find) = &mut self.find else {
+            return false;
+        };
+        let rows = self.area.height.max(1);
+        let mut parser = self.parser.lock().unwrap();
+        find.refresh(&mut parser);
+        let offset = parser.screen().scrollback();
+        if let Some(m) = find.step(older, offset, rows) {
+            reveal(&mut parser, m, rows);
+        }
+        true
+    }
+
+    /// Whether a full-screen program is running; it has no scrollback of its own.
+    pub fn alternate_screen(&self) -> bool {
+        self.parser.lock().unwrap().screen().alternate_screen()
+    }
+
+    /// Whether `(column, row)` lies on the terminal screen as last drawn.
+    pub fn contains(&self, column: u16, row: u16) -> bool {
+        self.area.contains(Position::new(column, row))
+    }
+
+    /// The shell's current working directory, falling back to the one it was started in.
+    pub fn current_dir(&self) -> Option<PathBuf> {
+        #[cfg(target_os = "linux")]
+        if let Some(pid) = self._pty.process_group_leader() {
+            if let Ok(cwd) = std::fs::read_link(format!("/proc/{}/cwd", pid)) {
+                return Some(cwd);
+            }
+        }
+        self.cwd.clone()
     }
 
     /// プロセスが終了しているか
@@ -112,8 +260,39 @@ impl Term {
     }
 }
 
+/// Scrolls so that `m` is in view, centring it if it was not.
+fn reveal(parser: &mut Parser, m: Match, rows: u16) {
+    let offset = parser.screen().scrollback() as isize;
+    let top = -offset;
+    if m.start.line >= top && m.end.line < top + rows as isize {
+        return;
+    }
+    let centred = rows as isize / 2 - m.start.line;
+    parser.set_scrollback(centred.max(0) as usize);
+}
+
+impl PtyScreen for Term {
+    fn parser(&self) -> MutexGuard<'_, Parser> {
+        self.parser.lock().unwrap()
+    }
+
+    fn screen_area(&self) -> Rect {
+        self.area
+    }
+
+    fn selection(&self) -> Option<&Selection> {
+        self.selection.as_ref()
+    }
+
+    fn set_selection(&mut self, selection: Option<Selection>) {
+        self.selection = selection;
+    }
+}
+
 impl PtyInput for Term {
     fn send_input(&self, input: &[u8]) {
+        // Typing brings the live screen back into view.
+        self.parser.lock().unwrap().set_scrollback(0);
         if let Ok(mut writer) = self.writer.lock() {
             let _ = writer.write_all(input);
         }
@@ -0,0 +1,229 @@
+//! Finding text in a terminal's screen and scrollback.
+
+use crate::components::{
+    primary_sidebar::search::query::SearchQuery,
+    pty_screen::{self, Point},
+};
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier},
+};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+use tui_term::vt100::{Parser, Screen};
+
+/// How often new output is searched again while it keeps coming.
+const OUTPUT_SEARCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A match in the output, from `start` to `end` inclusive. It may continue over
+/// wrapped lines.
+#[derive(Clone, Copy, Debug)]
+pub struct Match {
+    pub start: Point,
+    pub end: Point,
+}
+
+/// The find bar of a terminal: the query and what it matched.
+#[derive(Default)]
+pub struct TermFind {
+    query: SearchQuery,
+    matches: Vec<Match>,
+    /// The match last scrolled to.
+    current: Option<usize>,
+    error: Option<String>,
+    /// Identifies the output `matches` were found in; `None` searches again.
+    searched: Option<u64>,
+    searched_at: Option<Instant>,
+}
+
+impl TermFind {
+    pub fn query(&self) -> &SearchQuery {
+        &self.query
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    /// The 0-based index of the current match.
+    pub fn current(&self) -> Option<usize> {
+        self.current
+    }
+
+    /// Changes the query; `edit` returns whether anything changed.
+    pub fn edit(&mut self, edit: impl FnOnce(&mut SearchQuery) -> bool) -> bool {
+        let changed = edit(&mut self.query);
+        if changed {
+            self.searched = None;
+            self.current = None;
+        }
+        changed
+    }
+
+    /// Searches again if the query or the output changed since the last search.
+    /// Changed output is searched at most every `OUTPUT_SEARCH_INTERVAL`.
+    /// Returns `true` if it searched.
+    pub fn refresh(&mut self, parser: &mut Parser) -> bool {
+        if self.searched.is_some()
+            && self
+                .searched_at
+                .is_some_and(|at| at.elapsed() < OUTPUT_SEARCH_INTERVAL)
+        {
+            return false;
+        }
+        let stamp = output_stamp(parser);
+        if self.searched == Some(stamp) {
+            return false;
+        }
+        self.searched = Some(stamp);
+        self.searched_at = Some(Instant::now());
+        self.matches.clear();
+        self.error = None;
+        if self.query.pattern.is_empty() {
+            self.current = None;
+            return true;
+        }
+        match self.query.compile() {
+            Ok(compiled) => {
+                self.matches = find_matches(parser, |line| compiled.find_in_line(line));
+            }
+            Err(e) => self.error = Some(e),
+        }
+        // New output only adds matches at the end, so the index stays put.
+        self.current = self
+            .current
+            .map(|i| i.min(self.matches.len().saturating_sub(1)))
+            .filter(|_| !self.matches.is_empty());
+        true
+    }
+
+    /// Moves to the next match above (`older`) or below the current one, wrapping
+    /// around. Without a current match, starts from the bottom of the view
+    /// scrolled back `offset` lines with `rows` rows.
+    pub fn step(&mut self, older: bool, offset: usize, rows: u16) -> Option<Match> {
+        if self.matches.is_empty() {
+            return None;
+        }
+        let last = self.matches.len() - 1;
+        let index = match self.current {
+            Some(i) if older => i.checked_sub(1).unwrap_or(last),
+            Some(i) => (i + 1) % self.matches.len(),
+            None => {
+                // The match nearest to what is on screen, searching upwards from
+                // its bottom as build errors usually come last.
+                let bottom = rows as isize - 1 - offset as isize;
+                let below = self.matches.partition_point(|m| m.start.line <= bottom);
+                if older {
+                    below.checked_sub(1).unwrap_or(last)
+                } else {
+                    below % self.matches.len()
+                }
+            }
+        };
+        self.current = Some(index);
+        Some(self.matches[index])
+    }
+
+    /// Marks the matches visible in the view `area` scrolled back `offset` lines.
+    pub fn highlight(&self, buf: &mut Buffer, area: Rect, offset: usize) {
+        for (i, m) in self.matches.iter().enumerate() {
+            let current = self.current == Some(i);
+            pty_screen::highlight_range(buf, area, offset, m.start, m.end, |cell| {
+                cell.set_fg(Color::Black);
+                if current {
+                    cell.set_bg(Color::LightRed);
+                    cell.modifier.insert(Modifier::BOLD);
+                } else {
+                    cell.set_bg(Color::Yellow);
+                }
+            });
+        }
+    }
+}
+
+/// Changes whenever the scrollback grows or the live screen changes.
+fn output_stamp(parser: &mut Parser) -> u64 {
+    let history = scrollback_len(parser);
+    let rows = parser.screen().size().0;
+    let contents = pty_screen::with_view(parser, 0, rows, Screen::contents);
+    let mut hasher = DefaultHasher::new();
+    (history, contents).hash(&mut hasher);
+    hasher.finish()
+}
+
+fn scrollback_len(parser: &mut Parser) -> usize {
+    let offset = parser.screen().scrollback();
+    parser.set_scrollback(usize::MAX);
+    let history = parser.screen().scrollback();
+    parser.set_scrollback(offset);
+    history
+}
+
+/// Runs `find` over every line of the scrollback and screen, joining lines that
+/// wrapped, and turns the byte ranges it returns into output cells. Growing the
+/// view makes vt100 forget which live rows wrapped, so there a match may not
+/// continue onto the next row.
+fn find_matches(parser: &mut Parser, find: impl Fn(&str) -> Vec<(usize, usize)>) -> Vec<Match> {
+    let (rows, cols) = parser.screen().size();
+    // The whole output is read in one view, which has at most `u16::MAX` rows.
+    let history = scrollback_len(parser).min(usize::from(u16::MAX - rows));
+    let total = history as u16 + rows;
+    pty_screen::with_view(parser, history, total, |screen| {
+        let mut matches = Vec::new();
+        let mut text = String::new();
+        // The byte offset in `text` each of its rows starts at.
+        let mut starts: Vec<(usize, u16)> = Vec::new();
+        for (row, contents) in (0..total).zip(screen.rows(0, cols)) {
+            starts.push((text.len(), row));
+            text.push_str(&contents);
+            if screen.row_wrapped(row) {
+                continue;
+            }
+            for (start, end) in find(&text) {
+                let cell_at = |byte| {
+                    let mut point = cell_at(screen, &starts, byte);
+                    point.line -= history as isize;
+                    point
+                };
+                matches.push(Match {
+                    start: cell_at(start),
+                    end: cell_at(end - 1),
+                });
+            }
+            text.clear();
+            starts.clear();
+        }
+        matches
+    })
+}
+
+/// The cell holding byte `byte` of a line put together from the rows in `starts`.
+/// The row is walked the way `Screen::rows` writes it, with blank cells as spaces.
+fn cell_at(screen: &Screen, starts: &[(usize, u16)], byte: usize) -> Point {
+    let i = starts.partition_point(|(start, _)| *start <= byte);
+    let (start, row) = starts[i.saturating_sub(1)];
+    let mut rest = byte - start;
+    let mut col = 0;
+    while let Some(cell) = screen.cell(row, col) {
+        let len = if cell.has_contents() {
+            cell.contents().len()
+        } else {
+            1
+        };
+        if rest < len {
+            break;
+        }
+        rest -= len;
+        col += if cell.is_wide() { 2 } else { 1 };
+    }
+    Point {
+        line: row as isize,
+        col,
+    }
+}
@@ -3,23 +3,41 @@ use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Clear, Paragraph},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
     Frame,
 };
 
 use crate::theme::Theme;
 
-#[derive(PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum PopupResult {
     Confirm,
+    /// Go ahead without saving, for popups created with [`Popup::save_changes`].
+    Discard,
     Cancel,
     None,
 }
 
+const CONFIRM_CHOICES: &[(&str, PopupResult)] = &[
+    (" Cancel ", PopupResult::Cancel),
+    (" Confirm ", PopupResult::Confirm),
+];
+const SAVE_CHOICES: &[(&str, PopupResult)] = &[
+    (" Cancel ", PopupResult::Cancel),
+    (" Don't Save ", PopupResult::Discard),
+    (" Save ", PopupResult::Confirm),
+];
+
 pub struct Popup {
     title: String,
     message: String,
-    confirm_selected: bool,
+    /// The buttons, from left to right.
+    choices: &'static [(&'static str, PopupResult)],
+    selected: usize,
+    /// Text field contents for popups that ask for a value, e.g. a file name.
+    input: Option<String>,
+    /// Whether the text field shows bullets instead of the entered text.
+    masked: bool,
 }
 
 impl Popup {
@@ -27,23 +45,70 @@ impl Popup {
         Self {
             title,
             message,
-            confirm_selected: true,
+            choices: CONFIRM_CHOICES,
+            selected: CONFIRM_CHOICES.len() - 1,
+            input: None,
+            masked: false,
+        }
+    }
+
+    /// A popup asking whether to save changes before going on, with Save,
+    /// Don't Save and Cancel buttons.
+    pub fn save_changes(title: String, message: String) -> Self {
+        Self {
+            choices: SAVE_CHOICES,
+            selected: SAVE_CHOICES.len() - 1,
+            ..Self::new(title, message)
+        }
+    }
+
+    /// A popup with a single-line text field, pre-filled with `initial`.
+    pub fn input(title: String, message: String, initial: String) -> Self {
+        Self {
+            input: Some(initial),
+            ..Self::new(title, message)
+        }
+    }
+
+    /// A popup asking for a password or passphrase; the input is not shown.
+    pub fn secret(title: String, message: String) -> Self {
+        Self {
+            masked: true,
+            ..Self::input(title, message, String::new())
         }
     }
 
+    /// The entered text, for popups created with [`Popup::input`].
+    pub fn input_value(&self) -> Option<&str> {
+        self.input.as_deref()
+    }
+
     pub fn handle_key(&mut self, key: KeyEvent) -> PopupResult {
+        if let Some(input) = &mut self.input {
+            match key.code {
+                KeyCode::Enter => return PopupResult::Confirm,
+                KeyCode::Esc => return PopupResult::Cancel,
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                _ => {}
+            }
+            return PopupResult::None;
+        }
         match key.code {
-            KeyCode::Left | KeyCode::Right | KeyCode::Tab => {
-                self.confirm_selected = !self.confirm_selected;
+            KeyCode::Left => {
+                self.selected = self
+                    .selected
+                    .checked_sub(1)
+                    .unwrap_or(self.choices.len() - 1);
                 PopupResult::None
             }
-            KeyCode::Enter => {
-                if self.confirm_selected {
-                    PopupResult::Confirm
-                } else {
-                    PopupResult::Cancel
-                }
+            KeyCode::Right | KeyCode::Tab => {
+                self.selected = (self.selected + 1) % self.choices.len();
+                PopupResult::None
             }
+            KeyCode::Enter => self.choices[self.selected].1,
             KeyCode::Esc => PopupResult::Cancel,
             _ => PopupResult::None,
         }
@@ -55,48 +120,61 @@ impl Popup {
             .borders(Borders::ALL)
             .style(Style::default().bg(theme.secondary_bg).fg(theme.text_fg));
 
-        let area = centered_rect(60, 20, area);
+        let height = if self.input.is_some() { 30 } else { 20 };
+        let area = centered_rect(60, height, area);
         f.render_widget(Clear, area); // Clear the area behind the popup
         f.render_widget(&block, area);
 
         let inner_area = block.inner(area);
         let layout = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Min(0), Constraint::Length(3)])
+            .constraints([
+                Constraint::Min(0),
+                Constraint::Length(if self.input.is_some() { 3 } else { 0 }),
+                Constraint::Length(3),
+            ])
             .split(inner_area);
 
         let message = Paragraph::new(self.message.as_str())
             .style(Style::default().fg(theme.text_fg))
-            .alignment(ratatui::layout::Alignment::Center);
+            .alignment(ratatui::layout::Alignment::Center)
+            .wrap(Wrap { trim: false });
         f.render_widget(message, layout[0]);
 
-        let buttons = Line::from(vec![
-            Span::styled(
-                " Cancel ",
-                Style::default()
-                    .fg(if !self.confirm_selected {
-                        theme.highlight_fg
-                    } else {
-                        theme.text_fg
-                    })
-                    .add_modifier(Modifier::BOLD),
-            ),
-            Span::raw("  "),
-            Span::styled(
-                " Confirm ",
+        if let Some(input) = &self.input {
+            let shown = if self.masked {
+                "•".repeat(input.chars().count())
+            } else {
+                input.clone()
+            };
+            let field = Paragraph::new(Line::from(vec![
+                Span::raw(shown),
+                Span::styled(" ", Style::default().bg(theme.highlight_fg)),
+            ]))
+            .block(Block::default().borders(Borders::ALL));
+            f.render_widget(field, layout[1]);
+        }
+
+        let mut buttons = Vec::new();
+        for (i, (label, _)) in self.choices.iter().enumerate() {
+            if i > 0 {
+                buttons.push(Span::raw("  "));
+            }
+            buttons.push(Span::styled(
+                *label,
                 Style::default()
-                    .fg(if self.confirm_selected {
+                    .fg(if i == self.selected {
                         theme.highlight_fg
                     } else {
                         theme.text_fg
                     })
                     .add_modifier(Modifier::BOLD),
-            ),
-        ])
-        .centered();
+            ));
+        }
+        let buttons = Line::from(buttons).centered();
         let buttons_paragraph =
             Paragraph::new(buttons).alignment(ratatui::layout::Alignment::Center);
-        f.render_widget(buttons_paragraph, layout[1]);
+        f.render_widget(buttons_paragraph, layout[2]);
     }
 }
 
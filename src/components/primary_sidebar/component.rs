@@ -1,12 +1,13 @@
 use super::{file_view::FileView, git::GitWidget, search::SearchWidget};
-use crate::theme::Theme;
+use crate::{theme::Theme, workspace::WorkspaceWalker};
 use crossterm::event::KeyEvent;
 use ratatui::{prelude::*, Frame};
+use std::path::PathBuf;
 
 pub enum PrimarySidebarComponent {
-    FileView(FileView),
-    Search(SearchWidget),
-    Git(GitWidget),
+    FileView(Box<FileView>),
+    Search(Box<SearchWidget>),
+    Git(Box<GitWidget>),
 }
 
 impl PrimarySidebarComponent {
@@ -26,9 +27,53 @@ impl PrimarySidebarComponent {
         }
     }
 
-    pub fn poll_file_changes(&mut self) {
-        if let Self::FileView(fv) = self {
-            fv.poll_file_changes();
+    /// Whether the component shows a modal popup that should receive all keys.
+    pub fn has_popup(&self) -> bool {
+        match self {
+            Self::FileView(fv) => fv.has_popup(),
+            Self::Git(g) => g.has_popup(),
+            Self::Search(_) => false,
+        }
+    }
+
+    pub fn handle_popup_key(&mut self, key: KeyEvent) {
+        match self {
+            Self::FileView(fv) => fv.handle_popup_key(key),
+            Self::Git(g) => g.handle_popup_key(key),
+            Self::Search(_) => {}
+        }
+    }
+
+    pub fn render_popup(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        match self {
+            Self::FileView(fv) => fv.render_popup(f, area, theme),
+            Self::Git(g) => g.render_popup(f, area, theme),
+            Self::Search(_) => {}
+        }
+    }
+
+    pub fn set_walker(&mut self, walker: WorkspaceWalker) {
+        match self {
+            Self::FileView(fv) => fv.set_walker(walker),
+            Self::Search(s) => s.set_walker(walker),
+            Self::Git(_) => {}
+        }
+    }
+
+    /// Collects results of background work, such as a running project search.
+    pub fn poll_background(&mut self) {
+        match self {
+            Self::Search(s) => s.poll_results(),
+            Self::Git(g) => g.poll_background(),
+            Self::FileView(_) => {}
+        }
+    }
+
+    /// Paths the file view's watcher saw change since the last call.
+    pub fn poll_file_changes(&mut self) -> Vec<PathBuf> {
+        match self {
+            Self::FileView(fv) => fv.poll_file_changes(),
+            _ => Vec::new(),
         }
     }
 
@@ -1,4 +1,12 @@
-use crate::theme::Theme;
+use crate::{
+    components::{
+        notification::{send_notification, NotificationType},
+        popup::{Popup, PopupResult},
+    },
+    file_ops,
+    theme::Theme,
+    workspace::WorkspaceWalker,
+};
 use crossterm::event::{KeyCode, KeyEvent};
 use notify::{recommended_watcher, Event as NotifyEvent, RecursiveMode, Watcher};
 use ratatui::{
@@ -6,29 +14,69 @@ use ratatui::{
     widgets::{Block, Borders, List, ListItem, ListState},
 };
 use std::{
-    path::PathBuf,
+    collections::{HashMap, HashSet},
+    path::{Component, Path, PathBuf},
     sync::mpsc::{self, Receiver},
 };
-use walkdir::WalkDir;
+
+/// A visible row of the tree.
+struct TreeEntry {
+    path: PathBuf,
+    is_dir: bool,
+    is_last: bool,
+    /// For every ancestor level, whether a vertical guide continues through this row.
+    guides: Vec<bool>,
+}
+
+/// A file operation waiting for a name or a confirmation in a popup.
+enum PendingOp {
+    /// Create a file inside the directory.
+    NewFile(PathBuf),
+    /// Create a directory inside the directory.
+    NewFolder(PathBuf),
+    Rename(PathBuf),
+    Duplicate(PathBuf),
+    Delete(PathBuf),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ClipboardMode {
+    Copy,
+    Cut,
+}
 
 pub struct FileView {
     path: PathBuf,
-    files: Vec<PathBuf>,
+    entries: Vec<TreeEntry>,
+    expanded: HashSet<PathBuf>,
+    /// Children of directories read so far; filled lazily when a directory is expanded.
+    children: HashMap<PathBuf, Vec<PathBuf>>,
+    walker: WorkspaceWalker,
     list_state: ListState,
     _watcher: Option<Box<dyn Watcher + Send>>,
     rx: Option<Receiver<Result<NotifyEvent, notify::Error>>>,
     needs_refresh: bool,
+    popup: Option<(PendingOp, Popup)>,
+    clipboard: Option<(PathBuf, ClipboardMode)>,
+    /// Renames and moves made from the tree, for the app to update open tabs.
+    moved: Vec<(PathBuf, PathBuf)>,
 }
 
 impl FileView {
-    pub fn new(path: PathBuf) -> Self {
+    pub fn new(path: PathBuf, walker: WorkspaceWalker) -> Self {
         let mut view = Self {
             path,
-            files: Vec::new(),
+            entries: Vec::new(),
+            expanded: HashSet::new(),
+            children: HashMap::new(),
+            walker,
             list_state: ListState::default(),
             _watcher: None,
             rx: None,
             needs_refresh: false,
+            popup: None,
+            clipboard: None,
+            moved: Vec::new(),
         };
         view.refresh_files();
         view.start_watching();
@@ -39,25 +87,65 @@ impl FileView {
         &self.path
     }
 
+    /// Applies new ignore and exclude settings.
+    pub fn set_walker(&mut self, walker: WorkspaceWalker) {
+        self.walker = walker;
+        self.refresh_files();
+    }
+
+    /// Re-reads every loaded directory and rebuilds the visible rows, keeping the
+    /// expansion state and the selected path.
     fn refresh_files(&mut self) {
-        self.files = WalkDir::new(&self.path)
-            .min_depth(1)
-            .max_depth(1) // Only show top-level files/dirs for simplicity
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .map(|e| e.path().to_path_buf())
-            .collect();
-        self.files.sort();
+        self.children.clear();
+        self.expanded.retain(|p| p.is_dir());
+        self.rebuild_entries();
+        self.needs_refresh = false;
+    }
 
-        if !self.files.is_empty() {
-            self.list_state.select(Some(0));
-        } else {
-            self.list_state.select(None);
+    /// Flattens the expanded part of the tree into `entries`.
+    fn rebuild_entries(&mut self) {
+        let selected = self.selected_path();
+        let mut entries = Vec::new();
+        let root = self.path.clone();
+        self.push_children(&root, &mut Vec::new(), &mut entries);
+        self.entries = entries;
+        self.select_path_or_clamp(selected.as_deref());
+    }
+
+    fn push_children(&mut self, dir: &Path, guides: &mut Vec<bool>, entries: &mut Vec<TreeEntry>) {
+        let children = self.load_children(dir);
+        let count = children.len();
+        for (i, child) in children.into_iter().enumerate() {
+            let is_last = i + 1 == count;
+            let is_dir = child.is_dir();
+            let expanded = is_dir && self.expanded.contains(&child);
+            entries.push(TreeEntry {
+                path: child.clone(),
+                is_dir,
+                is_last,
+                guides: guides.clone(),
+            });
+            if expanded {
+                guides.push(!is_last);
+                self.push_children(&child, guides, entries);
+                guides.pop();
+            }
         }
-        self.needs_refresh = false;
+    }
+
+    /// Returns the children of `dir`, directories first, reading them on first use.
+    fn load_children(&mut self, dir: &Path) -> Vec<PathBuf> {
+        if let Some(children) = self.children.get(dir) {
+            return children.clone();
+        }
+        let children = self.walker.children(dir);
+        self.children.insert(dir.to_path_buf(), children.clone());
+        children
     }
 
     fn start_watching(&mut self) {
+        self._watcher = None;
+        self.rx = None;
         let (tx, rx) = mpsc::channel();
         let mut watcher = match recommended_watcher(move |res| {
             let _ = tx.send(res);
@@ -72,12 +160,22 @@ impl FileView {
         }
     }
 
-    pub fn poll_file_changes(&mut self) {
+    /// Drains the watcher and returns the paths that changed since the last call.
+    pub fn poll_file_changes(&mut self) -> Vec<PathBuf> {
+        let mut changed = Vec::new();
         if let Some(rx) = &self.rx {
-            if rx.try_recv().is_ok() {
-                self.needs_refresh = true;
+            while let Ok(event) = rx.try_recv() {
+                // Reads show up as access events; only modifications matter here.
+                match event {
+                    Ok(event) if !event.kind.is_access() => changed.extend(event.paths),
+                    _ => {}
+                }
             }
         }
+        if !changed.is_empty() {
+            self.needs_refresh = true;
+        }
+        changed
     }
 
     pub fn refresh_if_needed(&mut self) {
@@ -86,40 +184,374 @@ impl FileView {
         }
     }
 
+    /// Makes `root` the top of the tree.
+    pub fn set_root(&mut self, root: PathBuf) {
+        if root == self.path || !root.is_dir() {
+            return;
+        }
+        let previous = std::mem::replace(&mut self.path, root);
+        self.refresh_files();
+        // When leaving a directory, keep the directory we came from selected.
+        if previous.starts_with(&self.path) {
+            self.expand_to(&previous);
+        }
+        self.start_watching();
+    }
+
+    /// Expands every ancestor of `path` below the root and selects it.
+    /// Returns `false` if `path` is outside the tree.
+    pub fn reveal(&mut self, path: &Path) -> bool {
+        if !path.starts_with(&self.path) || path == self.path {
+            return false;
+        }
+        self.expand_to(path);
+        true
+    }
+
+    fn expand_to(&mut self, path: &Path) {
+        let mut ancestor = path.parent();
+        while let Some(dir) = ancestor {
+            if !dir.starts_with(&self.path) || dir == self.path {
+                break;
+            }
+            self.expanded.insert(dir.to_path_buf());
+            ancestor = dir.parent();
+        }
+        self.rebuild_entries();
+        self.select_path_or_clamp(Some(path));
+    }
+
     pub fn handle_key(&mut self, key: KeyEvent) -> bool {
         match key.code {
-            KeyCode::Down => self.select_next(),
-            KeyCode::Up => self.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => self.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.select_previous(),
+            KeyCode::Right | KeyCode::Char('l') => self.expand_selected(),
+            KeyCode::Left | KeyCode::Char('h') => self.collapse_selected(),
+            KeyCode::Enter => self.toggle_selected(),
+            KeyCode::Char('>') => {
+                if let Some(entry) = self.selected_entry().filter(|e| e.is_dir) {
+                    let root = entry.path.clone();
+                    self.set_root(root);
+                }
+            }
+            KeyCode::Backspace | KeyCode::Char('<') => {
+                if let Some(parent) = self.path.parent() {
+                    self.set_root(parent.to_path_buf());
+                }
+            }
+            KeyCode::Char('a') => {
+                let dir = self.target_dir();
+                self.open_popup(PendingOp::NewFile(dir), "New File", "File name:", "");
+            }
+            KeyCode::Char('A') => {
+                let dir = self.target_dir();
+                self.open_popup(PendingOp::NewFolder(dir), "New Folder", "Folder name:", "");
+            }
+            KeyCode::Char('r') | KeyCode::F(2) => {
+                if let Some(path) = self.selected_path() {
+                    let name = file_name(&path);
+                    self.open_popup(
+                        PendingOp::Rename(path),
+                        "Rename",
+                        "New name or relative path:",
+                        &name,
+                    );
+                }
+            }
+            KeyCode::Char('c') => {
+                if let Some(path) = self.selected_path() {
+                    let parent = path.parent().unwrap_or(&self.path);
+                    let suggestion =
+                        file_name(&file_ops::unique_destination(parent, &file_name(&path)));
+                    self.open_popup(
+                        PendingOp::Duplicate(path),
+                        "Duplicate",
+                        "Name of the copy:",
+                        &suggestion,
+                    );
+                }
+            }
+            KeyCode::Char('d') | KeyCode::Delete => {
+                if let Some(path) = self.selected_path() {
+                    let message = format!("Move {} to the trash?", file_name(&path));
+                    self.popup = Some((
+                        PendingOp::Delete(path),
+                        Popup::new("Delete".to_string(), message),
+                    ));
+                }
+            }
+            KeyCode::Char('y') => self.set_clipboard(ClipboardMode::Copy),
+            KeyCode::Char('x') => self.set_clipboard(ClipboardMode::Cut),
+            KeyCode::Char('p') => self.paste(),
             _ => return false,
         }
         true
     }
 
+    pub fn has_popup(&self) -> bool {
+        self.popup.is_some()
+    }
+
+    /// Sends a key to the open popup and runs the pending operation when it is confirmed.
+    pub fn handle_popup_key(&mut self, key: KeyEvent) {
+        let Some((_, popup)) = &mut self.popup else {
+            return;
+        };
+        match popup.handle_key(key) {
+            PopupResult::None => {}
+            PopupResult::Cancel | PopupResult::Discard => self.popup = None,
+            PopupResult::Confirm => {
+                if let Some((op, popup)) = self.popup.take() {
+                    let input = popup.input_value().unwrap_or_default().trim().to_string();
+                    if let Err(e) = self.run_operation(op, &input) {
+                        send_notification(e.to_string(), NotificationType::Error);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn render_popup(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        if let Some((_, popup)) = &self.popup {
+            popup.render(f, area, theme);
+        }
+    }
+
+    /// Returns the (from, to) pairs of paths renamed or moved since the last call.
+    pub fn take_moved_paths(&mut self) -> Vec<(PathBuf, PathBuf)> {
+        std::mem::take(&mut self.moved)
+    }
+
+    fn open_popup(&mut self, op: PendingOp, title: &str, message: &str, initial: &str) {
+        let popup = Popup::input(title.to_string(), message.to_string(), initial.to_string());
+        self.popup = Some((op, popup));
+    }
+
+    fn run_operation(&mut self, op: PendingOp, input: &str) -> anyhow::Result<()> {
+        let destination = |base: &Path| -> anyhow::Result<PathBuf> {
+            if input.is_empty() {
+                return Err(anyhow::anyhow!("The name must not be empty"));
+            }
+            Ok(normalize(&base.join(input)))
+        };
+        let created = match op {
+            PendingOp::NewFile(dir) => {
+                let path = destination(&dir)?;
+                file_ops::create_file(&path)?;
+                path
+            }
+            PendingOp::NewFolder(dir) => {
+                let path = destination(&dir)?;
+                file_ops::create_dir(&path)?;
+                path
+            }
+            PendingOp::Rename(from) => {
+                let to = destination(from.parent().unwrap_or(&self.path))?;
+                if to == from {
+                    return Ok(());
+                }
+                file_ops::move_path(&from, &to)?;
+                self.record_move(from, to.clone());
+                to
+            }
+            PendingOp::Duplicate(from) => {
+                let to = destination(from.parent().unwrap_or(&self.path))?;
+                file_ops::copy_path(&from, &to)?;
+                to
+            }
+            PendingOp::Delete(path) => {
+                file_ops::move_to_trash(&path)?;
+                send_notification(
+                    format!("Moved {} to the trash", file_name(&path)),
+                    NotificationType::Info,
+                );
+                self.refresh_files();
+                return Ok(());
+            }
+        };
+        self.refresh_files();
+        self.reveal(&created);
+        Ok(())
+    }
+
+    fn set_clipboard(&mut self, mode: ClipboardMode) {
+        if let Some(path) = self.selected_path() {
+            let verb = match mode {
+                ClipboardMode::Copy => "Copied",
+                ClipboardMode::Cut => "Cut",
+            };
+            send_notification(
+                format!("{} {}", verb, file_name(&path)),
+                NotificationType::Info,
+            );
+            self.clipboard = Some((path, mode));
+        }
+    }
+
+    /// Copies or moves the clipboard entry into the selected directory.
+    fn paste(&mut self) {
+        let Some((from, mode)) = self.clipboard.clone() else {
+            return;
+        };
+        let dir = self.target_dir();
+        let name = file_name(&from);
+        let result = match mode {
+            ClipboardMode::Copy => {
+                let to = file_ops::unique_destination(&dir, &name);
+                file_ops::copy_path(&from, &to).map(|_| to)
+            }
+            ClipboardMode::Cut => {
+                let to = dir.join(&name);
+                if to == from {
+                    return;
+                }
+                file_ops::move_path(&from, &to).map(|_| to)
+            }
+        };
+        match result {
+            Ok(to) => {
+                if mode == ClipboardMode::Cut {
+                    self.clipboard = None;
+                    self.record_move(from, to.clone());
+                }
+                self.refresh_files();
+                self.reveal(&to);
+            }
+            Err(e) => send_notification(e.to_string(), NotificationType::Error),
+        }
+    }
+
+    fn record_move(&mut self, from: PathBuf, to: PathBuf) {
+        // Keep moved directories (and their open descendants) expanded.
+        let expanded: Vec<PathBuf> = self
+            .expanded
+            .iter()
+            .filter(|p| p.starts_with(&from))
+            .cloned()
+            .collect();
+        for path in expanded {
+            self.expanded.remove(&path);
+            if let Ok(rel) = path.strip_prefix(&from) {
+                self.expanded.insert(if rel.as_os_str().is_empty() {
+                    to.clone()
+                } else {
+                    to.join(rel)
+                });
+            }
+        }
+        self.moved.push((from, to));
+    }
+
+    /// The selected directory, or the directory containing the selected file.
+    fn target_dir(&self) -> PathBuf {
+        match self.selected_entry() {
+            Some(entry) if entry.is_dir => entry.path.clone(),
+            Some(entry) => entry
+                .path
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_else(|| self.path.clone()),
+            None => self.path.clone(),
+        }
+    }
+
+    fn expand_selected(&mut self) {
+        let Some(entry) = self.selected_entry().filter(|e| e.is_dir) else {
+            return;
+        };
+        let path = entry.path.clone();
+        if self.expanded.contains(&path) {
+            // Already open: step into the first child.
+            self.select_next();
+        } else {
+            self.expanded.insert(path);
+            self.rebuild_entries();
+        }
+    }
+
+    fn collapse_selected(&mut self) {
+        let Some(entry) = self.selected_entry() else {
+            return;
+        };
+        let path = entry.path.clone();
+        if entry.is_dir && self.expanded.remove(&path) {
+            self.rebuild_entries();
+        } else if let Some(parent) = path.parent().filter(|p| *p != self.path) {
+            let parent = parent.to_path_buf();
+            self.select_path_or_clamp(Some(&parent));
+        }
+    }
+
+    fn toggle_selected(&mut self) {
+        let Some(entry) = self.selected_entry().filter(|e| e.is_dir) else {
+            return;
+        };
+        let path = entry.path.clone();
+        if !self.expanded.remove(&path) {
+            self.expanded.insert(path);
+        }
+        self.rebuild_entries();
+    }
+
     pub fn render(&mut self, f: &mut Frame, area: Rect, is_active: bool, theme: &Theme) {
         let border_style = if is_active {
             Style::default().fg(theme.highlight_fg)
         } else {
             Style::default().fg(theme.text_fg)
         };
+        let guide_style = Style::default().fg(Color::DarkGray);
 
         let items: Vec<ListItem> = self
-            .files
+            .entries
             .iter()
-            .map(|path| {
-                let filename = path.file_name().unwrap_or_default().to_string_lossy();
-                let style = if path.is_dir() {
-                    Style::default().fg(theme.highlight_fg)
+            .map(|entry| {
+                let mut prefix = String::new();
+                for &continues in &entry.guides {
+                    prefix.push_str(if continues { "│ " } else { "  " });
+                }
+                prefix.push_str(if entry.is_last { "└ " } else { "├ " });
+
+                let filename = entry.path.file_name().unwrap_or_default().to_string_lossy();
+                let (marker, style) = if entry.is_dir {
+                    let marker = if self.expanded.contains(&entry.path) {
+                        "▾ "
+                    } else {
+                        "▸ "
+                    };
+                    (marker, Style::default().fg(theme.highlight_fg))
                 } else {
-                    Style::default().fg(theme.text_fg)
+                    ("  ", Style::default().fg(theme.text_fg))
                 };
-                ListItem::new(Span::styled(filename, style))
+                ListItem::new(Line::from(vec![
+                    Span::styled(prefix, guide_style),
+                    Span::styled(marker, style),
+                    Span::styled(filename, style),
+                ]))
             })
             .collect();
 
+        let root_name = self
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.path.to_string_lossy().to_string());
+        let title = match &self.clipboard {
+            Some((path, mode)) => format!(
+                "Files: {} [{} {}]",
+                root_name,
+                if *mode == ClipboardMode::Cut {
+                    "cut"
+                } else {
+                    "copied"
+                },
+                file_name(path)
+            ),
+            None => format!("Files: {}", root_name),
+        };
         let list = List::new(items)
             .block(
                 Block::default()
-                    .title("Files")
+                    .title(title)
                     .borders(Borders::ALL)
                     .border_style(border_style),
             )
@@ -129,14 +561,39 @@ impl FileView {
         f.render_stateful_widget(list, area, &mut self.list_state);
     }
 
+    /// Returns the selected path if it is a file; directories are expanded instead of opened.
     pub fn selected_file(&self) -> Option<PathBuf> {
-        self.list_state
-            .selected()
-            .and_then(|i| self.files.get(i).cloned())
+        self.selected_entry()
+            .filter(|e| !e.is_dir)
+            .map(|e| e.path.clone())
+    }
+
+    fn selected_entry(&self) -> Option<&TreeEntry> {
+        self.list_state.selected().and_then(|i| self.entries.get(i))
+    }
+
+    fn selected_path(&self) -> Option<PathBuf> {
+        self.selected_entry().map(|e| e.path.clone())
+    }
+
+    /// Selects `path` if it is visible; otherwise keeps the current index within bounds.
+    fn select_path_or_clamp(&mut self, path: Option<&Path>) {
+        if let Some(i) = path.and_then(|p| self.entries.iter().position(|e| e.path == p)) {
+            self.list_state.select(Some(i));
+        } else if self.entries.is_empty() {
+            self.list_state.select(None);
+        } else {
+            let i = self
+                .list_state
+                .selected()
+                .unwrap_or(0)
+                .min(self.entries.len() - 1);
+            self.list_state.select(Some(i));
+        }
     }
 
     fn select_next(&mut self) {
-        let len = self.files.len();
+        let len = self.entries.len();
         if len == 0 {
             return;
         }
@@ -145,7 +602,7 @@ impl FileView {
     }
 
     fn select_previous(&mut self) {
-        let len = self.files.len();
+        let len = self.entries.len();
         if len == 0 {
             return;
         }
@@ -156,3 +613,24 @@ impl FileView {
         self.list_state.select(Some(i));
     }
 }
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Resolves `.` and `..` without touching the file system, since the path may not exist yet.
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other.as_os_str()),
+        }
+    }
+    out
+}
@@ -1,51 +1,162 @@
+mod branches;
+mod commit;
+mod credentials;
+mod hunks;
+pub mod merge;
+mod stash;
+mod worker;
+
+use self::branches::BranchInfo;
+use self::credentials::{AuthRequest, Authenticator};
+use self::hunks::{FileDiff, HunkAction};
+use self::stash::StashEntry;
+use self::worker::{GitEvent, GitWorker, RemoteJob, StatusSnapshot};
+use crate::components::diff;
+use crate::components::main_widget::text_editor::TextEditor;
 use crate::components::notification::{send_notification, NotificationType};
+use crate::components::popup::{Popup, PopupResult};
 use crate::theme::Theme;
-use crossterm::event::{KeyCode, KeyEvent};
-use git2::{DiffFormat, DiffOptions, Repository, StatusOptions};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use git2::{ErrorCode, Repository};
 use ratatui::{
     prelude::*,
     widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
 };
-use std::env;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// File changes are left to settle for this long before the status is re-read.
+const REFRESH_DELAY: Duration = Duration::from_millis(300);
 
 enum ActiveGitInput {
     Unstaged,
     Staged,
+    /// Hunks of the file selected in one of the change lists.
+    Diff,
+    Branches,
+    Stashes,
     Commit,
     CommitButton,
     PullButton, // New: Pull button
     PushButton,
 }
 
+/// A network operation that can be retried once the user supplied credentials.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum RemoteOp {
+    Pull,
+    Push,
+}
+
+/// What the open popup is asking for. Credential prompts remember which operation
+/// to retry afterwards.
+enum GitPrompt {
+    Username(RemoteOp),
+    Password(RemoteOp),
+    Passphrase(RemoteOp),
+    TrustHost {
+        op: RemoteOp,
+        host: String,
+        fingerprint: String,
+    },
+    NewBranch,
+    RenameBranch(String),
+    DeleteBranch(BranchInfo),
+    SetUpstream(String),
+    DiscardLines,
+    StashMessage {
+        include_untracked: bool,
+    },
+    DropStash(StashEntry),
+}
+
 #[derive(Clone)]
 struct GitFile {
     path: String,
     display: String,
+    /// Unmerged after a merge or rebase stopped; Enter opens the conflict tab.
+    conflicted: bool,
+}
+
+impl GitFile {
+    /// `code` is the two-letter status shown before the path, e.g. `M ` or `??`.
+    fn new(code: &str, path: String, conflicted: bool) -> Self {
+        Self {
+            display: format!("{} {}", code, path),
+            path,
+            conflicted,
+        }
+    }
 }
 
 pub struct GitWidget {
-    commit_message: String,
+    commit_editor: TextEditor,
+    /// Replace the last commit instead of adding one.
+    amend: bool,
+    /// Contents of `commit.template`, used to prefill the message.
+    template: Option<String>,
     staged_files: Vec<GitFile>,
     unstaged_files: Vec<GitFile>,
     staged_state: ListState,
     unstaged_state: ListState,
     active_input: ActiveGitInput,
-    diff_lines: Vec<Line<'static>>,
+    file_diff: Option<FileDiff>,
+    /// (hunk, line within the hunk) under the cursor in the diff pane.
+    diff_cursor: (usize, usize),
+    /// Start of a line selection inside the cursor's hunk.
+    diff_anchor: Option<usize>,
+    diff_scroll: u16,
+    auth: Authenticator,
+    popup: Option<(GitPrompt, Popup)>,
+    branches: Vec<BranchInfo>,
+    branches_state: ListState,
+    /// Remote picked for pull and push; `None` follows the current branch's upstream.
+    remote: Option<String>,
+    stashes: Vec<StashEntry>,
+    stashes_state: ListState,
+    /// Patch of the selected stash, shown while the stash list is focused.
+    stash_patch: Vec<Line<'static>>,
+    /// "merging" or "rebasing" while one is unfinished.
+    in_progress: Option<&'static str>,
+    worker: GitWorker,
+    workdir: Option<PathBuf>,
+    /// Last error reading the repository, reported once until it changes.
+    status_error: Option<String>,
+    /// When to re-read the status after files changed on disk.
+    refresh_due: Option<Instant>,
 }
 
 impl GitWidget {
     pub fn new() -> Self {
         let mut widget = Self {
-            commit_message: String::new(),
+            commit_editor: TextEditor::new(),
+            amend: false,
+            template: None,
             staged_files: Vec::new(),
             unstaged_files: Vec::new(),
             staged_state: ListState::default(),
             unstaged_state: ListState::default(),
             active_input: ActiveGitInput::Unstaged,
-            diff_lines: Vec::new(),
+            file_diff: None,
+            diff_cursor: (0, 0),
+            diff_anchor: None,
+            diff_scroll: 0,
+            auth: Authenticator::default(),
+            popup: None,
+            branches: Vec::new(),
+            branches_state: ListState::default(),
+            remote: None,
+            stashes: Vec::new(),
+            stashes_state: ListState::default(),
+            stash_patch: Vec::new(),
+            in_progress: None,
+            worker: GitWorker::default(),
+            workdir: None,
+            status_error: None,
+            refresh_due: None,
         };
         widget.refresh_status();
+        widget.reset_commit_message();
         widget
     }
 
@@ -59,7 +170,8 @@ impl GitWidget {
             .direction(Direction::Vertical)
             .constraints([
                 Constraint::Percentage(50), // Unstaged/Staged changes area
-                Constraint::Length(3),      // Commit message input
+                Constraint::Min(0),         // Branches
+                Constraint::Length(7),      // Commit message input
                 Constraint::Length(3),      // Buttons (Commit, Pull, Push)
             ])
             .split(chunks[0]);
@@ -76,10 +188,14 @@ impl GitWidget {
             .iter()
             .map(|f| ListItem::new(f.display.as_str()))
             .collect();
+        let unstaged_title = match self.in_progress {
+            Some(state) => format!("Unstaged Changes ({})", state),
+            None => "Unstaged Changes".to_string(),
+        };
         let unstaged_list = List::new(unstaged_items)
             .block(
                 Block::default()
-                    .title("Unstaged Changes")
+                    .title(unstaged_title)
                     .borders(Borders::ALL)
                     .border_style(unstaged_border)
                     .bg(theme.primary_bg),
@@ -105,14 +221,88 @@ impl GitWidget {
             .highlight_style(Style::default().bg(theme.highlight_bg).fg(theme.text_fg));
         f.render_stateful_widget(staged_list, changes_chunks[1], &mut self.staged_state);
 
-        let commit_input = Paragraph::new(self.commit_message.as_str()).block(
-            Block::default()
-                .title("Commit Message")
-                .borders(Borders::ALL)
-                .border_style(self.get_border_style(is_active, &ActiveGitInput::Commit, theme))
-                .bg(theme.primary_bg),
+        // Branches
+        let branch_items: Vec<ListItem> = self
+            .branches
+            .iter()
+            .map(|b| {
+                let style = if b.is_remote {
+                    Style::default().fg(Color::DarkGray)
+                } else if b.is_head {
+                    Style::default()
+                        .fg(theme.highlight_fg)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                let mut spans = vec![Span::styled(
+                    format!("{} {}", if b.is_head { "*" } else { " " }, b.name),
+                    style,
+                )];
+                if let Some((ahead, behind)) = b.ahead_behind.filter(|&(a, b)| a + b > 0) {
+                    spans.push(Span::styled(
+                        format!(" ↑{} ↓{}", ahead, behind),
+                        Style::default().fg(Color::Yellow),
+                    ));
+                }
+                if let Some(upstream) = &b.upstream {
+                    spans.push(Span::styled(
+                        format!(" → {}", upstream),
+                        Style::default().fg(Color::DarkGray),
+                    ));
+                }
+                ListItem::new(Line::from(spans))
+            })
+            .collect();
+        let branches_title = format!(
+            "Branches (remote: {})",
+            self.remote.as_deref().unwrap_or("upstream")
         );
-        f.render_widget(commit_input, left_chunks[1]);
+        let branches_list = List::new(branch_items)
+            .block(
+                Block::default()
+                    .title(branches_title)
+                    .borders(Borders::ALL)
+                    .border_style(self.get_border_style(
+                        is_active,
+                        &ActiveGitInput::Branches,
+                        theme,
+                    ))
+                    .bg(theme.primary_bg),
+            )
+            .highlight_style(Style::default().bg(theme.highlight_bg).fg(theme.text_fg));
+        let refs_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(left_chunks[1]);
+        f.render_stateful_widget(branches_list, refs_chunks[0], &mut self.branches_state);
+
+        // Stashes
+        let stash_items: Vec<ListItem> = self
+            .stashes
+            .iter()
+            .map(|s| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("stash@{{{}}} ", s.index),
+                        Style::default().fg(Color::Yellow),
+                    ),
+                    Span::raw(s.message.clone()),
+                ]))
+            })
+            .collect();
+        let stashes_list = List::new(stash_items)
+            .block(
+                Block::default()
+                    .title("Stashes")
+                    .borders(Borders::ALL)
+                    .border_style(self.get_border_style(is_active, &ActiveGitInput::Stashes, theme))
+                    .bg(theme.primary_bg),
+            )
+            .highlight_style(Style::default().bg(theme.highlight_bg).fg(theme.text_fg));
+        f.render_stateful_widget(stashes_list, refs_chunks[1], &mut self.stashes_state);
+
+        self.render_commit_message(f, left_chunks[2], is_active, theme);
 
         let button_chunks = Layout::default()
             .direction(Direction::Horizontal)
@@ -121,18 +311,26 @@ impl GitWidget {
                 Constraint::Ratio(1, 3), // Pull
                 Constraint::Ratio(1, 3), // Push
             ])
-            .split(left_chunks[2]);
+            .split(left_chunks[3]);
 
-        let commit_button = Paragraph::new("Commit").alignment(Alignment::Center).block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_style(self.get_border_style(
-                    is_active,
-                    &ActiveGitInput::CommitButton,
-                    theme,
-                ))
-                .bg(theme.secondary_bg),
-        );
+        let commit_label = match (self.in_progress, self.amend) {
+            (Some("rebasing"), _) => "Continue",
+            (Some(_), _) => "Commit Merge",
+            (None, true) => "Amend",
+            (None, false) => "Commit",
+        };
+        let commit_button = Paragraph::new(commit_label)
+            .alignment(Alignment::Center)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(self.get_border_style(
+                        is_active,
+                        &ActiveGitInput::CommitButton,
+                        theme,
+                    ))
+                    .bg(theme.secondary_bg),
+            );
         let pull_button = Paragraph::new("Pull") // New: Pull button
             .alignment(Alignment::Center)
             .block(
@@ -156,33 +354,42 @@ impl GitWidget {
         f.render_widget(pull_button, button_chunks[1]);
         f.render_widget(push_button, button_chunks[2]); // Render Push button in the correct chunk
 
-        // Diff View
-        let diff_paragraph = Paragraph::new(self.diff_lines.clone()).block(
-            Block::default()
-                .title("Diff")
-                .borders(Borders::ALL)
-                .bg(theme.primary_bg),
-        );
-        f.render_widget(diff_paragraph, chunks[1]);
+        self.render_diff(f, chunks[1], is_active, theme);
     }
 
     pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if matches!(self.active_input, ActiveGitInput::Branches) && self.handle_branch_key(key) {
+            return true;
+        }
+        if matches!(self.active_input, ActiveGitInput::Stashes) && self.handle_stash_key(key) {
+            return true;
+        }
+        if matches!(self.active_input, ActiveGitInput::Diff) {
+            return self.handle_diff_key(key);
+        }
         match self.active_input {
-            ActiveGitInput::Commit => match key.code {
-                KeyCode::Char(c) => {
-                    self.commit_message.push(c);
-                    return true;
-                }
-                KeyCode::Backspace => {
-                    self.commit_message.pop();
-                    return true;
+            ActiveGitInput::Commit if key.code != KeyCode::Tab => {
+                let alt = key.modifiers.contains(KeyModifiers::ALT);
+                let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+                match key.code {
+                    KeyCode::Enter if alt => self.perform_commit(),
+                    KeyCode::Char('s') if ctrl => self.perform_commit(),
+                    KeyCode::Char('a') if alt => self.toggle_amend(),
+                    KeyCode::Char('s') if alt => self.sign_off(),
+                    _ => return self.commit_editor.handle_key(key),
                 }
-                _ => {}
-            },
+                return true;
+            }
             ActiveGitInput::Unstaged | ActiveGitInput::Staged => {
                 if self.handle_list_nav(key) {
                     return true;
                 }
+                if key.code == KeyCode::Right
+                    && self.file_diff.as_ref().is_some_and(|d| !d.hunks.is_empty())
+                {
+                    self.active_input = ActiveGitInput::Diff;
+                    return true;
+                }
                 if key.code == KeyCode::Enter {
                     match self.active_input {
                         ActiveGitInput::Unstaged => {
@@ -231,104 +438,573 @@ impl GitWidget {
         if key.code == KeyCode::Tab {
             self.active_input = match self.active_input {
                 ActiveGitInput::Unstaged => ActiveGitInput::Staged,
-                ActiveGitInput::Staged => ActiveGitInput::Commit,
+                ActiveGitInput::Staged | ActiveGitInput::Diff => ActiveGitInput::Branches,
+                ActiveGitInput::Branches => ActiveGitInput::Stashes,
+                ActiveGitInput::Stashes => ActiveGitInput::Commit,
                 ActiveGitInput::Commit => ActiveGitInput::CommitButton,
                 ActiveGitInput::CommitButton => ActiveGitInput::PullButton, // Cycle to Pull
                 ActiveGitInput::PullButton => ActiveGitInput::PushButton,   // Cycle to Push
                 ActiveGitInput::PushButton => ActiveGitInput::Unstaged, // Cycle back to Unstaged
             };
+            if matches!(self.active_input, ActiveGitInput::Stashes) {
+                self.update_stash_patch();
+            }
             return true;
         }
 
         false
     }
 
-    fn refresh_status(&mut self) {
-        self.staged_files.clear();
-        self.unstaged_files.clear();
+    /// Re-reads the repository status and the shown diff.
+    pub fn refresh(&mut self) {
+        self.refresh_status();
+        self.update_diff_view();
+    }
+
+    /// The conflicted file selected in the unstaged list, relative to the repository root.
+    pub fn selected_conflict(&self) -> Option<String> {
+        if !matches!(self.active_input, ActiveGitInput::Unstaged) {
+            return None;
+        }
+        self.unstaged_state
+            .selected()
+            .and_then(|i| self.unstaged_files.get(i))
+            .filter(|f| f.conflicted)
+            .map(|f| f.path.clone())
+    }
+
+    pub fn has_popup(&self) -> bool {
+        self.popup.is_some()
+    }
+
+    /// Sends a key to the credentials popup and retries the operation when confirmed.
+    pub fn handle_popup_key(&mut self, key: KeyEvent) {
+        let Some((_, popup)) = &mut self.popup else {
+            return;
+        };
+        match popup.handle_key(key) {
+            PopupResult::None => {}
+            PopupResult::Cancel | PopupResult::Discard => self.popup = None,
+            PopupResult::Confirm => {
+                let Some((prompt, popup)) = self.popup.take() else {
+                    return;
+                };
+                let value = popup.input_value().unwrap_or_default().to_string();
+                let op = match prompt {
+                    GitPrompt::Username(op) => {
+                        self.auth.username = Some(value.trim().to_string());
+                        self.popup = Some((
+                            GitPrompt::Password(op),
+                            Popup::secret(
+                                "Authentication".to_string(),
+                                format!("Password or token for {}:", value.trim()),
+                            ),
+                        ));
+                        return;
+                    }
+                    GitPrompt::Password(op) => {
+                        self.auth.password = Some(value);
+                        op
+                    }
+                    GitPrompt::Passphrase(op) => {
+                        self.auth.passphrase = Some(value);
+                        op
+                    }
+                    GitPrompt::TrustHost {
+                        op,
+                        host,
+                        fingerprint,
+                    } => {
+                        self.auth.trust_host(host, fingerprint);
+                        op
+                    }
+                    GitPrompt::DiscardLines => {
+                        self.apply_hunk(HunkAction::Discard);
+                        return;
+                    }
+                    branch_prompt => {
+                        self.run_branch_prompt(branch_prompt, value.trim());
+                        return;
+                    }
+                };
+                self.run_remote_op(op);
+            }
+        }
+    }
 
-        let repo_result = Repository::open(env::current_dir().unwrap_or_else(|_| ".".into()));
-        let repo = match repo_result {
-            Ok(r) => r,
-            Err(e) => {
+    pub fn render_popup(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        if let Some((_, popup)) = &self.popup {
+            popup.render(f, area, theme);
+        }
+    }
+
+    fn handle_branch_key(&mut self, key: KeyEvent) -> bool {
+        let len = self.branches.len();
+        let selected = self
+            .branches_state
+            .selected()
+            .and_then(|i| self.branches.get(i))
+            .cloned();
+        match key.code {
+            KeyCode::Down if len > 0 => {
+                let i = self.branches_state.selected().map_or(0, |i| (i + 1) % len);
+                self.branches_state.select(Some(i));
+            }
+            KeyCode::Up if len > 0 => {
+                let i =
+                    self.branches_state
+                        .selected()
+                        .map_or(0, |i| if i == 0 { len - 1 } else { i - 1 });
+                self.branches_state.select(Some(i));
+            }
+            KeyCode::Enter => {
+                if let Some(branch) = selected {
+                    self.with_repo("check out branch", |repo| branches::checkout(repo, &branch));
+                }
+            }
+            KeyCode::Char('n') => {
+                self.popup = Some((
+                    GitPrompt::NewBranch,
+                    Popup::input(
+                        "New Branch".to_string(),
+                        "Create a branch at HEAD and check it out:".to_string(),
+                        String::new(),
+                    ),
+                ));
+            }
+            KeyCode::Char('r') => {
+                if let Some(branch) = selected.filter(|b| !b.is_remote) {
+                    self.popup = Some((
+                        GitPrompt::RenameBranch(branch.name.clone()),
+                        Popup::input(
+                            "Rename Branch".to_string(),
+                            format!("New name for {}:", branch.name),
+                            branch.name,
+                        ),
+                    ));
+                }
+            }
+            KeyCode::Char('d') => {
+                if let Some(branch) = selected.filter(|b| !b.is_head) {
+                    let message = if branch.is_remote {
+                        format!(
+                            "Forget the remote-tracking branch {}?\nThe branch on the server is kept.",
+                            branch.name
+                        )
+                    } else {
+                        format!("Delete branch {}?", branch.name)
+                    };
+                    self.popup = Some((
+                        GitPrompt::DeleteBranch(branch),
+                        Popup::new("Delete Branch".to_string(), message),
+                    ));
+                }
+            }
+            KeyCode::Char('u') => {
+                if let Some(branch) = selected.filter(|b| !b.is_remote) {
+                    let initial = branch.upstream.clone().unwrap_or_else(|| {
+                        format!(
+                            "{}/{}",
+                            self.remote.as_deref().unwrap_or("origin"),
+                            branch.name
+                        )
+                    });
+                    self.popup = Some((
+                        GitPrompt::SetUpstream(branch.name.clone()),
+                        Popup::input(
+                            "Set Upstream".to_string(),
+                            format!("Upstream of {} (empty to unset):", branch.name),
+                            initial,
+                        ),
+                    ));
+                }
+            }
+            KeyCode::Char('m') => self.cycle_remote(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Switches the remote used for pull and push: upstream, then each remote.
+    fn cycle_remote(&mut self) {
+        let Ok(repo) = Repository::open(".") else {
+            return;
+        };
+        let remotes: Vec<String> = repo
+            .remotes()
+            .map(|r| r.iter().flatten().map(str::to_string).collect())
+            .unwrap_or_default();
+        self.remote = match &self.remote {
+            None => remotes.first().cloned(),
+            Some(current) => remotes
+                .iter()
+                .position(|r| r == current)
+                .and_then(|i| remotes.get(i + 1))
+                .cloned(),
+        };
+        send_notification(
+            format!(
+                "Pull/Push remote: {}",
+                self.remote
+                    .as_deref()
+                    .unwrap_or("upstream of the current branch")
+            ),
+            NotificationType::Info,
+        );
+    }
+
+    fn run_branch_prompt(&mut self, prompt: GitPrompt, value: &str) {
+        match prompt {
+            GitPrompt::NewBranch if !value.is_empty() => {
+                self.with_repo("create branch", |repo| branches::create_branch(repo, value));
+            }
+            GitPrompt::RenameBranch(old) if !value.is_empty() && value != old => {
+                self.with_repo("rename branch", |repo| {
+                    branches::rename_branch(repo, &old, value)
+                });
+            }
+            GitPrompt::StashMessage { include_untracked } => {
+                self.with_repo("stash changes", |repo| {
+                    stash::save(repo, value, include_untracked)
+                });
+            }
+            GitPrompt::DropStash(entry) => self.drop_stash(&entry),
+            GitPrompt::DeleteBranch(branch) => {
+                self.with_repo("delete branch", |repo| {
+                    branches::delete_branch(repo, &branch)
+                });
+            }
+            GitPrompt::SetUpstream(name) => {
+                let upstream = Some(value).filter(|v| !v.is_empty());
+                self.with_repo("set upstream", |repo| {
+                    branches::set_upstream(repo, &name, upstream)
+                });
+            }
+            _ => {}
+        }
+    }
+
+    /// Runs a branch operation, reports failures and refreshes the view.
+    fn with_repo(
+        &mut self,
+        action: &str,
+        op: impl FnOnce(&mut Repository) -> Result<(), git2::Error>,
+    ) {
+        match Repository::open(".") {
+            Ok(mut repo) => {
+                if let Err(e) = op(&mut repo) {
+                    send_notification(
+                        format!("Git Error: Failed to {}: {}", action, e.message()),
+                        NotificationType::Error,
+                    );
+                }
+            }
+            Err(_) => send_notification(
+                "Git Error: Not a git repository.".to_string(),
+                NotificationType::Error,
+            ),
+        }
+        self.refresh_status();
+    }
+
+    fn handle_stash_key(&mut self, key: KeyEvent) -> bool {
+        let len = self.stashes.len();
+        let selected = self
+            .stashes_state
+            .selected()
+            .and_then(|i| self.stashes.get(i))
+            .cloned();
+        match key.code {
+            KeyCode::Down if len > 0 => {
+                let i = self.stashes_state.selected().map_or(0, |i| (i + 1) % len);
+                self.stashes_state.select(Some(i));
+                self.update_stash_patch();
+            }
+            KeyCode::Up if len > 0 => {
+                let i =
+                    self.stashes_state
+                        .selected()
+                        .map_or(0, |i| if i == 0 { len - 1 } else { i - 1 });
+                self.stashes_state.select(Some(i));
+                self.update_stash_patch();
+            }
+            KeyCode::Char('s') => self.start_stash(false),
+            KeyCode::Char('S') => self.start_stash(true),
+            KeyCode::Enter | KeyCode::Char('a') => {
+                if let Some(entry) = selected {
+                    self.apply_stash_entry(&entry, false);
+                }
+            }
+            KeyCode::Char('p') => {
+                if let Some(entry) = selected {
+                    self.apply_stash_entry(&entry, true);
+                }
+            }
+            KeyCode::Char('d') => {
+                if let Some(entry) = selected {
+                    self.confirm_drop_stash(entry);
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Asks for a message and stashes the local changes.
+    pub fn start_stash(&mut self, include_untracked: bool) {
+        let message = if include_untracked {
+            "Message for the stash, including untracked files (empty for the default):"
+        } else {
+            "Message for the stash (empty for the default):"
+        };
+        self.popup = Some((
+            GitPrompt::StashMessage { include_untracked },
+            Popup::input(
+                "Stash Changes".to_string(),
+                message.to_string(),
+                String::new(),
+            ),
+        ));
+    }
+
+    /// Focuses the stash list and shows the selected stash in the diff pane.
+    pub fn focus_stashes(&mut self) {
+        self.refresh_status();
+        self.active_input = ActiveGitInput::Stashes;
+        self.update_stash_patch();
+    }
+
+    /// Applies stash `index` (0 is the newest), dropping it too with `pop`.
+    pub fn apply_stash(&mut self, index: usize, pop: bool) {
+        self.reload_status();
+        match self.stashes.get(index).cloned() {
+            Some(entry) => self.apply_stash_entry(&entry, pop),
+            None => send_notification("There are no stashes.".to_string(), NotificationType::Info),
+        }
+    }
+
+    fn apply_stash_entry(&mut self, entry: &StashEntry, pop: bool) {
+        let action = if pop { "pop stash" } else { "apply stash" };
+        self.with_repo(action, |repo| stash::apply(repo, entry, pop));
+        self.reload_status();
+    }
+
+    /// Asks before dropping the stash at `index`.
+    pub fn confirm_drop_stash_at(&mut self, index: usize) {
+        self.reload_status();
+        match self.stashes.get(index).cloned() {
+            Some(entry) => self.confirm_drop_stash(entry),
+            None => send_notification("There are no stashes.".to_string(), NotificationType::Info),
+        }
+    }
+
+    fn confirm_drop_stash(&mut self, entry: StashEntry) {
+        self.popup = Some((
+            GitPrompt::DropStash(entry.clone()),
+            Popup::new(
+                "Drop Stash".to_string(),
+                format!(
+                    "Drop stash@{{{}}} ({})?\nIts changes are lost.",
+                    entry.index, entry.message
+                ),
+            ),
+        ));
+    }
+
+    fn drop_stash(&mut self, entry: &StashEntry) {
+        self.with_repo("drop stash", |repo| stash::drop(repo, entry));
+        self.reload_status();
+    }
+
+    fn update_stash_patch(&mut self) {
+        self.stash_patch = self
+            .stashes_state
+            .selected()
+            .and_then(|i| self.stashes.get(i))
+            .and_then(|entry| {
+                let repo = Repository::open(".").ok()?;
+                let diff = stash::diff(&repo, entry.id).ok()?;
+                Some(diff::patch_lines(&diff))
+            })
+            .unwrap_or_default();
+    }
+
+    fn run_remote_op(&mut self, op: RemoteOp) {
+        match op {
+            RemoteOp::Pull => self.perform_pull(),
+            RemoteOp::Push => self.perform_push(),
+        }
+    }
+
+    /// Reports a failed fetch or push, or asks for what is needed to retry it.
+    fn remote_failed(
+        &mut self,
+        op: RemoteOp,
+        action: &str,
+        error: git2::Error,
+        request: Option<AuthRequest>,
+    ) {
+        let (prompt, popup) = match request {
+            None => {
                 send_notification(
-                    format!("Git Error: Failed to open repository: {}", e),
+                    format!("Git Error: Failed to {}: {}", action, error),
                     NotificationType::Error,
                 );
                 return;
             }
+            Some(AuthRequest::UserPass { url, username: None }) => (
+                GitPrompt::Username(op),
+                Popup::input(
+                    "Authentication".to_string(),
+                    format!("Username for {}:", url),
+                    String::new(),
+                ),
+            ),
+            Some(AuthRequest::UserPass {
+                url,
+                username: Some(username),
+            }) => {
+                self.auth.username = Some(username.clone());
+                (
+                    GitPrompt::Password(op),
+                    Popup::secret(
+                        "Authentication".to_string(),
+                        format!("Password or token for {} at {}:", username, url),
+                    ),
+                )
+            }
+            Some(AuthRequest::Passphrase { key }) => (
+                GitPrompt::Passphrase(op),
+                Popup::secret(
+                    "SSH Key".to_string(),
+                    format!("Passphrase for {}:", key.display()),
+                ),
+            ),
+            Some(AuthRequest::TrustHost { host, fingerprint }) => (
+                GitPrompt::TrustHost {
+                    op,
+                    host: host.clone(),
+                    fingerprint: fingerprint.clone(),
+                },
+                Popup::new(
+                    "Unknown Host".to_string(),
+                    format!(
+                        "The SSH host key of {} is not in known_hosts.\nSHA-256: {}\n\nTrust it for this session?",
+                        host, fingerprint
+                    ),
+                ),
+            ),
         };
+        self.popup = Some((prompt, popup));
+    }
 
-        let mut opts = StatusOptions::new();
-        opts.include_untracked(true)
-            .recurse_untracked_dirs(true)
-            .include_ignored(false); // Typically don't show ignored files in status
-
-        if let Ok(statuses) = repo.statuses(Some(&mut opts)) {
-            for entry in statuses.iter() {
-                let path = entry.path().unwrap_or("").to_string();
-                let path_clone = path.clone();
-                let status = entry.status();
-
-                // Staged changes (Index vs HEAD)
-                if status.is_index_new() {
-                    self.staged_files.push(GitFile {
-                        display: format!("A  {}", path),
-                        path,
-                    });
-                } else if status.is_index_modified() {
-                    self.staged_files.push(GitFile {
-                        display: format!("M  {}", path),
-                        path,
-                    });
-                } else if status.is_index_deleted() {
-                    self.staged_files.push(GitFile {
-                        display: format!("D  {}", path),
-                        path,
-                    });
-                } else if status.is_index_renamed() {
-                    self.staged_files.push(GitFile {
-                        display: format!("R  {}", path),
-                        path,
-                    });
-                } else if status.is_index_typechange() {
-                    self.staged_files.push(GitFile {
-                        display: format!("T  {}", path),
-                        path,
-                    });
-                }
+    /// Re-reads the status, branches and stashes in the background.
+    fn refresh_status(&mut self) {
+        self.worker.request_status();
+    }
 
-                // Unstaged changes (Working Tree vs Index)
-                if status.is_wt_new() {
-                    self.unstaged_files.push(GitFile {
-                        display: format!("?? {}", path_clone),
-                        path: path_clone,
-                    });
+    /// Re-reads the status right away, for when the next step depends on it.
+    fn reload_status(&mut self) {
+        let snapshot = self.worker.read_status_now();
+        self.apply_status(snapshot);
+    }
+
+    fn apply_status(&mut self, snapshot: StatusSnapshot) {
+        if snapshot.error.is_some() && snapshot.error != self.status_error {
+            send_notification(
+                format!(
+                    "Git Error: {}",
+                    snapshot.error.as_deref().unwrap_or_default()
+                ),
+                NotificationType::Error,
+            );
+        }
+        self.status_error = snapshot.error;
+        self.staged_files = snapshot.staged;
+        self.unstaged_files = snapshot.unstaged;
+        self.branches = snapshot.branches;
+        self.stashes = snapshot.stashes;
+        self.in_progress = snapshot.in_progress;
+        self.workdir = snapshot.workdir;
+        clamp_selection(&mut self.staged_state, self.staged_files.len());
+        clamp_selection(&mut self.unstaged_state, self.unstaged_files.len());
+        clamp_selection(&mut self.branches_state, self.branches.len());
+        clamp_selection(&mut self.stashes_state, self.stashes.len());
+        match self.active_input {
+            ActiveGitInput::Stashes => self.update_stash_patch(),
+            // Keep the cursor in the diff being browsed.
+            ActiveGitInput::Diff => {
+                if let Some((path, staged)) =
+                    self.file_diff.as_ref().map(|d| (d.path.clone(), d.staged))
+                {
+                    self.load_file_diff(&path, staged);
                 }
-                // Untracked
-                else if status.is_wt_modified() {
-                    self.unstaged_files.push(GitFile {
-                        display: format!("M  {}", path_clone),
-                        path: path_clone,
-                    });
-                } else if status.is_wt_deleted() {
-                    self.unstaged_files.push(GitFile {
-                        display: format!("D  {}", path_clone),
-                        path: path_clone,
-                    });
-                } else if status.is_wt_renamed() {
-                    self.unstaged_files.push(GitFile {
-                        display: format!("R  {}", path_clone),
-                        path: path_clone,
-                    });
-                } else if status.is_wt_typechange() {
-                    self.unstaged_files.push(GitFile {
-                        display: format!("T  {}", path_clone),
-                        path: path_clone,
-                    });
+                if self.file_diff.as_ref().is_none_or(|d| d.hunks.is_empty()) {
+                    self.leave_diff();
                 }
             }
+            _ => self.update_diff_view(),
+        }
+    }
+
+    /// Picks up finished background work and re-reads the status once file
+    /// changes have settled.
+    pub fn poll_background(&mut self) {
+        for event in self.worker.poll() {
+            match event {
+                GitEvent::Status(snapshot) => self.apply_status(*snapshot),
+                GitEvent::RemoteDone(op, failure) => {
+                    if let Some(failure) = failure {
+                        self.remote_failed(op, failure.action, failure.error, failure.request);
+                    }
+                    self.refresh_status();
+                }
+            }
+        }
+        if self.refresh_due.is_some_and(|due| Instant::now() >= due) {
+            self.refresh_due = None;
+            self.refresh_status();
+        }
+    }
+
+    /// Schedules a status refresh if any of `paths` lies in the repository.
+    pub fn files_changed(&mut self, paths: &[PathBuf]) {
+        let in_repo = match &self.workdir {
+            Some(workdir) => paths.iter().any(|p| p.starts_with(workdir)),
+            None => !paths.is_empty(),
         };
+        if in_repo && self.refresh_due.is_none() {
+            self.refresh_due = Some(Instant::now() + REFRESH_DELAY);
+        }
+    }
+
+    /// One line for the bottom bar: branch, distance to upstream, changes and
+    /// any pull or push in flight.
+    pub fn status_summary(&self) -> Option<String> {
+        self.workdir.as_ref()?;
+        let head = self.branches.iter().find(|b| b.is_head);
+        let mut summary = format!("Git: {}", head.map_or("(detached)", |b| b.name.as_str()));
+        if let Some((ahead, behind)) = head
+            .and_then(|b| b.ahead_behind)
+            .filter(|&(a, b)| a + b > 0)
+        {
+            summary.push_str(&format!(" ↑{} ↓{}", ahead, behind));
+        }
+        let changes = self.staged_files.len() + self.unstaged_files.len();
+        if changes > 0 {
+            summary.push_str(&format!(" ({} changes)", changes));
+        } else {
+            summary.push_str(" (clean)");
+        }
+        if let Some(state) = self.in_progress {
+            summary.push_str(&format!(" [{}]", state));
+        }
+        match self.worker.remote_running() {
+            Some(RemoteOp::Pull) => summary.push_str(" pulling…"),
+            Some(RemoteOp::Push) => summary.push_str(" pushing…"),
+            None => {}
+        }
+        Some(summary)
     }
 
     fn stage_file(&mut self, path: &str) {
@@ -364,327 +1040,509 @@ impl GitWidget {
 
     fn unstage_file(&mut self, path: &str) {
         if let Ok(repo) = Repository::open(".") {
-            if let Ok(head) = repo.head().and_then(|h| h.peel_to_commit()) {
-                if let Err(e) = repo.reset_default(Some(head.as_object()), [path]) {
-                    send_notification(
-                        format!("Git Error: Failed to unstage {}: {}", path, e),
-                        NotificationType::Error,
-                    );
+            let result = match repo.head() {
+                Ok(head) => head
+                    .peel_to_commit()
+                    .and_then(|head| repo.reset_default(Some(head.as_object()), [path])),
+                // Nothing is committed yet, so unstaging drops the path from the index.
+                Err(e) if matches!(e.code(), ErrorCode::UnbornBranch | ErrorCode::NotFound) => {
+                    repo.index().and_then(|mut index| {
+                        index.remove_path(Path::new(path))?;
+                        index.write()
+                    })
                 }
-                self.refresh_status();
-            } else {
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
                 send_notification(
-                    "Git Error: No HEAD commit found to unstage from.".to_string(),
+                    format!("Git Error: Failed to unstage {}: {}", path, e),
                     NotificationType::Error,
                 );
             }
+            self.refresh_status();
         }
     }
 
     fn perform_commit(&mut self) {
-        if self.commit_message.is_empty() {
+        if self.in_progress.is_some() {
+            self.finish_merge();
             return;
         }
-        if let Ok(repo) = Repository::open(".") {
-            if let (Ok(mut index), Ok(head), Ok(signature)) =
-                (repo.index(), repo.head(), repo.signature())
-            {
-                match index.write_tree() {
-                    Ok(oid) => {
-                        match head.peel_to_commit() {
-                            Ok(parent_commit) => match repo.find_tree(oid) {
-                                Ok(tree) => {
-                                    if let Err(e) = repo.commit(
-                                        Some("HEAD"),
-                                        &signature,
-                                        &signature,
-                                        &self.commit_message,
-                                        &tree,
-                                        &[&parent_commit],
-                                    ) {
-                                        send_notification(
-                                            format!("Git Error: Failed to commit: {}", e),
-                                            NotificationType::Error,
-                                        );
-                                    } else {
-                                        send_notification(
-                                            "Commit successful!".to_string(),
-                                            NotificationType::Info,
-                                        );
-                                    }
-                                }
-                                Err(e) => send_notification(
-                                    format!("Git Error: Failed to find tree: {}", e),
-                                    NotificationType::Error,
-                                ),
-                            },
-                            Err(e) => send_notification(
-                                format!("Git Error: Failed to peel HEAD to commit: {}", e),
-                                NotificationType::Error,
-                            ),
-                        }
-                        self.commit_message.clear();
-                        self.refresh_status();
-                    }
-                    Err(e) => send_notification(
-                        format!("Git Error: Failed to write index tree: {}", e),
-                        NotificationType::Error,
-                    ),
-                }
-            } else {
+        let message = commit::cleanup(&self.commit_editor.text());
+        if message.is_empty() {
+            send_notification(
+                "Aborting commit due to empty commit message.".to_string(),
+                NotificationType::Warning,
+            );
+            return;
+        }
+        if self
+            .template
+            .as_deref()
+            .is_some_and(|t| commit::cleanup(t) == message)
+        {
+            send_notification(
+                "Aborting commit; the template was not edited.".to_string(),
+                NotificationType::Warning,
+            );
+            return;
+        }
+        let amend = self.amend;
+        match Repository::open(".").and_then(|repo| commit::create(&repo, &message, amend)) {
+            Ok(oid) => {
+                let short = oid.to_string()[..7].to_string();
+                let verb = if amend { "Amended" } else { "Committed" };
+                send_notification(format!("{} {}", verb, short), NotificationType::Info);
+                self.amend = false;
+                self.reset_commit_message();
+            }
+            Err(e) => send_notification(
+                format!("Git Error: Failed to commit: {}", e.message()),
+                NotificationType::Error,
+            ),
+        }
+        self.refresh_status();
+    }
+
+    /// Empties the commit message, or fills in `commit.template`.
+    fn reset_commit_message(&mut self) {
+        self.template = Repository::open(".")
+            .ok()
+            .and_then(|repo| commit::template(&repo));
+        self.set_commit_message(self.template.clone().unwrap_or_default());
+    }
+
+    fn set_commit_message(&mut self, message: String) {
+        self.commit_editor = TextEditor::new();
+        self.commit_editor.insert_text(&message);
+        self.commit_editor.goto(1, 1);
+    }
+
+    /// Switches amending on or off. Amending starts from the last commit's message
+    /// unless one was already typed.
+    fn toggle_amend(&mut self) {
+        let Ok(repo) = Repository::open(".") else {
+            return;
+        };
+        let current = commit::cleanup(&self.commit_editor.text());
+        let untouched = current.is_empty()
+            || self
+                .template
+                .as_deref()
+                .is_some_and(|t| commit::cleanup(t) == current);
+        let head_message = commit::head_message(&repo);
+        if !self.amend {
+            let Some(head_message) = head_message else {
                 send_notification(
-                    "Git Error: Failed to get repo components for commit.".to_string(),
-                    NotificationType::Error,
+                    "There is no commit to amend yet.".to_string(),
+                    NotificationType::Warning,
                 );
+                return;
+            };
+            self.amend = true;
+            if untouched {
+                self.set_commit_message(head_message);
             }
         } else {
-            send_notification(
-                "Git Error: Not a git repository.".to_string(),
+            self.amend = false;
+            if head_message.is_some_and(|m| commit::cleanup(&m) == current) {
+                self.reset_commit_message();
+            }
+        }
+    }
+
+    /// Adds a `Signed-off-by` trailer with the configured name and email.
+    fn sign_off(&mut self) {
+        match Repository::open(".").and_then(|repo| repo.signature()) {
+            Ok(signature) => {
+                let message = commit::sign_off(&self.commit_editor.text(), &signature);
+                self.set_commit_message(message);
+            }
+            Err(e) => send_notification(
+                format!("Git Error: No user identity for sign-off: {}", e.message()),
                 NotificationType::Error,
-            );
+            ),
+        }
+    }
+
+    fn render_commit_message(&mut self, f: &mut Frame, area: Rect, is_active: bool, theme: &Theme) {
+        let text = self.commit_editor.text();
+        let summary_len = text.lines().next().map_or(0, |l| l.chars().count());
+        // Git convention: a summary of at most 50 columns (72 at worst), then a blank line.
+        let summary_style = match summary_len {
+            0..=50 => Style::default().fg(Color::DarkGray),
+            51..=72 => Style::default().fg(Color::Yellow),
+            _ => Style::default().fg(Color::Red),
+        };
+        let mut title = vec![
+            Span::raw(if self.amend {
+                "Commit Message [amend] "
+            } else {
+                "Commit Message "
+            }),
+            Span::styled(format!("{}/50", summary_len), summary_style),
+        ];
+        if text
+            .lines()
+            .nth(1)
+            .is_some_and(|l| !l.trim().is_empty() && !l.starts_with('#'))
+        {
+            title.push(Span::styled(
+                " - leave line 2 blank",
+                Style::default().fg(Color::Yellow),
+            ));
+        }
+        let block = Block::default()
+            .title(Line::from(title))
+            .borders(Borders::ALL)
+            .border_style(self.get_border_style(is_active, &ActiveGitInput::Commit, theme))
+            .bg(theme.primary_bg);
+        if is_active && matches!(self.active_input, ActiveGitInput::Commit) {
+            self.commit_editor.render_with_block(f, area, block, theme);
+        } else {
+            // Only the focused editor may place the terminal cursor.
+            f.render_widget(Paragraph::new(text).block(block), area);
         }
     }
 
+    /// The remote and remote branch to pull from and push to: the remote chosen in
+    /// the branches section, else the upstream of the current branch, else `origin`.
+    fn remote_target(&self, repo: &Repository) -> Option<(String, String, String)> {
+        let (local, upstream) = branches::head_and_upstream(repo)?;
+        let (remote, branch) = match (&self.remote, upstream) {
+            (Some(remote), Some((up_remote, up_branch))) if *remote == up_remote => {
+                (remote.clone(), up_branch)
+            }
+            (Some(remote), _) => (remote.clone(), local.clone()),
+            (None, Some(upstream)) => upstream,
+            (None, None) => ("origin".to_string(), local.clone()),
+        };
+        Some((local, remote, branch))
+    }
+
     fn perform_pull(&mut self) {
-        if let Ok(repo) = Repository::open(".") {
-            if let Ok(mut remote) = repo.find_remote("origin") {
-                let callbacks = git2::RemoteCallbacks::new();
-                // TODO: Add credentials callback for private repos
-                let mut fo = git2::FetchOptions::new();
-                fo.remote_callbacks(callbacks);
-
-                let branch_name = match repo.head() {
-                    // Correctly handle Result/Option
-                    Ok(head) => head.shorthand().map(|s| s.to_string()),
-                    Err(_) => None,
-                }
-                .unwrap_or_else(|| "main".to_string());
-
-                let refspec = format!("refs/heads/{}:refs/heads/{}", branch_name, branch_name);
-
-                match remote.fetch(&[refspec], Some(&mut fo), None) {
-                    Ok(_) => {
-                        send_notification("Fetch successful!".to_string(), NotificationType::Info);
-                        // Attempt to merge
-                        if let Ok(fetch_head) = repo.find_reference("FETCH_HEAD") {
-                            // Correctly find FETCH_HEAD
-                            if let Ok(fetch_commit) =
-                                repo.reference_to_annotated_commit(&fetch_head)
-                            {
-                                // Use merge_analysis, not merge_analysis_for_ref
-                                if let Ok((analysis, _)) = repo.merge_analysis(&[&fetch_commit]) {
-                                    if analysis.is_up_to_date() {
-                                        send_notification(
-                                            "Already up-to-date.".to_string(),
-                                            NotificationType::Info,
-                                        );
-                                    } else if analysis.is_fast_forward() {
-                                        send_notification(
-                                            "Performing fast-forward merge...".to_string(),
-                                            NotificationType::Info,
-                                        );
-                                        // More robust fast-forward merge without panics
-                                        if let Ok(mut head_ref) = repo.head() {
-                                            let target_oid = fetch_commit.id();
-                                            if let Err(e) = head_ref
-                                                .set_target(target_oid, "fast-forward merge")
-                                            {
-                                                send_notification(
-                                                    format!(
-                                                        "Git Error: Failed to set target: {}",
-                                                        e
-                                                    ),
-                                                    NotificationType::Error,
-                                                );
-                                            } else if let Some(head_name) = head_ref.name() {
-                                                if let Err(e) = repo.set_head(head_name) {
-                                                    send_notification(
-                                                        format!(
-                                                            "Git Error: Failed to set HEAD: {}",
-                                                            e
-                                                        ),
-                                                        NotificationType::Error,
-                                                    );
-                                                } else if let Err(e) = repo.checkout_head(Some(
-                                                    git2::build::CheckoutBuilder::new().force(),
-                                                )) {
-                                                    send_notification(format!("Git Error: Failed to checkout HEAD: {}", e), NotificationType::Error);
-                                                } else {
-                                                    send_notification(
-                                                        "Merge successful.".to_string(),
-                                                        NotificationType::Info,
-                                                    );
-                                                }
-                                            }
-                                        } else {
-                                            send_notification(
-                                                "Git Error: Could not get HEAD reference."
-                                                    .to_string(),
-                                                NotificationType::Error,
-                                            );
-                                        }
-                                    } else if analysis.is_normal() {
-                                        send_notification(
-                                            "Merge required. Please resolve conflicts manually."
-                                                .to_string(),
-                                            NotificationType::Warning,
-                                        );
-                                        // Normal merge (requires merge commit) - not automatically handled here
-                                    }
-                                } else {
-                                    send_notification(
-                                        "Git Error: Failed to analyze merge.".to_string(),
-                                        NotificationType::Error,
-                                    );
-                                }
-                            } else {
-                                send_notification(
-                                    "Git Error: Failed to get FETCH_HEAD commit.".to_string(),
-                                    NotificationType::Error,
-                                );
-                            }
-                        } else {
-                            send_notification(
-                                "Git Error: Failed to find FETCH_HEAD.".to_string(),
-                                NotificationType::Error,
-                            );
-                        }
+        self.start_remote(RemoteOp::Pull);
+    }
 
-                        if let Err(e) = repo.cleanup_state() {
-                            // Cleanup state after fetch
-                            send_notification(
-                                format!("Git Error: Failed to cleanup state: {}", e),
-                                NotificationType::Error,
-                            );
-                        }
-                        self.refresh_status();
-                    }
-                    Err(e) => send_notification(
-                        format!("Git Error: Failed to fetch: {}", e),
-                        NotificationType::Error,
-                    ),
-                }
-            } else {
-                send_notification(
-                    "Git Error: No 'origin' remote found.".to_string(),
-                    NotificationType::Error,
-                );
+    /// Creates the merge commit or continues the rebase once conflicts are resolved.
+    /// The commit message, if typed, replaces the prepared merge message.
+    fn finish_merge(&mut self) {
+        let message = commit::cleanup(&self.commit_editor.text());
+        let result = Repository::open(".").and_then(|repo| merge::finish(&repo, &message));
+        match result {
+            Ok(outcome) => {
+                self.reset_commit_message();
+                worker::report_outcome(outcome);
             }
-        } else {
-            send_notification(
-                "Git Error: Not a git repository.".to_string(),
+            Err(e) => send_notification(
+                format!("Git Error: Failed to finish: {}", e.message()),
                 NotificationType::Error,
-            );
+            ),
         }
+        self.refresh_status();
     }
 
     fn perform_push(&mut self) {
-        if let Ok(repo) = Repository::open(".") {
-            if let Ok(mut remote) = repo.find_remote("origin") {
-                if let Ok(head) = repo.head() {
-                    if let Some(branch_name) = head.shorthand() {
-                        let refspec =
-                            format!("refs/heads/{}:refs/heads/{}", branch_name, branch_name);
-                        let cbs = git2::RemoteCallbacks::new();
-                        // NOTE: This does not handle authentication.
-                        // For private repos, credentials callback would be needed here.
-                        let mut push_opts = git2::PushOptions::new();
-                        push_opts.remote_callbacks(cbs);
-                        if let Err(e) = remote.push(&[refspec], Some(&mut push_opts)) {
-                            send_notification(
-                                format!("Git Error: Failed to push: {}", e),
-                                NotificationType::Error,
-                            );
-                        } else {
-                            send_notification(
-                                "Push successful!".to_string(),
-                                NotificationType::Info,
-                            );
-                        }
-                    } else {
-                        send_notification(
-                            "Git Error: No branch found to push from.".to_string(),
-                            NotificationType::Error,
-                        );
-                    }
-                } else {
-                    send_notification(
-                        "Git Error: No HEAD found to push from.".to_string(),
-                        NotificationType::Error,
-                    );
-                }
+        self.start_remote(RemoteOp::Push);
+    }
+
+    /// Resolves what to pull from or push to and hands the transfer to the worker.
+    fn start_remote(&mut self, op: RemoteOp) {
+        if let Some(running) = self.worker.remote_running() {
+            let what = if running == RemoteOp::Pull {
+                "pull"
             } else {
-                send_notification(
-                    "Git Error: No 'origin' remote found.".to_string(),
-                    NotificationType::Error,
-                );
-            }
-        } else {
+                "push"
+            };
+            send_notification(
+                format!("A {} is still running.", what),
+                NotificationType::Warning,
+            );
+            return;
+        }
+        let Ok(repo) = Repository::open(".") else {
             send_notification(
                 "Git Error: Not a git repository.".to_string(),
                 NotificationType::Error,
             );
-        }
+            return;
+        };
+        let Some((local, remote, branch)) = self.remote_target(&repo) else {
+            send_notification(
+                "Git Error: No branch is checked out.".to_string(),
+                NotificationType::Error,
+            );
+            return;
+        };
+        let job = match op {
+            RemoteOp::Pull => RemoteJob::Pull { remote, branch },
+            RemoteOp::Push => RemoteJob::Push {
+                local,
+                remote,
+                branch,
+            },
+        };
+        self.worker.start_remote(op, job, &self.auth);
     }
 
     fn update_diff_view(&mut self) {
-        self.diff_lines.clear();
-
-        let (selected_index, is_staged) = match self.active_input {
-            ActiveGitInput::Unstaged => (self.unstaged_state.selected(), false),
-            ActiveGitInput::Staged => (self.staged_state.selected(), true),
+        let staged = match self.active_input {
+            ActiveGitInput::Unstaged => false,
+            ActiveGitInput::Staged => true,
             _ => return,
         };
+        let (state, files) = if staged {
+            (&self.staged_state, &self.staged_files)
+        } else {
+            (&self.unstaged_state, &self.unstaged_files)
+        };
+        let Some(path) = state
+            .selected()
+            .and_then(|i| files.get(i))
+            .map(|f| f.path.clone())
+        else {
+            self.file_diff = None;
+            return;
+        };
+        self.load_file_diff(&path, staged);
+    }
 
-        let file_path = match selected_index {
-            Some(index) => {
-                if is_staged {
-                    self.staged_files.get(index).map(|f| f.path.clone())
-                } else {
-                    self.unstaged_files.get(index).map(|f| f.path.clone())
+    /// Loads the diff of `path`, keeping the cursor when the same file is reloaded.
+    fn load_file_diff(&mut self, path: &str, staged: bool) {
+        let same_file = self
+            .file_diff
+            .as_ref()
+            .is_some_and(|d| d.path == path && d.staged == staged);
+        self.file_diff = Repository::open(".")
+            .and_then(|repo| FileDiff::load(&repo, path, staged))
+            .ok();
+        self.diff_anchor = None;
+        if !same_file {
+            self.diff_cursor = (0, 0);
+            self.diff_scroll = 0;
+        }
+        let hunks = self.file_diff.as_ref().map_or(&[][..], |d| &d.hunks[..]);
+        let hunk = self.diff_cursor.0.min(hunks.len().saturating_sub(1));
+        let line = hunks.get(hunk).map_or(0, |h| {
+            self.diff_cursor.1.min(h.lines.len().saturating_sub(1))
+        });
+        self.diff_cursor = (hunk, line);
+    }
+
+    fn render_diff(&mut self, f: &mut Frame, area: Rect, is_active: bool, theme: &Theme) {
+        if matches!(self.active_input, ActiveGitInput::Stashes) {
+            let title = self
+                .stashes_state
+                .selected()
+                .and_then(|i| self.stashes.get(i))
+                .map_or("Diff".to_string(), |s| {
+                    format!("Diff - stash@{{{}}}", s.index)
+                });
+            let patch = Paragraph::new(self.stash_patch.clone()).block(
+                Block::default()
+                    .title(title)
+                    .borders(Borders::ALL)
+                    .bg(theme.primary_bg),
+            );
+            f.render_widget(patch, area);
+            return;
+        }
+        let focused = is_active && matches!(self.active_input, ActiveGitInput::Diff);
+        let mut lines = Vec::new();
+        let mut cursor_row = 0;
+        let mut title = "Diff".to_string();
+        if let Some(file) = &self.file_diff {
+            for text in &file.header {
+                lines.push(diff::styled_line('F', text));
+            }
+            let (cursor_hunk, cursor_line) = self.diff_cursor;
+            let selection = self.diff_anchor.map_or(cursor_line..=cursor_line, |a| {
+                a.min(cursor_line)..=a.max(cursor_line)
+            });
+            for (h, hunk) in file.hunks.iter().enumerate() {
+                lines.push(Line::styled(
+                    hunk.header.clone(),
+                    Style::default().fg(Color::Cyan),
+                ));
+                for (i, line) in hunk.lines.iter().enumerate() {
+                    let mut styled = diff::styled_line(line.origin, &line.content);
+                    if focused && h == cursor_hunk {
+                        if i == cursor_line {
+                            cursor_row = lines.len();
+                            styled = styled.bg(theme.highlight_bg);
+                        } else if self.diff_anchor.is_some() && selection.contains(&i) {
+                            styled = styled.bg(theme.secondary_bg);
+                        }
+                    }
+                    lines.push(styled);
                 }
             }
-            None => return, // Early return if no file is selected
-        };
+            if focused && !file.hunks.is_empty() {
+                title = format!(
+                    "Diff - hunk {}/{}{}",
+                    cursor_hunk + 1,
+                    file.hunks.len(),
+                    if self.diff_anchor.is_some() {
+                        " (lines)"
+                    } else {
+                        ""
+                    }
+                );
+            }
+        }
+
+        // Keep the cursor inside the visible part of the pane.
+        let height = area.height.saturating_sub(2) as usize;
+        let scroll = self.diff_scroll as usize;
+        if focused && height > 0 {
+            if cursor_row < scroll {
+                self.diff_scroll = cursor_row as u16;
+            } else if cursor_row >= scroll + height {
+                self.diff_scroll = (cursor_row + 1 - height) as u16;
+            }
+        }
 
-        if let Some(path) = file_path {
-            if let Ok(repo) = Repository::open(".") {
-                let mut diff_options = DiffOptions::new();
-                diff_options.pathspec(&path);
+        let diff_paragraph = Paragraph::new(lines).scroll((self.diff_scroll, 0)).block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(self.get_border_style(is_active, &ActiveGitInput::Diff, theme))
+                .bg(theme.primary_bg),
+        );
+        f.render_widget(diff_paragraph, area);
+    }
 
-                let diff_result = if is_staged {
-                    // Diff for staged files (HEAD vs Index)
-                    let head_obj = repo.revparse_single("HEAD").ok();
-                    let head_tree = head_obj.as_ref().and_then(|o| o.peel_to_tree().ok());
-                    repo.diff_tree_to_index(head_tree.as_ref(), None, Some(&mut diff_options))
+    fn handle_diff_key(&mut self, key: KeyEvent) -> bool {
+        let Some(file) = &self.file_diff else {
+            self.leave_diff();
+            return true;
+        };
+        let staged = file.staged;
+        let (hunk, line) = self.diff_cursor;
+        let hunk_len = |h: usize| file.hunks.get(h).map_or(0, |h| h.lines.len());
+        match key.code {
+            KeyCode::Down => {
+                if line + 1 < hunk_len(hunk) {
+                    self.diff_cursor.1 += 1;
+                } else if self.diff_anchor.is_none() && hunk + 1 < file.hunks.len() {
+                    self.diff_cursor = (hunk + 1, 0);
+                }
+            }
+            KeyCode::Up => {
+                if line > 0 {
+                    self.diff_cursor.1 -= 1;
+                } else if self.diff_anchor.is_none() && hunk > 0 {
+                    self.diff_cursor = (hunk - 1, hunk_len(hunk - 1).saturating_sub(1));
+                }
+            }
+            KeyCode::Char('n') if hunk + 1 < file.hunks.len() => {
+                self.diff_cursor = (hunk + 1, 0);
+                self.diff_anchor = None;
+            }
+            KeyCode::Char('p') if hunk > 0 => {
+                self.diff_cursor = (hunk - 1, 0);
+                self.diff_anchor = None;
+            }
+            KeyCode::Char('v') => {
+                self.diff_anchor = match self.diff_anchor {
+                    Some(_) => None,
+                    None => Some(line),
+                };
+            }
+            KeyCode::Char('s') | KeyCode::Enter if !staged => self.apply_hunk(HunkAction::Stage),
+            KeyCode::Char('u') | KeyCode::Enter if staged => self.apply_hunk(HunkAction::Unstage),
+            KeyCode::Char('d') if !staged => {
+                let what = if self.diff_anchor.is_some() {
+                    "the selected lines"
                 } else {
-                    // Diff for unstaged files (Index vs Workdir)
-                    repo.diff_index_to_workdir(None, Some(&mut diff_options))
+                    "this hunk"
                 };
-
-                if let Ok(diff) = diff_result {
-                    // Filter the diff to only include the selected file
-                    // Apply the path filter when printing the diff
-
-                    let mut lines = Vec::new();
-                    let _ = diff.print(DiffFormat::Patch, |_delta, _hunk, line| {
-                        let (style, prefix) = match line.origin() {
-                            '+' => (Style::default().fg(Color::Green), "+"),
-                            '-' => (Style::default().fg(Color::Red), "-"),
-                            ' ' => (Style::default().fg(Color::DarkGray), " "),
-                            _ => (Style::default(), " "), // Headers, etc.
-                        };
-                        let content = String::from_utf8_lossy(line.content()).to_string();
-                        lines.push(Line::from(vec![
-                            Span::styled(prefix, style),
-                            Span::raw(" "),
-                            Span::styled(content, style),
-                        ]));
-                        true
-                    });
-                    self.diff_lines = lines;
-                }
+                self.popup = Some((
+                    GitPrompt::DiscardLines,
+                    Popup::new(
+                        "Discard Changes".to_string(),
+                        format!("Discard {} in {}?\nThis cannot be undone.", what, file.path),
+                    ),
+                ));
             }
+            KeyCode::Esc if self.diff_anchor.is_some() => self.diff_anchor = None,
+            KeyCode::Esc | KeyCode::Left => self.leave_diff(),
+            KeyCode::Tab => self.leave_diff(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Returns focus to the change list the diff belongs to.
+    fn leave_diff(&mut self) {
+        self.diff_anchor = None;
+        let staged = self.file_diff.as_ref().is_some_and(|d| d.staged);
+        self.active_input = if staged {
+            ActiveGitInput::Staged
+        } else {
+            ActiveGitInput::Unstaged
+        };
+        self.update_diff_view();
+    }
+
+    /// Stages, unstages or discards the hunk under the cursor, or the selected lines.
+    fn apply_hunk(&mut self, action: HunkAction) {
+        let Some(file) = &self.file_diff else {
+            return;
+        };
+        if !file.partial {
+            send_notification(
+                format!(
+                    "Only modified files can be changed by hunk; use the file list for {}.",
+                    file.path
+                ),
+                NotificationType::Warning,
+            );
+            return;
+        }
+        let (hunk, line) = self.diff_cursor;
+        let lines = match self.diff_anchor {
+            Some(anchor) => anchor.min(line)..anchor.max(line) + 1,
+            None => 0..file.hunks.get(hunk).map_or(0, |h| h.lines.len()),
+        };
+        let (path, staged) = (file.path.clone(), file.staged);
+        let result = Repository::open(".").and_then(|repo| file.apply(&repo, hunk, lines, action));
+        if let Err(e) = result {
+            let verb = match action {
+                HunkAction::Stage => "stage",
+                HunkAction::Unstage => "unstage",
+                HunkAction::Discard => "discard",
+            };
+            send_notification(
+                format!(
+                    "Git Error: Failed to {} changes in {}: {}",
+                    verb,
+                    path,
+                    e.message()
+                ),
+                NotificationType::Error,
+            );
+        }
+
+        self.reload_status();
+        // Keep the file selected in its list while it still has changes there.
+        let (state, files) = if staged {
+            (&mut self.staged_state, &self.staged_files)
+        } else {
+            (&mut self.unstaged_state, &self.unstaged_files)
+        };
+        match files.iter().position(|f| f.path == path) {
+            Some(i) => state.select(Some(i)),
+            None => state.select(
+                state
+                    .selected()
+                    .filter(|_| !files.is_empty())
+                    .map(|i| i.min(files.len() - 1)),
+            ),
+        }
+        self.load_file_diff(&path, staged);
+        if self.file_diff.as_ref().is_none_or(|d| d.hunks.is_empty()) {
+            self.leave_diff();
         }
     }
 
@@ -732,3 +1590,12 @@ impl Default for GitWidget {
         Self::new()
     }
 }
+
+fn clamp_selection(state: &mut ListState, len: usize) {
+    let selected = state.selected().unwrap_or(0);
+    state.select(if len == 0 {
+        None
+    } else {
+        Some(selected.min(len - 1))
+    });
+}
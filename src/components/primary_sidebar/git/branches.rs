@@ -0,0 +1,191 @@
+//! Branch listing and management on top of `git2`.
+
+use git2::{build::CheckoutBuilder, BranchType, Error, Repository};
+
+#[derive(Clone)]
+pub struct BranchInfo {
+    /// Short name, e.g. `main` or `origin/main`.
+    pub name: String,
+    pub is_remote: bool,
+    pub is_head: bool,
+    /// Short name of the upstream branch, for local branches that track one.
+    pub upstream: Option<String>,
+    /// Commits (ahead, behind) relative to the upstream.
+    pub ahead_behind: Option<(usize, usize)>,
+}
+
+/// Local branches first (the checked-out one on top), then remote branches.
+pub fn list_branches(repo: &Repository) -> Result<Vec<BranchInfo>, Error> {
+    let mut branches = Vec::new();
+    for entry in repo.branches(None)? {
+        let (branch, kind) = entry?;
+        let Some(name) = branch.name()?.map(str::to_string) else {
+            continue;
+        };
+        let is_remote = kind == BranchType::Remote;
+        // `origin/HEAD` is a symbolic ref, not a branch of its own.
+        if is_remote && name.ends_with("/HEAD") {
+            continue;
+        }
+        let upstream = branch.upstream().ok();
+        let ahead_behind = match (&upstream, branch.get().target()) {
+            (Some(up), Some(local)) => up
+                .get()
+                .target()
+                .and_then(|remote| repo.graph_ahead_behind(local, remote).ok()),
+            _ => None,
+        };
+        branches.push(BranchInfo {
+            upstream: upstream.and_then(|u| u.name().ok().flatten().map(str::to_string)),
+            is_head: branch.is_head(),
+            name,
+            is_remote,
+            ahead_behind,
+        });
+    }
+    branches.sort_by(|a, b| {
+        (a.is_remote, !a.is_head, &a.name).cmp(&(b.is_remote, !b.is_head, &b.name))
+    });
+    Ok(branches)
+}
+
+/// Creates `name` at HEAD and checks it out.
+pub fn create_branch(repo: &Repository, name: &str) -> Result<(), Error> {
+    let head = repo.head()?.peel_to_commit()?;
+    repo.branch(name, &head, false)?;
+    checkout_local(repo, name)
+}
+
+/// Checks out a branch. For a remote branch, a local branch of the same name that
+/// tracks it is created first unless one already exists.
+pub fn checkout(repo: &Repository, branch: &BranchInfo) -> Result<(), Error> {
+    if !branch.is_remote {
+        return checkout_local(repo, &branch.name);
+    }
+    // Remote names may contain slashes, so ask git which remote this is.
+    let remote = repo.branch_remote_name(&format!("refs/remotes/{}", branch.name))?;
+    let remote = remote
+        .as_str()
+        .ok_or_else(|| Error::from_str("remote name is not valid UTF-8"))?;
+    let local_name = branch
+        .name
+        .strip_prefix(remote)
+        .and_then(|rest| rest.strip_prefix('/'))
+        .unwrap_or(&branch.name);
+    if repo.find_branch(local_name, BranchType::Local).is_err() {
+        let commit = repo
+            .find_branch(&branch.name, BranchType::Remote)?
+            .get()
+            .peel_to_commit()?;
+        let mut local = repo.branch(local_name, &commit, false)?;
+        local.set_upstream(Some(&branch.name))?;
+    }
+    checkout_local(repo, local_name)
+}
+
+fn checkout_local(repo: &Repository, name: &str) -> Result<(), Error> {
+    let branch = repo.find_branch(name, BranchType::Local)?;
+    let refname = branch
+        .get()
+        .name()
+        .ok_or_else(|| Error::from_str("branch name is not valid UTF-8"))?
+        .to_string();
+    let target = branch.get().peel(git2::ObjectType::Commit)?;
+    // A safe checkout refuses to overwrite local changes instead of losing them.
+    repo.checkout_tree(&target, Some(CheckoutBuilder::new().safe()))?;
+    repo.set_head(&refname)
+}
+
+pub fn rename_branch(repo: &Repository, old: &str, new: &str) -> Result<(), Error> {
+    repo.find_branch(old, BranchType::Local)?
+        .rename(new, false)
+        .map(|_| ())
+}
+
+/// Deletes a local branch, or forgets a remote-tracking branch locally.
+pub fn delete_branch(repo: &Repository, branch: &BranchInfo) -> Result<(), Error> {
+    let kind = if branch.is_remote {
+        BranchType::Remote
+    } else {
+        BranchType::Local
+    };
+    repo.find_branch(&branch.name, kind)?.delete()
+}
+
+/// Sets (or with `None`, clears) the upstream of a local branch, e.g. `origin/main`.
+pub fn set_upstream(repo: &Repository, name: &str, upstream: Option<&str>) -> Result<(), Error> {
+    repo.find_branch(name, BranchType::Local)?
+        .set_upstream(upstream)
+}
+
+/// The checked-out branch's name and its upstream as (remote, branch on the
+/// remote), read from the branch's `remote` and `merge` settings.
+pub fn head_and_upstream(repo: &Repository) -> Option<(String, Option<(String, String)>)> {
+    let head = repo.head().ok()?;
+    if !head.is_branch() {
+        return None;
+    }
+    let name = head.shorthand()?.to_string();
+    let refname = head.name()?;
+    let remote = repo.branch_upstream_remote(refname).ok();
+    let merge = repo.branch_upstream_merge(refname).ok();
+    let upstream = remote.zip(merge).and_then(|(remote, merge)| {
+        let branch = merge.as_str()?.strip_prefix("refs/heads/")?;
+        Some((remote.as_str()?.to_string(), branch.to_string()))
+    });
+    Some((name, upstream))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Signature;
+
+    /// A repository with one commit on `main` and a remote named
+    /// `team/origin` that has a `feature/x` branch.
+    fn repo_with_slashed_remote() -> (tempfile::TempDir, Repository) {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        {
+            let sig = Signature::now("Test", "test@example.com").unwrap();
+            let tree = repo
+                .find_tree(repo.index().unwrap().write_tree().unwrap())
+                .unwrap();
+            let id = repo
+                .commit(Some("refs/heads/main"), &sig, &sig, "init", &tree, &[])
+                .unwrap();
+            repo.set_head("refs/heads/main").unwrap();
+            repo.remote("team/origin", "https://example.com/repo.git")
+                .unwrap();
+            repo.reference("refs/remotes/team/origin/feature/x", id, false, "")
+                .unwrap();
+        }
+        (dir, repo)
+    }
+
+    #[test]
+    fn checkout_of_a_remote_branch_strips_the_whole_remote_name() {
+        let (_dir, repo) = repo_with_slashed_remote();
+        let remote = BranchInfo {
+            name: "team/origin/feature/x".into(),
+            is_remote: true,
+            is_head: false,
+            upstream: None,
+            ahead_behind: None,
+        };
+        checkout(&repo, &remote).unwrap();
+        assert_eq!(
+            head_and_upstream(&repo),
+            Some((
+                "feature/x".to_string(),
+                Some(("team/origin".to_string(), "feature/x".to_string()))
+            ))
+        );
+    }
+
+    #[test]
+    fn head_without_upstream() {
+        let (_dir, repo) = repo_with_slashed_remote();
+        assert_eq!(head_and_upstream(&repo), Some(("main".to_string(), None)));
+    }
+}
@@ -0,0 +1,178 @@
+//! Creating commits and preparing their messages.
+
+use git2::{ErrorCode, Oid, Repository, Signature};
+use std::fs;
+
+/// Commits the index. With `amend`, HEAD is replaced instead of extended. An
+/// unborn branch gets a root commit.
+pub fn create(repo: &Repository, message: &str, amend: bool) -> Result<Oid, git2::Error> {
+    let mut index = repo.index()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let signature = repo.signature()?;
+    let head = match repo.head() {
+        Ok(head) => Some(head.peel_to_commit()?),
+        Err(e) if matches!(e.code(), ErrorCode::UnbornBranch | ErrorCode::NotFound) => None,
+        Err(e) => return Err(e),
+    };
+    if amend {
+        let head = head.ok_or_else(|| git2::Error::from_str("there is no commit to amend"))?;
+        return head.amend(
+            Some("HEAD"),
+            None,
+            Some(&signature),
+            None,
+            Some(message),
+            Some(&tree),
+        );
+    }
+    let parents: Vec<_> = head.iter().collect();
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &parents,
+    )
+}
+
+/// The message of the HEAD commit, for amending.
+pub fn head_message(repo: &Repository) -> Option<String> {
+    let commit = repo.head().ok()?.peel_to_commit().ok()?;
+    commit.message().map(str::to_string)
+}
+
+/// The contents of the file named by `commit.template`, if set.
+pub fn template(repo: &Repository) -> Option<String> {
+    let path = repo.config().ok()?.get_path("commit.template").ok()?;
+    // A relative path is relative to the working tree, as for `git commit`.
+    let path = match repo.workdir() {
+        Some(workdir) if path.is_relative() => workdir.join(path),
+        _ => path,
+    };
+    fs::read_to_string(path).ok()
+}
+
+/// Strips comment lines and trailing whitespace the way `git commit` does by
+/// default, and drops blank lines at both ends.
+pub fn cleanup(message: &str) -> String {
+    let lines: Vec<&str> = message
+        .lines()
+        .filter(|l| !l.starts_with('#'))
+        .map(str::trim_end)
+        .collect();
+    let mut cleaned: Vec<&str> = Vec::new();
+    for line in lines {
+        // Runs of blank lines collapse into one.
+        if line.is_empty() && cleaned.last().is_none_or(|l| l.is_empty()) {
+            continue;
+        }
+        cleaned.push(line);
+    }
+    while cleaned.last().is_some_and(|l| l.is_empty()) {
+        cleaned.pop();
+    }
+    cleaned.join("\n")
+}
+
+/// Appends a `Signed-off-by` trailer for `signature` unless it is already there.
+pub fn sign_off(message: &str, signature: &Signature) -> String {
+    let trailer = format!(
+        "Signed-off-by: {} <{}>",
+        signature.name().unwrap_or(""),
+        signature.email().unwrap_or("")
+    );
+    let body = message.trim_end();
+    if body.lines().any(|l| l.trim_end() == trailer) {
+        return message.to_string();
+    }
+    // Join an existing trailer block; otherwise start one after a blank line.
+    let last_paragraph = body.rsplit("\n\n").next().unwrap_or("");
+    let has_trailers =
+        body.contains("\n\n") && last_paragraph.lines().all(|l| is_trailer(l.trim_end()));
+    if body.is_empty() {
+        format!("\n\n{}", trailer)
+    } else if has_trailers {
+        format!("{}\n{}", body, trailer)
+    } else {
+        format!("{}\n\n{}", body, trailer)
+    }
+}
+
+/// Keys that are trailers without a hyphen; hyphenated keys such as
+/// `Reviewed-by` or `Change-Id` always count.
+const PLAIN_TRAILER_KEYS: &[&str] = &["bug", "cc", "closes", "fixes", "link", "refs"];
+
+/// Whether `line` is a `Key: value` trailer rather than prose such as
+/// `Note: foo`.
+fn is_trailer(line: &str) -> bool {
+    let Some((key, value)) = line.split_once(": ") else {
+        return false;
+    };
+    !key.is_empty()
+        && !value.trim().is_empty()
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        && (key.contains('-') || PLAIN_TRAILER_KEYS.contains(&key.to_ascii_lowercase().as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signature() -> Signature<'static> {
+        Signature::now("Ada", "ada@example.com").unwrap()
+    }
+
+    #[test]
+    fn cleanup_strips_comments_and_blank_runs() {
+        let message = "\n\nSubject  \n# comment\n\n\n\nBody\t\n#another\n\n";
+        assert_eq!(cleanup(message), "Subject\n\nBody");
+    }
+
+    #[test]
+    fn cleanup_of_only_comments_is_empty() {
+        assert_eq!(cleanup("# Please enter a message\n#\n"), "");
+    }
+
+    #[test]
+    fn sign_off_starts_a_trailer_block() {
+        assert_eq!(
+            sign_off("Subject\n\nBody text.", &signature()),
+            "Subject\n\nBody text.\n\nSigned-off-by: Ada <ada@example.com>"
+        );
+        assert_eq!(
+            sign_off("Subject", &signature()),
+            "Subject\n\nSigned-off-by: Ada <ada@example.com>"
+        );
+        assert_eq!(
+            sign_off("", &signature()),
+            "\n\nSigned-off-by: Ada <ada@example.com>"
+        );
+    }
+
+    #[test]
+    fn sign_off_joins_existing_trailers() {
+        assert_eq!(
+            sign_off(
+                "Subject\n\nReviewed-by: Bob <bob@example.com>\nFixes: #12\n",
+                &signature()
+            ),
+            "Subject\n\nReviewed-by: Bob <bob@example.com>\nFixes: #12\n\
+             Signed-off-by: Ada <ada@example.com>"
+        );
+    }
+
+    #[test]
+    fn sign_off_treats_prose_with_a_colon_as_body() {
+        assert_eq!(
+            sign_off("Subject\n\nNote: foo", &signature()),
+            "Subject\n\nNote: foo\n\nSigned-off-by: Ada <ada@example.com>"
+        );
+    }
+
+    #[test]
+    fn sign_off_is_not_repeated() {
+        let message = "Subject\n\nSigned-off-by: Ada <ada@example.com>\n";
+        assert_eq!(sign_off(message, &signature()), message);
+    }
+}
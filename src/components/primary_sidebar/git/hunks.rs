@@ -0,0 +1,349 @@
+//! Hunk- and line-level staging.
+//!
+//! A partial change is applied by writing a small patch that contains only the
+//! chosen lines and handing it to `git2::Repository::apply`, the same way
+//! `git add -p` edits hunks: unselected removals become context and unselected
+//! additions are dropped.
+
+use git2::{ApplyLocation, Delta, Diff, DiffFormat, DiffOptions, Repository};
+use std::ops::Range;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum HunkAction {
+    /// Copy working tree changes into the index.
+    Stage,
+    /// Take staged changes back out of the index.
+    Unstage,
+    /// Throw away working tree changes.
+    Discard,
+}
+
+pub struct HunkLine {
+    /// `' '`, `'+'` or `'-'`.
+    pub origin: char,
+    pub content: String,
+    /// The line is followed by "\ No newline at end of file".
+    pub no_newline: bool,
+}
+
+pub struct Hunk {
+    pub header: String,
+    old_start: u32,
+    new_start: u32,
+    pub lines: Vec<HunkLine>,
+}
+
+/// The diff of one file, either working tree vs index or index vs HEAD.
+pub struct FileDiff {
+    pub path: String,
+    pub staged: bool,
+    pub header: Vec<String>,
+    pub hunks: Vec<Hunk>,
+    /// Only modifications of existing files can be staged piece by piece.
+    pub partial: bool,
+}
+
+impl FileDiff {
+    pub fn load(repo: &Repository, path: &str, staged: bool) -> Result<Self, git2::Error> {
+        let mut opts = DiffOptions::new();
+        opts.pathspec(path).disable_pathspec_match(true);
+        let diff = if staged {
+            let head_tree = repo
+                .revparse_single("HEAD")
+                .ok()
+                .and_then(|o| o.peel_to_tree().ok());
+            repo.diff_tree_to_index(head_tree.as_ref(), None, Some(&mut opts))?
+        } else {
+            repo.diff_index_to_workdir(None, Some(&mut opts))?
+        };
+        let partial = diff
+            .deltas()
+            .next()
+            .is_some_and(|d| d.status() == Delta::Modified);
+        let mut file = FileDiff {
+            path: path.to_string(),
+            staged,
+            header: Vec::new(),
+            hunks: Vec::new(),
+            partial,
+        };
+        file.collect(&diff)?;
+        Ok(file)
+    }
+
+    fn collect(&mut self, diff: &Diff) -> Result<(), git2::Error> {
+        diff.print(DiffFormat::Patch, |_delta, hunk, line| {
+            let text = String::from_utf8_lossy(line.content());
+            match line.origin() {
+                'F' => self.header.extend(text.lines().map(str::to_string)),
+                'H' => {
+                    if let Some(hunk) = hunk {
+                        self.hunks.push(Hunk {
+                            header: text.trim_end().to_string(),
+                            old_start: hunk.old_start(),
+                            new_start: hunk.new_start(),
+                            lines: Vec::new(),
+                        });
+                    }
+                }
+                origin @ (' ' | '+' | '-') => {
+                    if let Some(hunk) = self.hunks.last_mut() {
+                        hunk.lines.push(HunkLine {
+                            origin,
+                            content: text.strip_suffix('\n').unwrap_or(&text).to_string(),
+                            no_newline: false,
+                        });
+                    }
+                }
+                // '=', '>' and '<' mark a missing newline at the end of the file.
+                '=' | '>' | '<' => {
+                    if let Some(last) = self.hunks.last_mut().and_then(|h| h.lines.last_mut()) {
+                        last.no_newline = true;
+                    }
+                }
+                _ => {}
+            }
+            true
+        })
+    }
+
+    /// Applies `action` to `lines` (indices into the hunk's lines) of one hunk.
+    pub fn apply(
+        &self,
+        repo: &Repository,
+        hunk: usize,
+        lines: Range<usize>,
+        action: HunkAction,
+    ) -> Result<(), git2::Error> {
+        let hunk = self
+            .hunks
+            .get(hunk)
+            .ok_or_else(|| git2::Error::from_str("no such hunk"))?;
+        // Unstaging and discarding undo the shown change, so the patch is reversed.
+        let reverse = action != HunkAction::Stage;
+        let patch = self.partial_patch(hunk, &lines, reverse);
+        let diff = Diff::from_buffer(patch.as_bytes())?;
+        let location = match action {
+            HunkAction::Stage | HunkAction::Unstage => ApplyLocation::Index,
+            HunkAction::Discard => ApplyLocation::WorkDir,
+        };
+        repo.apply(&diff, location, None)
+    }
+
+    fn partial_patch(&self, hunk: &Hunk, selected: &Range<usize>, reverse: bool) -> String {
+        let mut body = String::new();
+        let (mut old_count, mut new_count) = (0u32, 0u32);
+        // Reversing turns "-old +new" into "+old -new"; additions are held back so
+        // each run of changes still lists its removals first, as patches must when
+        // a line lacks its final newline.
+        let mut additions = Vec::new();
+        for (i, line) in hunk.lines.iter().enumerate() {
+            let origin = match (line.origin, reverse) {
+                ('+', true) => '-',
+                ('-', true) => '+',
+                (origin, _) => origin,
+            };
+            let origin = match origin {
+                '-' if !selected.contains(&i) => ' ',
+                '+' if !selected.contains(&i) => continue,
+                origin => origin,
+            };
+            match origin {
+                ' ' => {
+                    old_count += 1;
+                    new_count += 1;
+                    for addition in additions.drain(..) {
+                        push_line(&mut body, '+', addition);
+                    }
+                    push_line(&mut body, ' ', line);
+                }
+                '-' => {
+                    old_count += 1;
+                    push_line(&mut body, '-', line);
+                }
+                _ => {
+                    new_count += 1;
+                    additions.push(line);
+                }
+            }
+        }
+        for addition in additions {
+            push_line(&mut body, '+', addition);
+        }
+
+        let old_start = if reverse {
+            hunk.new_start
+        } else {
+            hunk.old_start
+        };
+        // In unified diffs an empty range names the line before it.
+        let new_start = match (old_count, new_count) {
+            (0, _) => old_start + 1,
+            (_, 0) => old_start.saturating_sub(1),
+            _ => old_start,
+        };
+        format!(
+            "diff --git a/{path} b/{path}\n--- a/{path}\n+++ b/{path}\n@@ -{} +{} @@\n{}",
+            range(old_start, old_count),
+            range(new_start, new_count),
+            body,
+            path = self.path,
+        )
+    }
+}
+
+fn push_line(body: &mut String, origin: char, line: &HunkLine) {
+    body.push(origin);
+    body.push_str(&line.content);
+    body.push('\n');
+    if line.no_newline {
+        body.push_str("\\ No newline at end of file\n");
+    }
+}
+
+fn range(start: u32, count: u32) -> String {
+    if count == 1 {
+        start.to_string()
+    } else {
+        format!("{},{}", start, count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Signature;
+    use std::fs;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    const FILE: &str = "f.txt";
+
+    /// A repository with `committed` in HEAD and the index, and `edited` on disk.
+    fn repo_with(committed: &str, edited: &str) -> (TempDir, Repository) {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        fs::write(dir.path().join(FILE), committed).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(FILE)).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = Signature::now("Test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "initial", &tree, &[])
+            .unwrap();
+        drop(tree);
+        fs::write(dir.path().join(FILE), edited).unwrap();
+        (dir, repo)
+    }
+
+    fn index_content(repo: &Repository) -> String {
+        let index = repo.index().unwrap();
+        let entry = index.get_path(Path::new(FILE), 0).unwrap();
+        let blob = repo.find_blob(entry.id).unwrap();
+        String::from_utf8(blob.content().to_vec()).unwrap()
+    }
+
+    fn workdir_content(dir: &TempDir) -> String {
+        fs::read_to_string(dir.path().join(FILE)).unwrap()
+    }
+
+    /// Index of the line shown as `origin` + `content` in the only hunk.
+    fn line(diff: &FileDiff, origin: char, content: &str) -> usize {
+        diff.hunks[0]
+            .lines
+            .iter()
+            .position(|l| l.origin == origin && l.content == content)
+            .unwrap()
+    }
+
+    fn apply(repo: &Repository, staged: bool, lines: &[(char, &str)], action: HunkAction) {
+        let diff = FileDiff::load(repo, FILE, staged).unwrap();
+        assert_eq!(diff.hunks.len(), 1);
+        let indices: Vec<_> = lines.iter().map(|&(o, c)| line(&diff, o, c)).collect();
+        let (start, end) = (indices[0], indices[indices.len() - 1] + 1);
+        diff.apply(repo, 0, start..end, action).unwrap();
+    }
+
+    const BEFORE: &str = "one\ntwo\nthree\nfour\nfive\n";
+    const AFTER: &str = "one\nTWO\nthree\nfour\nFIVE\n";
+
+    #[test]
+    fn stages_only_the_selected_change() {
+        let (dir, repo) = repo_with(BEFORE, AFTER);
+        apply(
+            &repo,
+            false,
+            &[('-', "two"), ('+', "TWO")],
+            HunkAction::Stage,
+        );
+        assert_eq!(index_content(&repo), "one\nTWO\nthree\nfour\nfive\n");
+        assert_eq!(workdir_content(&dir), AFTER);
+    }
+
+    #[test]
+    fn unselected_removals_stay_and_unselected_additions_are_left_out() {
+        let (_dir, repo) = repo_with(BEFORE, AFTER);
+        apply(&repo, false, &[('-', "two")], HunkAction::Stage);
+        assert_eq!(index_content(&repo), "one\nthree\nfour\nfive\n");
+
+        let (_dir, repo) = repo_with(BEFORE, AFTER);
+        apply(&repo, false, &[('+', "TWO")], HunkAction::Stage);
+        assert_eq!(index_content(&repo), "one\ntwo\nTWO\nthree\nfour\nfive\n");
+    }
+
+    #[test]
+    fn unstages_the_selected_change_from_the_index() {
+        let (dir, repo) = repo_with(BEFORE, AFTER);
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(FILE)).unwrap();
+        index.write().unwrap();
+
+        apply(
+            &repo,
+            true,
+            &[('-', "five"), ('+', "FIVE")],
+            HunkAction::Unstage,
+        );
+        assert_eq!(index_content(&repo), "one\nTWO\nthree\nfour\nfive\n");
+        assert_eq!(workdir_content(&dir), AFTER);
+    }
+
+    #[test]
+    fn discards_the_selected_change_from_the_working_tree() {
+        let (dir, repo) = repo_with(BEFORE, AFTER);
+        apply(
+            &repo,
+            false,
+            &[('-', "two"), ('+', "TWO")],
+            HunkAction::Discard,
+        );
+        assert_eq!(workdir_content(&dir), "one\ntwo\nthree\nfour\nFIVE\n");
+        assert_eq!(index_content(&repo), BEFORE);
+    }
+
+    #[test]
+    fn stages_additions_and_removals_at_the_edges_of_the_file() {
+        let (_dir, repo) = repo_with("a\nb\nc\n", "x\na\nb\nc\n");
+        apply(&repo, false, &[('+', "x")], HunkAction::Stage);
+        assert_eq!(index_content(&repo), "x\na\nb\nc\n");
+
+        let (_dir, repo) = repo_with("a\nb\nc\n", "b\nc\n");
+        apply(&repo, false, &[('-', "a")], HunkAction::Stage);
+        assert_eq!(index_content(&repo), "b\nc\n");
+
+        let (dir, repo) = repo_with("a\nb\nc\n", "a\nb\nc\nd\ne\n");
+        apply(&repo, false, &[('+', "e")], HunkAction::Discard);
+        assert_eq!(workdir_content(&dir), "a\nb\nc\nd\n");
+    }
+
+    #[test]
+    fn keeps_a_missing_newline_at_the_end_of_the_file() {
+        let (_dir, repo) = repo_with("a\nb\n", "a\nb\nc");
+        apply(&repo, false, &[('+', "c")], HunkAction::Stage);
+        assert_eq!(index_content(&repo), "a\nb\nc");
+
+        let (dir, repo) = repo_with("a\nb", "a\nB");
+        apply(&repo, false, &[('-', "b"), ('+', "B")], HunkAction::Discard);
+        assert_eq!(workdir_content(&dir), "a\nb");
+    }
+}
@@ -0,0 +1,175 @@
+//! Integrating fetched commits: fast-forward, merge or rebase, and finishing an
+//! interrupted merge or rebase once its conflicts are resolved.
+
+use git2::{build::CheckoutBuilder, AnnotatedCommit, ErrorCode, Oid, Repository, RepositoryState};
+use std::fs;
+
+pub enum PullOutcome {
+    UpToDate,
+    FastForward,
+    Merged,
+    Rebased,
+    /// The merge or rebase stopped with this many conflicted files.
+    Conflicts(usize),
+}
+
+/// Whether `git pull` would rebase instead of merge (`pull.rebase`).
+pub fn pull_rebase(repo: &Repository) -> bool {
+    repo.config()
+        .and_then(|c| c.get_bool("pull.rebase"))
+        .unwrap_or(false)
+}
+
+/// Brings `fetched` into the checked-out branch. `label` names it in the merge
+/// commit message, e.g. `branch 'main' of origin`.
+pub fn integrate(
+    repo: &Repository,
+    fetched: &AnnotatedCommit,
+    label: &str,
+    rebase: bool,
+) -> Result<PullOutcome, git2::Error> {
+    let (analysis, _) = repo.merge_analysis(&[fetched])?;
+    if analysis.is_up_to_date() {
+        return Ok(PullOutcome::UpToDate);
+    }
+    if analysis.is_fast_forward() {
+        // Update the files first: a safe checkout refuses to overwrite local
+        // changes, and HEAD only moves once it succeeded.
+        let target = repo.find_object(fetched.id(), None)?;
+        repo.checkout_tree(&target, Some(CheckoutBuilder::new().safe()))
+            .map_err(|e| match e.code() {
+                ErrorCode::Conflict => git2::Error::from_str(
+                    "local changes would be overwritten by the pull; commit or stash them first",
+                ),
+                _ => e,
+            })?;
+        repo.head()?
+            .set_target(fetched.id(), "pull: fast-forward")?;
+        return Ok(PullOutcome::FastForward);
+    }
+    if rebase {
+        repo.rebase(None, Some(fetched), None, None)?;
+        return continue_rebase(repo);
+    }
+
+    repo.merge(&[fetched], None, Some(CheckoutBuilder::new().safe()))?;
+    let conflicts = conflicted_paths(repo)?.len();
+    let message = format!("Merge {}", label);
+    if conflicts > 0 {
+        // Prepared for when the merge is finished after resolving.
+        fs::write(repo.path().join("MERGE_MSG"), format!("{}\n", message))
+            .map_err(|e| git2::Error::from_str(&e.to_string()))?;
+        return Ok(PullOutcome::Conflicts(conflicts));
+    }
+    commit_merge(repo, &message)?;
+    Ok(PullOutcome::Merged)
+}
+
+/// Paths with conflict entries in the index.
+pub fn conflicted_paths(repo: &Repository) -> Result<Vec<String>, git2::Error> {
+    let index = repo.index()?;
+    let mut paths = Vec::new();
+    for conflict in index.conflicts()? {
+        let conflict = conflict?;
+        let entry = conflict.our.or(conflict.their).or(conflict.ancestor);
+        if let Some(entry) = entry {
+            paths.push(String::from_utf8_lossy(&entry.path).into_owned());
+        }
+    }
+    Ok(paths)
+}
+
+/// Describes an unfinished merge or rebase, if there is one.
+pub fn in_progress(repo: &Repository) -> Option<&'static str> {
+    match repo.state() {
+        RepositoryState::Merge => Some("merging"),
+        RepositoryState::Rebase
+        | RepositoryState::RebaseInteractive
+        | RepositoryState::RebaseMerge => Some("rebasing"),
+        _ => None,
+    }
+}
+
+/// Finishes whatever is in progress once no conflicts are left: commits a merge
+/// (with `message`, or the prepared MERGE_MSG when empty) or continues a rebase.
+pub fn finish(repo: &Repository, message: &str) -> Result<PullOutcome, git2::Error> {
+    if !conflicted_paths(repo)?.is_empty() {
+        return Err(git2::Error::from_str("resolve all conflicts first"));
+    }
+    match repo.state() {
+        RepositoryState::Merge => {
+            let message = if message.trim().is_empty() {
+                // Like git, drop the comment lines of the prepared message.
+                repo.message()?
+                    .lines()
+                    .filter(|l| !l.starts_with('#'))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            } else {
+                message.to_string()
+            };
+            commit_merge(repo, &message)?;
+            Ok(PullOutcome::Merged)
+        }
+        RepositoryState::RebaseMerge => continue_rebase(repo),
+        _ => Err(git2::Error::from_str("no merge or rebase in progress")),
+    }
+}
+
+/// Commits the index with HEAD and every MERGE_HEAD as parents.
+fn commit_merge(repo: &Repository, message: &str) -> Result<Oid, git2::Error> {
+    let mut index = repo.index()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let signature = repo.signature()?;
+    let mut parent_ids = vec![repo.head()?.peel_to_commit()?.id()];
+    // MERGE_HEAD lists one commit per line.
+    let merge_heads = fs::read_to_string(repo.path().join("MERGE_HEAD")).unwrap_or_default();
+    for line in merge_heads.lines().filter(|l| !l.trim().is_empty()) {
+        parent_ids.push(Oid::from_str(line.trim())?);
+    }
+    let parents = parent_ids
+        .iter()
+        .map(|id| repo.find_commit(*id))
+        .collect::<Result<Vec<_>, _>>()?;
+    let parents: Vec<_> = parents.iter().collect();
+    let oid = repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        message.trim_end(),
+        &tree,
+        &parents,
+    )?;
+    repo.cleanup_state()?;
+    Ok(oid)
+}
+
+/// Commits the current rebase step, if any, and applies the remaining ones until
+/// the rebase is done or stops at a conflict.
+fn continue_rebase(repo: &Repository) -> Result<PullOutcome, git2::Error> {
+    let signature = repo.signature()?;
+    let mut rebase = repo.open_rebase(None)?;
+    loop {
+        if rebase.operation_current().is_some() {
+            let conflicts = conflicted_paths(repo)?.len();
+            if conflicts > 0 {
+                return Ok(PullOutcome::Conflicts(conflicts));
+            }
+            match rebase.commit(None, &signature, None) {
+                // The change is already upstream; nothing to commit for this step.
+                Err(e) if e.code() == ErrorCode::Applied => {}
+                result => {
+                    result?;
+                }
+            }
+        }
+        match rebase.next() {
+            Some(op) => {
+                op?;
+            }
+            None => break,
+        }
+    }
+    rebase.finish(Some(&signature))?;
+    Ok(PullOutcome::Rebased)
+}
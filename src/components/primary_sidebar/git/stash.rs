@@ -0,0 +1,77 @@
+//! Stash handling on top of `git2::Repository::stash_*`.
+
+use git2::{Diff, Error, Oid, Repository, StashApplyOptions, StashFlags};
+
+#[derive(Clone)]
+pub struct StashEntry {
+    /// Position in the stash list; 0 is the newest.
+    pub index: usize,
+    pub message: String,
+    pub id: Oid,
+}
+
+pub fn list(repo: &mut Repository) -> Result<Vec<StashEntry>, Error> {
+    let mut entries = Vec::new();
+    repo.stash_foreach(|index, message, id| {
+        entries.push(StashEntry {
+            index,
+            message: message.to_string(),
+            id: *id,
+        });
+        true
+    })?;
+    Ok(entries)
+}
+
+/// Stashes local changes, optionally including untracked files. An empty message
+/// gets git's default "WIP on <branch>" one.
+pub fn save(repo: &mut Repository, message: &str, include_untracked: bool) -> Result<(), Error> {
+    let signature = repo.signature()?;
+    let flags = if include_untracked {
+        StashFlags::INCLUDE_UNTRACKED
+    } else {
+        StashFlags::DEFAULT
+    };
+    let message = Some(message).filter(|m| !m.is_empty());
+    repo.stash_save2(&signature, message, Some(flags))
+        .map(|_| ())
+}
+
+/// Applies a stash and, with `pop`, drops it once it applied cleanly.
+pub fn apply(repo: &mut Repository, entry: &StashEntry, pop: bool) -> Result<(), Error> {
+    let index = position(repo, entry)?;
+    let mut opts = StashApplyOptions::new();
+    opts.reinstantiate_index();
+    if pop {
+        repo.stash_pop(index, Some(&mut opts))
+    } else {
+        repo.stash_apply(index, Some(&mut opts))
+    }
+}
+
+pub fn drop(repo: &mut Repository, entry: &StashEntry) -> Result<(), Error> {
+    let index = position(repo, entry)?;
+    repo.stash_drop(index)
+}
+
+/// Where `entry` is in the stash list now. Stashes made or dropped elsewhere
+/// since the list was read move it, and it may be gone.
+fn position(repo: &mut Repository, entry: &StashEntry) -> Result<usize, Error> {
+    list(repo)?
+        .into_iter()
+        .find(|e| e.id == entry.id)
+        .map(|e| e.index)
+        .ok_or_else(|| Error::from_str(&format!("stash '{}' no longer exists", entry.message)))
+}
+
+/// The working tree changes recorded by a stash, against the commit it was made on.
+pub fn diff<'r>(repo: &'r Repository, id: Oid) -> Result<Diff<'r>, Error> {
+    let commit = repo.find_commit(id)?;
+    let base = commit.parent(0)?.tree()?;
+    let mut diff = repo.diff_tree_to_tree(Some(&base), Some(&commit.tree()?), None)?;
+    // With untracked files, they are recorded in a third parent.
+    if let Ok(untracked) = commit.parent(2) {
+        diff.merge(&repo.diff_tree_to_tree(None, Some(&untracked.tree()?), None)?)?;
+    }
+    Ok(diff)
+}
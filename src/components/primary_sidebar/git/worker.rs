@@ -0,0 +1,563 @@
+//! Git work that can take a while — reading the status, fetching and pushing —
+//! runs on background threads. Results come back over a channel that the widget
+//! polls every tick, and transfers report their progress in a notification.
+
+use super::branches::{self, BranchInfo};
+use super::credentials::{AuthRequest, Authenticator};
+use super::merge::{self, PullOutcome};
+use super::stash::{self, StashEntry};
+use super::{GitFile, RemoteOp};
+use crate::components::notification::{
+    clear_progress, send_notification, send_progress, NotificationType,
+};
+use git2::{FetchOptions, Progress, PushOptions, Repository, Status, StatusOptions};
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Key of the transfer progress notification.
+const PROGRESS_KEY: &str = "git-transfer";
+/// Progress updates closer together than this are dropped.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Everything the sidebar and the bottom bar show about the repository.
+#[derive(Default)]
+pub struct StatusSnapshot {
+    pub staged: Vec<GitFile>,
+    pub unstaged: Vec<GitFile>,
+    pub branches: Vec<BranchInfo>,
+    pub stashes: Vec<StashEntry>,
+    /// "merging" or "rebasing" while one is unfinished.
+    pub in_progress: Option<&'static str>,
+    pub workdir: Option<PathBuf>,
+    /// Why the repository could not be read.
+    pub error: Option<String>,
+}
+
+/// A fetch or push to run, with the refs already resolved by the caller.
+pub enum RemoteJob {
+    Pull {
+        remote: String,
+        branch: String,
+    },
+    Push {
+        local: String,
+        remote: String,
+        branch: String,
+    },
+}
+
+/// A transfer that failed, possibly for lack of credentials.
+pub struct RemoteFailure {
+    pub action: &'static str,
+    pub error: git2::Error,
+    pub request: Option<AuthRequest>,
+}
+
+pub enum GitEvent {
+    Status(Box<StatusSnapshot>),
+    /// A pull or push ended. Outcomes were already reported unless it failed.
+    RemoteDone(RemoteOp, Option<RemoteFailure>),
+}
+
+enum Message {
+    Status(u64, Box<StatusSnapshot>),
+    RemoteDone(RemoteOp, Option<RemoteFailure>),
+}
+
+pub struct GitWorker {
+    tx: Sender<Message>,
+    rx: Receiver<Message>,
+    /// Bumped by every status request; older results are dropped.
+    generation: u64,
+    status_running: bool,
+    status_pending: bool,
+    remote_running: Option<RemoteOp>,
+}
+
+impl Default for GitWorker {
+    fn default() -> Self {
+        let (tx, rx) = mpsc::channel();
+        Self {
+            tx,
+            rx,
+            generation: 0,
+            status_running: false,
+            status_pending: false,
+            remote_running: None,
+        }
+    }
+}
+
+impl GitWorker {
+    /// Reads the status in the background. While a read is running, one more is
+    /// queued so changes made in the meantime are not missed.
+    pub fn request_status(&mut self) {
+        self.generation += 1;
+        if self.status_running {
+            self.status_pending = true;
+        } else {
+            self.spawn_status();
+        }
+    }
+
+    fn spawn_status(&mut self) {
+        self.status_running = true;
+        let (tx, generation) = (self.tx.clone(), self.generation);
+        thread::spawn(move || {
+            let _ = tx.send(Message::Status(generation, Box::new(read_status())));
+        });
+    }
+
+    /// Reads the status right away, discarding any background read in flight.
+    pub fn read_status_now(&mut self) -> StatusSnapshot {
+        self.generation += 1;
+        read_status()
+    }
+
+    /// The pull or push currently running, if any.
+    pub fn remote_running(&self) -> Option<RemoteOp> {
+        self.remote_running
+    }
+
+    /// Starts a pull or push. Only one runs at a time; returns `false` if another
+    /// is still running.
+    pub fn start_remote(&mut self, op: RemoteOp, job: RemoteJob, auth: &Authenticator) -> bool {
+        if self.remote_running.is_some() {
+            return false;
+        }
+        self.remote_running = Some(op);
+        let tx = self.tx.clone();
+        let auth = auth.clone();
+        thread::spawn(move || {
+            let failure = run_remote(job, &auth);
+            clear_progress(PROGRESS_KEY);
+            let _ = tx.send(Message::RemoteDone(op, failure));
+        });
+        true
+    }
+
+    /// Collects what the background threads finished since the last call.
+    pub fn poll(&mut self) -> Vec<GitEvent> {
+        let mut events = Vec::new();
+        while let Ok(message) = self.rx.try_recv() {
+            match message {
+                Message::Status(generation, snapshot) => {
+                    self.status_running = false;
+                    if generation == self.generation {
+                        events.push(GitEvent::Status(snapshot));
+                    }
+                }
+                Message::RemoteDone(op, failure) => {
+                    self.remote_running = None;
+                    events.push(GitEvent::RemoteDone(op, failure));
+                }
+            }
+        }
+        if self.status_pending && !self.status_running {
+            self.status_pending = false;
+            self.spawn_status();
+        }
+        events
+    }
+}
+
+fn read_status() -> StatusSnapshot {
+    let mut repo = match Repository::open(".") {
+        Ok(repo) => repo,
+        Err(e) => {
+            return StatusSnapshot {
+                error: Some(e.message().to_string()),
+                ..Default::default()
+            }
+        }
+    };
+    let mut snapshot = StatusSnapshot {
+        in_progress: merge::in_progress(&repo),
+        workdir: repo.workdir().map(|p| p.to_path_buf()),
+        ..Default::default()
+    };
+    match branches::list_branches(&repo) {
+        Ok(list) => snapshot.branches = list,
+        Err(e) => snapshot.error = Some(format!("Failed to list branches: {}", e)),
+    }
+
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .include_ignored(false); // Typically don't show ignored files in status
+
+    let mut conflicts = Vec::new();
+    if let Ok(statuses) = repo.statuses(Some(&mut opts)) {
+        for entry in statuses.iter() {
+            let path = entry.path().unwrap_or("").to_string();
+            let status = entry.status();
+            if status.is_conflicted() {
+                conflicts.push(GitFile::new("U ", path, true));
+                continue;
+            }
+            if let Some(code) = index_code(status) {
+                snapshot
+                    .staged
+                    .push(GitFile::new(code, path.clone(), false));
+            }
+            if let Some(code) = worktree_code(status) {
+                snapshot.unstaged.push(GitFile::new(code, path, false));
+            }
+        }
+    }
+    // Conflicts come first; they block committing.
+    snapshot.unstaged.splice(0..0, conflicts);
+
+    snapshot.stashes = stash::list(&mut repo).unwrap_or_default();
+    snapshot
+}
+
+/// Staged changes (index vs HEAD).
+fn index_code(status: Status) -> Option<&'static str> {
+    if status.is_index_new() {
+        Some("A ")
+    } else if status.is_index_modified() {
+        Some("M ")
+    } else if status.is_index_deleted() {
+        Some("D ")
+    } else if status.is_index_renamed() {
+        Some("R ")
+    } else if status.is_index_typechange() {
+        Some("T ")
+    } else {
+        None
+    }
+}
+
+/// Unstaged changes (working tree vs index), including untracked files.
+fn worktree_code(status: Status) -> Option<&'static str> {
+    if status.is_wt_new() {
+        Some("??")
+    } else if status.is_wt_modified() {
+        Some("M ")
+    } else if status.is_wt_deleted() {
+        Some("D ")
+    } else if status.is_wt_renamed() {
+        Some("R ")
+    } else if status.is_wt_typechange() {
+        Some("T ")
+    } else {
+        None
+    }
+}
+
+fn run_remote(job: RemoteJob, auth: &Authenticator) -> Option<RemoteFailure> {
+    let repo = match Repository::open(".") {
+        Ok(repo) => repo,
+        Err(_) => {
+            send_notification(
+                "Git Error: Not a git repository.".to_string(),
+                NotificationType::Error,
+            );
+            return None;
+        }
+    };
+    transfer(&repo, job, auth)
+}
+
+fn transfer(repo: &Repository, job: RemoteJob, auth: &Authenticator) -> Option<RemoteFailure> {
+    let remote_name = match &job {
+        RemoteJob::Pull { remote, .. } | RemoteJob::Push { remote, .. } => remote,
+    };
+    let Ok(mut remote) = repo.find_remote(remote_name) else {
+        send_notification(
+            format!("Git Error: No '{}' remote found.", remote_name),
+            NotificationType::Error,
+        );
+        return None;
+    };
+    send_progress(PROGRESS_KEY, format!("Connecting to {}…", remote_name));
+    let (mut callbacks, attempt) = auth.callbacks(repo.config().ok());
+    let mut throttle = Throttle::default();
+
+    match &job {
+        RemoteJob::Pull {
+            remote: remote_name,
+            branch,
+        } => {
+            callbacks.transfer_progress(move |stats| {
+                if throttle.ready() {
+                    send_progress(PROGRESS_KEY, fetch_progress(&stats));
+                }
+                true
+            });
+            let mut fo = FetchOptions::new();
+            fo.remote_callbacks(callbacks);
+            let tracking_ref = format!("refs/remotes/{}/{}", remote_name, branch);
+            let refspec = format!("+refs/heads/{}:{}", branch, tracking_ref);
+            if let Err(error) = remote.fetch(&[refspec], Some(&mut fo), None) {
+                let request = attempt.borrow().request(&error);
+                return Some(RemoteFailure {
+                    action: "fetch",
+                    error,
+                    request,
+                });
+            }
+            send_notification("Fetch successful!".to_string(), NotificationType::Info);
+            clear_progress(PROGRESS_KEY);
+
+            let fetched = repo
+                .find_reference(&tracking_ref)
+                .and_then(|r| repo.reference_to_annotated_commit(&r));
+            let label = format!("branch '{}' of {}", branch, remote_name);
+            let rebase = merge::pull_rebase(repo);
+            match fetched.and_then(|commit| merge::integrate(repo, &commit, &label, rebase)) {
+                Ok(outcome) => report_outcome(outcome),
+                Err(e) => send_notification(
+                    format!(
+                        "Git Error: Failed to integrate {}: {}",
+                        tracking_ref,
+                        e.message()
+                    ),
+                    NotificationType::Error,
+                ),
+            }
+        }
+        RemoteJob::Push {
+            local,
+            remote: remote_name,
+            branch,
+        } => {
+            callbacks.push_transfer_progress(move |current, total, bytes| {
+                if throttle.ready() {
+                    send_progress(
+                        PROGRESS_KEY,
+                        format!(
+                            "Pushing: {}/{} objects, {}",
+                            current,
+                            total,
+                            format_bytes(bytes)
+                        ),
+                    );
+                }
+            });
+            // The server reports rejected updates (e.g. non-fast-forward) per reference
+            // rather than failing the whole push.
+            let rejected = Rc::new(RefCell::new(None));
+            let rejected_in_cb = Rc::clone(&rejected);
+            callbacks.push_update_reference(move |_refname, status| {
+                if let Some(message) = status {
+                    *rejected_in_cb.borrow_mut() = Some(message.to_string());
+                }
+                Ok(())
+            });
+            let mut push_opts = PushOptions::new();
+            push_opts.remote_callbacks(callbacks);
+            let refspec = format!("refs/heads/{}:refs/heads/{}", local, branch);
+            if let Err(error) = remote.push(&[refspec], Some(&mut push_opts)) {
+                let request = attempt.borrow().request(&error);
+                return Some(RemoteFailure {
+                    action: "push",
+                    error,
+                    request,
+                });
+            }
+            if let Some(message) = rejected.borrow_mut().take() {
+                send_notification(
+                    format!("Git Error: Push rejected: {}", message),
+                    NotificationType::Error,
+                );
+                return None;
+            }
+
+            // Like `git push -u`, start tracking the branch that was just published.
+            let has_upstream =
+                branches::head_and_upstream(repo).is_some_and(|(_, up)| up.is_some());
+            if !has_upstream {
+                let upstream = format!("{}/{}", remote_name, branch);
+                if let Err(e) = branches::set_upstream(repo, local, Some(&upstream)) {
+                    send_notification(
+                        format!("Git Error: Failed to set upstream: {}", e),
+                        NotificationType::Warning,
+                    );
+                }
+            }
+            send_notification(
+                format!("Pushed {} to {}/{}", local, remote_name, branch),
+                NotificationType::Info,
+            );
+        }
+    }
+    None
+}
+
+pub fn report_outcome(outcome: PullOutcome) {
+    let (message, kind) = match outcome {
+        PullOutcome::UpToDate => ("Already up-to-date.".to_string(), NotificationType::Info),
+        PullOutcome::FastForward => ("Fast-forwarded.".to_string(), NotificationType::Info),
+        PullOutcome::Merged => ("Merge committed.".to_string(), NotificationType::Info),
+        PullOutcome::Rebased => ("Rebase finished.".to_string(), NotificationType::Info),
+        PullOutcome::Conflicts(count) => (
+            format!(
+                "{} conflicted file(s). Press Enter on one to resolve it, then Commit to finish.",
+                count
+            ),
+            NotificationType::Warning,
+        ),
+    };
+    send_notification(message, kind);
+}
+
+fn fetch_progress(stats: &Progress) -> String {
+    if stats.total_objects() > 0 && stats.received_objects() == stats.total_objects() {
+        format!(
+            "Resolving deltas: {}/{}",
+            stats.indexed_deltas(),
+            stats.total_deltas()
+        )
+    } else {
+        format!(
+            "Fetching: {}/{} objects, {}",
+            stats.received_objects(),
+            stats.total_objects(),
+            format_bytes(stats.received_bytes())
+        )
+    }
+}
+
+fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+#[derive(Default)]
+struct Throttle(Option<Instant>);
+
+impl Throttle {
+    fn ready(&mut self) -> bool {
+        if self
+            .0
+            .is_some_and(|last| last.elapsed() < PROGRESS_INTERVAL)
+        {
+            return false;
+        }
+        self.0 = Some(Instant::now());
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Signature;
+    use std::fs;
+    use std::path::Path;
+
+    /// Commits `content` as `name` on the current branch.
+    fn commit_file(repo: &Repository, name: &str, content: &str) -> git2::Oid {
+        let workdir = repo.workdir().unwrap();
+        fs::write(workdir.join(name), content).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(name)).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = Signature::now("Test", "test@example.com").unwrap();
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<_> = parent.iter().collect();
+        repo.commit(Some("HEAD"), &sig, &sig, name, &tree, &parents)
+            .unwrap()
+    }
+
+    fn branch(repo: &Repository) -> String {
+        repo.head().unwrap().shorthand().unwrap().to_string()
+    }
+
+    fn clone(url: &str, path: &Path) -> Repository {
+        let repo = Repository::clone(url, path).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        repo
+    }
+
+    #[test]
+    fn pushes_and_pulls_through_a_bare_remote() {
+        let dir = tempfile::tempdir().unwrap();
+        let bare = Repository::init_bare(dir.path().join("remote.git")).unwrap();
+        let url = format!("file://{}", bare.path().display());
+
+        let local = Repository::init(dir.path().join("local")).unwrap();
+        local.remote("origin", &url).unwrap();
+        let first = commit_file(&local, "a.txt", "one\n");
+        let name = branch(&local);
+
+        let push = RemoteJob::Push {
+            local: name.clone(),
+            remote: "origin".into(),
+            branch: name.clone(),
+        };
+        assert!(transfer(&local, push, &Authenticator::default()).is_none());
+        let pushed = bare.find_reference(&format!("refs/heads/{}", name));
+        assert_eq!(pushed.unwrap().target(), Some(first));
+        let upstream = local.find_branch(&name, git2::BranchType::Local).unwrap();
+        assert_eq!(
+            upstream.upstream().unwrap().name().unwrap(),
+            Some(format!("origin/{}", name).as_str())
+        );
+
+        // Someone else pushes on top; pulling fast-forwards the local branch.
+        let other = clone(&url, &dir.path().join("other"));
+        let second = commit_file(&other, "b.txt", "two\n");
+        let mut remote = other.find_remote("origin").unwrap();
+        let refspec = format!("refs/heads/{0}:refs/heads/{0}", name);
+        remote.push(&[refspec], None).unwrap();
+
+        let pull = RemoteJob::Pull {
+            remote: "origin".into(),
+            branch: name,
+        };
+        assert!(transfer(&local, pull, &Authenticator::default()).is_none());
+        assert_eq!(local.head().unwrap().target(), Some(second));
+        let workdir = local.workdir().unwrap();
+        assert_eq!(fs::read_to_string(workdir.join("b.txt")).unwrap(), "two\n");
+        assert!(local.statuses(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn reports_a_rejected_push_without_moving_the_remote() {
+        let dir = tempfile::tempdir().unwrap();
+        let bare = Repository::init_bare(dir.path().join("remote.git")).unwrap();
+        let url = format!("file://{}", bare.path().display());
+
+        let first = clone(&url, &dir.path().join("first"));
+        let ahead = commit_file(&first, "a.txt", "one\n");
+        let name = branch(&first);
+        let mut remote = first.find_remote("origin").unwrap();
+        let refspec = format!("refs/heads/{0}:refs/heads/{0}", name);
+        remote.push(&[refspec], None).unwrap();
+
+        // A diverged history cannot be pushed without forcing.
+        let second = Repository::init(dir.path().join("second")).unwrap();
+        second.remote("origin", &url).unwrap();
+        commit_file(&second, "b.txt", "two\n");
+        let push = RemoteJob::Push {
+            local: branch(&second),
+            remote: "origin".into(),
+            branch: name.clone(),
+        };
+        let failure = transfer(&second, push, &Authenticator::default());
+        // libgit2 either fails the push or reports the reference as rejected.
+        assert!(failure.is_none_or(|f| f.request.is_none()));
+        let head = bare.find_reference(&format!("refs/heads/{}", name));
+        assert_eq!(head.unwrap().target(), Some(ahead));
+    }
+}
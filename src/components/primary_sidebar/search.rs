@@ -1,18 +1,30 @@
-use crate::theme::Theme;
-use crossterm::event::{KeyCode, KeyEvent};
+pub mod query;
+mod replace;
+mod worker;
+
+use self::query::{CompiledQuery, SearchQuery};
+use self::replace::{FileTargets, ReplacedFile};
+use self::worker::{SearchHandle, MAX_RESULTS};
+use crate::components::notification::{send_notification, NotificationType};
+use crate::{theme::Theme, workspace::WorkspaceWalker};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::{
     prelude::*,
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
 };
-use std::collections::HashMap;
-use std::env;
-use std::fs;
+use std::collections::{BTreeMap, HashSet};
 use std::path::PathBuf;
-use walkdir::WalkDir;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+/// How long typing must pause before the query is run.
+const SEARCH_DEBOUNCE: Duration = Duration::from_millis(250);
 
 enum ActiveInput {
     Search,
     Replace,
+    Include,
+    Exclude,
     Button,
     Results,
 }
@@ -21,56 +33,141 @@ enum ActiveInput {
 struct SearchResult {
     path: PathBuf,
     line_number: usize,
+    /// 1-based char column of the first match.
+    column: usize,
     line_content: String,
+    /// Byte range of the match in `line_content`. A line with several matches
+    /// has a result for each.
+    range: (usize, usize),
+}
+
+impl SearchResult {
+    /// Identifies the match for leaving it out of a replace-all.
+    fn key(&self) -> MatchKey {
+        (self.path.clone(), self.line_number, self.range)
+    }
+}
+
+/// A match as (path, line, byte range).
+type MatchKey = (PathBuf, usize, (usize, usize));
+
+/// The lines of the results with their match replaced, made for one query and
+/// replacement.
+struct Preview {
+    query: SearchQuery,
+    replacement: String,
+    /// `None` while the query does not compile.
+    compiled: Option<CompiledQuery>,
+    /// One per result, in order; results that stream in later are added as the
+    /// list is drawn.
+    lines: Vec<String>,
 }
 
 pub struct SearchWidget {
-    search_input: String,
+    query: SearchQuery,
     replace_input: String,
     active_input: ActiveInput,
     search_results: Vec<SearchResult>,
     results_state: ListState,
+    /// Why the last query could not be run, e.g. an invalid regular expression.
+    error: Option<String>,
+    walker: WorkspaceWalker,
+    /// The running (or last finished) search.
+    search: Option<SearchHandle>,
+    /// Set when the query was edited; the search starts once typing pauses.
+    pending_since: Option<Instant>,
+    /// Whether the results of the finished search have been put in final order.
+    results_sorted: bool,
+    /// Matches the user left out of the next replace-all.
+    excluded_matches: HashSet<MatchKey>,
+    /// Files the user left out of the next replace-all.
+    excluded_files: HashSet<PathBuf>,
+    /// Files rewritten by the last replace-all, for Alt+Z.
+    last_replace: Option<Vec<ReplacedFile>>,
+    /// Cached previews of the replacement.
+    preview: Option<Preview>,
 }
 
 impl SearchWidget {
-    pub fn new() -> Self {
+    pub fn new(walker: WorkspaceWalker) -> Self {
         Self {
-            search_input: String::new(),
+            walker,
+            query: SearchQuery::default(),
             replace_input: String::new(),
             active_input: ActiveInput::Search,
             search_results: vec![],
             results_state: ListState::default(),
+            error: None,
+            search: None,
+            pending_since: None,
+            results_sorted: true,
+            excluded_matches: HashSet::new(),
+            excluded_files: HashSet::new(),
+            last_replace: None,
+            preview: None,
         }
     }
 
+    /// Uses new ignore and exclude settings for the next search.
+    pub fn set_walker(&mut self, walker: WorkspaceWalker) {
+        self.walker = walker;
+    }
+
     pub fn render(&mut self, f: &mut Frame, area: Rect, is_active: bool, theme: &Theme) {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
                 Constraint::Length(3), // Search input
                 Constraint::Length(3), // Replace input
+                Constraint::Length(3), // Include globs
+                Constraint::Length(3), // Exclude globs
                 Constraint::Length(3), // Buttons
                 Constraint::Min(0),    // Results
             ])
             .split(area);
 
-        let search_input_p = Paragraph::new(self.search_input.as_str()).block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title("Search")
-                .border_style(self.get_border_style(is_active, &ActiveInput::Search, theme))
-                .bg(theme.primary_bg),
-        );
+        let toggle = |label: &'static str, on: bool| {
+            let style = if on {
+                Style::default()
+                    .fg(theme.highlight_fg)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::DarkGray)
+            };
+            Span::styled(format!("[{}]", label), style)
+        };
+        let search_title = Line::from(vec![
+            Span::raw("Search "),
+            toggle(".*", self.query.use_regex),
+            toggle("Aa", self.query.case_sensitive),
+            toggle("ab", self.query.whole_word),
+        ]);
 
-        let replace_input_p = Paragraph::new(self.replace_input.as_str()).block(
+        let input_block = |title: Line<'static>, input: ActiveInput| {
             Block::default()
                 .borders(Borders::ALL)
-                .title("Replace")
-                .border_style(self.get_border_style(is_active, &ActiveInput::Replace, theme))
-                .bg(theme.primary_bg),
-        );
+                .title(title)
+                .border_style(self.get_border_style(is_active, &input, theme))
+                .bg(theme.primary_bg)
+        };
+        let search_input_p = Paragraph::new(self.query.pattern.as_str())
+            .block(input_block(search_title, ActiveInput::Search));
+        let replace_input_p = Paragraph::new(self.replace_input.as_str())
+            .block(input_block(Line::from("Replace"), ActiveInput::Replace));
+        let include_input_p = Paragraph::new(self.query.include.as_str()).block(input_block(
+            Line::from("Files to include"),
+            ActiveInput::Include,
+        ));
+        let exclude_input_p = Paragraph::new(self.query.exclude.as_str()).block(input_block(
+            Line::from("Files to exclude"),
+            ActiveInput::Exclude,
+        ));
 
-        let replace_button = Paragraph::new("Replace All")
+        let replace_label = match self.included_count() {
+            0 => "Replace All".to_string(),
+            n => format!("Replace All ({})", n),
+        };
+        let replace_button = Paragraph::new(replace_label)
             .alignment(Alignment::Center)
             .block(
                 Block::default()
@@ -79,41 +176,115 @@ impl SearchWidget {
                     .bg(theme.secondary_bg),
             );
 
+        f.render_widget(search_input_p, chunks[0]);
+        f.render_widget(replace_input_p, chunks[1]);
+        f.render_widget(include_input_p, chunks[2]);
+        f.render_widget(exclude_input_p, chunks[3]);
+        f.render_widget(replace_button, chunks[4]);
+
+        let results_block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.results_title())
+            .border_style(self.get_border_style(is_active, &ActiveInput::Results, theme))
+            .bg(theme.primary_bg);
+
+        if let Some(error) = &self.error {
+            let error_p = Paragraph::new(error.as_str())
+                .style(Style::default().fg(Color::Red))
+                .wrap(Wrap { trim: false })
+                .block(results_block);
+            f.render_widget(error_p, chunks[5]);
+            return;
+        }
+
+        let match_style = Style::default()
+            .fg(theme.highlight_fg)
+            .add_modifier(Modifier::BOLD);
+        let root = self.walker.root().to_path_buf();
+        // While a replacement is being edited, each match shows the line before and
+        // after, and whether it takes part in the replace-all.
+        if self.is_previewing() {
+            self.update_preview();
+        }
+        let preview = self
+            .preview
+            .as_ref()
+            .filter(|p| self.is_previewing() && p.compiled.is_some());
         let results: Vec<ListItem> = self
             .search_results
             .iter()
-            .map(|r| {
-                let path_display =
-                    if let Ok(rel_path) = r.path.strip_prefix(env::current_dir().unwrap()) {
-                        rel_path.to_string_lossy()
-                    } else {
-                        r.path.to_string_lossy()
-                    };
-                let content = format!("{}:{}: {}", path_display, r.line_number, r.line_content);
-                ListItem::new(content)
+            .enumerate()
+            .map(|(i, r)| {
+                let path_display = r.path.strip_prefix(&root).unwrap_or(&r.path);
+                let location = format!("{}:{}: ", path_display.to_string_lossy(), r.line_number);
+                let Some(after) = preview.and_then(|p| p.lines.get(i)) else {
+                    let mut spans =
+                        vec![Span::styled(location, Style::default().fg(Color::DarkGray))];
+                    spans.extend(highlight_ranges(&r.line_content, &[r.range], match_style));
+                    return ListItem::new(Line::from(spans));
+                };
+
+                let included = self.is_included(r);
+                let marker = if included { "[x] " } else { "[ ] " };
+                let (removed_style, added_style) = if included {
+                    (
+                        Style::default()
+                            .fg(Color::Red)
+                            .add_modifier(Modifier::CROSSED_OUT),
+                        Style::default().fg(Color::Green),
+                    )
+                } else {
+                    (
+                        Style::default().fg(Color::DarkGray),
+                        Style::default().fg(Color::DarkGray),
+                    )
+                };
+                let mut before = vec![
+                    Span::raw(marker),
+                    Span::styled(location, Style::default().fg(Color::DarkGray)),
+                    Span::styled("- ", removed_style.remove_modifier(Modifier::CROSSED_OUT)),
+                ];
+                before.extend(highlight_ranges(&r.line_content, &[r.range], removed_style));
+                let after = Line::from(vec![
+                    Span::raw("    "),
+                    Span::styled("+ ", added_style),
+                    Span::styled(after.trim_start().to_string(), added_style),
+                ]);
+                ListItem::new(vec![Line::from(before), after])
             })
             .collect();
         let results_list = List::new(results)
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .title("Results")
-                    .border_style(self.get_border_style(is_active, &ActiveInput::Results, theme))
-                    .bg(theme.primary_bg),
-            )
+            .block(results_block)
             .highlight_style(Style::default().bg(theme.highlight_bg).fg(theme.text_fg));
 
-        f.render_widget(search_input_p, chunks[0]);
-        f.render_widget(replace_input_p, chunks[1]);
-        f.render_widget(replace_button, chunks[2]);
-        f.render_stateful_widget(results_list, chunks[3], &mut self.results_state);
+        f.render_stateful_widget(results_list, chunks[5], &mut self.results_state);
     }
 
     pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if key.modifiers.contains(KeyModifiers::ALT) {
+            let toggled = match key.code {
+                KeyCode::Char('z') => {
+                    self.undo_replace();
+                    return true;
+                }
+                KeyCode::Char('r') => &mut self.query.use_regex,
+                KeyCode::Char('c') => &mut self.query.case_sensitive,
+                KeyCode::Char('w') => &mut self.query.whole_word,
+                _ => return false,
+            };
+            *toggled = !*toggled;
+            if !self.query.pattern.is_empty() {
+                self.perform_search();
+            }
+            return true;
+        }
         match key.code {
             KeyCode::Tab => self.cycle_focus(),
             _ => match self.active_input {
-                ActiveInput::Search | ActiveInput::Replace => self.handle_text_input(key),
+                ActiveInput::Search
+                | ActiveInput::Replace
+                | ActiveInput::Include
+                | ActiveInput::Exclude => self.handle_text_input(key),
                 ActiveInput::Button if key.code == KeyCode::Enter => {
                     self.perform_replace_all();
                     true
@@ -127,7 +298,9 @@ impl SearchWidget {
     fn cycle_focus(&mut self) -> bool {
         self.active_input = match self.active_input {
             ActiveInput::Search => ActiveInput::Replace,
-            ActiveInput::Replace => ActiveInput::Button,
+            ActiveInput::Replace => ActiveInput::Include,
+            ActiveInput::Include => ActiveInput::Exclude,
+            ActiveInput::Exclude => ActiveInput::Button,
             ActiveInput::Button => ActiveInput::Results,
             ActiveInput::Results => ActiveInput::Search,
         };
@@ -136,24 +309,129 @@ impl SearchWidget {
 
     fn handle_text_input(&mut self, key: KeyEvent) -> bool {
         let input_str = match self.active_input {
-            ActiveInput::Search => &mut self.search_input,
+            ActiveInput::Search => &mut self.query.pattern,
             ActiveInput::Replace => &mut self.replace_input,
+            ActiveInput::Include => &mut self.query.include,
+            ActiveInput::Exclude => &mut self.query.exclude,
             _ => return false,
         };
         match key.code {
-            KeyCode::Char(c) => {
-                input_str.push(c);
-                true
-            }
+            KeyCode::Char(c) => input_str.push(c),
             KeyCode::Backspace => {
                 input_str.pop();
-                true
             }
-            KeyCode::Enter if matches!(self.active_input, ActiveInput::Search) => {
+            KeyCode::Enter if !matches!(self.active_input, ActiveInput::Replace) => {
                 self.perform_search();
-                true
+                return true;
             }
-            _ => false,
+            _ => return false,
+        }
+        if !matches!(self.active_input, ActiveInput::Replace) {
+            self.schedule_search();
+        }
+        true
+    }
+
+    /// The file and 1-based (line, column) of the selected result, if the results
+    /// list has focus.
+    pub fn selected_location(&self) -> Option<(PathBuf, usize, usize)> {
+        if !matches!(self.active_input, ActiveInput::Results) {
+            return None;
+        }
+        let r = self.search_results.get(self.results_state.selected()?)?;
+        Some((r.path.clone(), r.line_number, r.column))
+    }
+
+    /// Selects the next (or previous) result, wrapping around, and returns its
+    /// location.
+    pub fn step_result(&mut self, forward: bool) -> Option<(PathBuf, usize, usize)> {
+        let len = self.search_results.len();
+        if len == 0 {
+            return None;
+        }
+        let i = match self.results_state.selected() {
+            None if forward => 0,
+            None => len - 1,
+            Some(i) if forward => (i + 1) % len,
+            Some(i) => i.checked_sub(1).unwrap_or(len - 1),
+        };
+        self.results_state.select(Some(i));
+        let r = &self.search_results[i];
+        Some((r.path.clone(), r.line_number, r.column))
+    }
+
+    /// Cancels the running search and starts a new one once typing pauses.
+    fn schedule_search(&mut self) {
+        if let Some(search) = &self.search {
+            search.cancel();
+        }
+        self.pending_since = Some(Instant::now());
+    }
+
+    fn is_searching(&self) -> bool {
+        self.pending_since.is_some() || self.search.as_ref().is_some_and(|s| !s.is_done())
+    }
+
+    /// Starts a scheduled search and moves streamed results into the list.
+    pub fn poll_results(&mut self) {
+        if self
+            .pending_since
+            .is_some_and(|since| since.elapsed() >= SEARCH_DEBOUNCE)
+        {
+            self.perform_search();
+        }
+        let Some(search) = &self.search else {
+            return;
+        };
+        let done = search.is_done();
+        let new_results = search.drain();
+        if !new_results.is_empty() {
+            self.search_results.extend(new_results);
+            if self.results_state.selected().is_none() {
+                self.results_state.select(Some(0));
+            }
+        }
+        if done && !self.results_sorted {
+            // Workers finish files in any order; settle on a stable one, keeping the
+            // selected result selected.
+            let selected = self
+                .results_state
+                .selected()
+                .and_then(|i| self.search_results.get(i))
+                .map(SearchResult::key);
+            self.search_results.sort_by(|a, b| {
+                (&a.path, a.line_number, a.range).cmp(&(&b.path, b.line_number, b.range))
+            });
+            if let Some(key) = selected {
+                let index = self.search_results.iter().position(|r| r.key() == key);
+                self.results_state.select(index);
+            }
+            self.results_sorted = true;
+            self.preview = None;
+        }
+    }
+
+    fn results_title(&self) -> String {
+        let files = {
+            let mut paths: Vec<&PathBuf> = self.search_results.iter().map(|r| &r.path).collect();
+            paths.dedup();
+            paths.len()
+        };
+        let count = format!("{} matches in {} files", self.search_results.len(), files);
+        let Some(search) = &self.search else {
+            return "Results".to_string();
+        };
+        let searched = search.progress.files_searched.load(Ordering::Relaxed);
+        let skipped = search.progress.files_skipped.load(Ordering::Relaxed);
+        if self.is_searching() {
+            format!("Searching… {} ({} files searched)", count, searched)
+        } else if search.hit_limit() {
+            format!("Results: {} (stopped at {} matches)", count, MAX_RESULTS)
+        } else {
+            format!(
+                "Results: {} ({} searched, {} skipped)",
+                count, searched, skipped
+            )
         }
     }
 
@@ -162,7 +440,29 @@ impl SearchWidget {
             return false;
         }
         let len = self.search_results.len();
+        let selected = self
+            .results_state
+            .selected()
+            .and_then(|i| self.search_results.get(i));
         match key.code {
+            KeyCode::Char(' ') => {
+                if let Some(r) = selected {
+                    let key = r.key();
+                    if !self.excluded_matches.remove(&key) {
+                        self.excluded_matches.insert(key);
+                    }
+                }
+                true
+            }
+            KeyCode::Char('f') => {
+                if let Some(r) = selected {
+                    let path = r.path.clone();
+                    if !self.excluded_files.remove(&path) {
+                        self.excluded_files.insert(path);
+                    }
+                }
+                true
+            }
             KeyCode::Down => {
                 let i = self.results_state.selected().map_or(0, |i| (i + 1) % len);
                 self.results_state.select(Some(i));
@@ -180,68 +480,158 @@ impl SearchWidget {
         }
     }
 
+    /// Compiles the query, recording a displayable error if it is invalid.
+    fn compile_query(&mut self) -> Option<CompiledQuery> {
+        match self.query.compile() {
+            Ok(compiled) => {
+                self.error = None;
+                Some(compiled)
+            }
+            Err(e) => {
+                self.error = Some(e);
+                None
+            }
+        }
+    }
+
+    /// Cancels any running search and starts the current query on the worker pool.
     fn perform_search(&mut self) {
+        self.pending_since = None;
+        self.search = None;
         self.search_results.clear();
         self.results_state.select(None);
-        if self.search_input.is_empty() {
+        self.error = None;
+        self.excluded_matches.clear();
+        self.excluded_files.clear();
+        self.preview = None;
+        if self.query.pattern.is_empty() {
             return;
         }
+        let Some(compiled) = self.compile_query() else {
+            return;
+        };
+        self.search = Some(SearchHandle::start(&self.walker, compiled));
+        self.results_sorted = false;
+    }
 
-        let search_term = self.search_input.clone();
-        let root = env::current_dir().unwrap_or_else(|_| "/".into());
-
-        for entry in WalkDir::new(root)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file())
-        {
-            let path = entry.path();
-            if path.to_string_lossy().contains(".git") {
-                continue;
-            }
+    /// Whether the results list shows before/after previews of the replacement.
+    fn is_previewing(&self) -> bool {
+        !self.replace_input.is_empty()
+            || matches!(
+                self.active_input,
+                ActiveInput::Replace | ActiveInput::Button
+            )
+    }
 
-            if let Ok(content) = fs::read_to_string(path) {
-                for (i, line) in content.lines().enumerate() {
-                    if line.contains(&search_term) {
-                        self.search_results.push(SearchResult {
-                            path: path.to_path_buf(),
-                            line_number: i + 1,
-                            line_content: line.trim().to_string(),
-                        });
-                    }
-                }
-            }
+    /// Brings the preview up to date with the query, the replacement and the
+    /// results. The query is compiled without touching the displayed error.
+    fn update_preview(&mut self) {
+        let stale = self
+            .preview
+            .as_ref()
+            .is_none_or(|p| p.query != self.query || p.replacement != self.replace_input);
+        if stale {
+            self.preview = Some(Preview {
+                query: self.query.clone(),
+                replacement: self.replace_input.clone(),
+                compiled: self.query.compile().ok(),
+                lines: Vec::new(),
+            });
         }
-        if !self.search_results.is_empty() {
-            self.results_state.select(Some(0));
+        let Some(Preview {
+            replacement,
+            compiled: Some(compiled),
+            lines,
+            ..
+        }) = &mut self.preview
+        else {
+            return;
+        };
+        for r in self.search_results.iter().skip(lines.len()) {
+            lines.push(compiled.replace_ranges(&r.line_content, &[r.range], replacement));
         }
     }
 
+    fn is_included(&self, result: &SearchResult) -> bool {
+        !self.excluded_files.contains(&result.path)
+            && !self.excluded_matches.contains(&result.key())
+    }
+
+    fn included_count(&self) -> usize {
+        self.search_results
+            .iter()
+            .filter(|r| self.is_included(r))
+            .count()
+    }
+
+    /// Replaces the included matches, writing each file atomically.
     fn perform_replace_all(&mut self) {
-        if self.search_input.is_empty() || self.search_results.is_empty() {
+        if self.query.pattern.is_empty() || self.search_results.is_empty() {
             return;
         }
-
-        let mut file_changes: HashMap<PathBuf, String> = HashMap::new();
-        for result in &self.search_results {
-            if !file_changes.contains_key(&result.path) {
-                if let Ok(content) = fs::read_to_string(&result.path) {
-                    file_changes.insert(result.path.clone(), content);
-                }
-            }
+        if self.is_searching() {
+            send_notification(
+                "Wait for the search to finish before replacing".to_string(),
+                NotificationType::Warning,
+            );
+            return;
         }
+        let Some(compiled) = self.compile_query() else {
+            return;
+        };
 
-        for (_path, content) in file_changes.iter_mut() {
-            *content = content.replace(&self.search_input, &self.replace_input);
+        let mut targets: BTreeMap<PathBuf, FileTargets> = BTreeMap::new();
+        for result in self.search_results.iter().filter(|r| self.is_included(r)) {
+            targets
+                .entry(result.path.clone())
+                .or_default()
+                .entry(result.line_number)
+                .or_insert_with(|| (result.line_content.clone(), Vec::new()))
+                .1
+                .push(result.range);
+        }
+        if targets.is_empty() {
+            return;
         }
 
-        for (path, new_content) in file_changes {
-            let _ = fs::write(path, new_content);
+        let outcome = replace::replace_in_files(&compiled, &self.replace_input, &targets);
+        for error in &outcome.errors {
+            send_notification(error.clone(), NotificationType::Error);
+        }
+        if !outcome.files.is_empty() {
+            send_notification(
+                format!(
+                    "Replaced {} matches in {} files (Alt+Z to undo)",
+                    outcome.matches,
+                    outcome.files.len()
+                ),
+                NotificationType::Info,
+            );
+            self.last_replace = Some(outcome.files);
         }
 
         self.perform_search();
     }
 
+    /// Restores the files changed by the last replace-all.
+    fn undo_replace(&mut self) {
+        let Some(files) = self.last_replace.take() else {
+            send_notification("Nothing to undo".to_string(), NotificationType::Info);
+            return;
+        };
+        let (restored, errors) = replace::undo(&files);
+        for error in errors {
+            send_notification(error, NotificationType::Error);
+        }
+        if restored > 0 {
+            send_notification(
+                format!("Restored {} files", restored),
+                NotificationType::Info,
+            );
+        }
+        self.perform_search();
+    }
+
     fn get_border_style(&self, is_active: bool, input: &ActiveInput, theme: &Theme) -> Style {
         if is_active && std::mem::discriminant(&self.active_input) == std::mem::discriminant(input)
         {
@@ -252,8 +642,24 @@ impl SearchWidget {
     }
 }
 
-impl Default for SearchWidget {
-    fn default() -> Self {
-        Self::new()
+/// Splits `line` (leading whitespace trimmed) into spans with `ranges` highlighted.
+fn highlight_ranges(line: &str, ranges: &[(usize, usize)], style: Style) -> Vec<Span<'static>> {
+    let offset = line.len() - line.trim_start().len();
+    let mut spans = Vec::new();
+    let mut pos = offset;
+    for &(start, end) in ranges {
+        let start = start.max(pos);
+        if end <= start {
+            continue;
+        }
+        if start > pos {
+            spans.push(Span::raw(line[pos..start].to_string()));
+        }
+        spans.push(Span::styled(line[start..end].to_string(), style));
+        pos = end;
+    }
+    if pos < line.len() {
+        spans.push(Span::raw(line[pos..].to_string()));
     }
+    spans
 }
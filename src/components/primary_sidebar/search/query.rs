@@ -0,0 +1,130 @@
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use regex::{Regex, RegexBuilder};
+use std::path::Path;
+
+/// What the user typed into the search form.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    pub pattern: String,
+    pub use_regex: bool,
+    pub case_sensitive: bool,
+    pub whole_word: bool,
+    /// Comma-separated globs; when non-empty only matching files are searched.
+    pub include: String,
+    /// Comma-separated globs of files to skip.
+    pub exclude: String,
+}
+
+/// A validated query, ready to run against files.
+#[derive(Debug, Clone)]
+pub struct CompiledQuery {
+    regex: Regex,
+    use_regex: bool,
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+}
+
+impl SearchQuery {
+    /// Builds the matcher, returning a message suitable for display if the pattern
+    /// or one of the globs is invalid.
+    pub fn compile(&self) -> Result<CompiledQuery, String> {
+        let mut source = if self.use_regex {
+            self.pattern.clone()
+        } else {
+            regex::escape(&self.pattern)
+        };
+        if self.whole_word {
+            source = format!(r"\b(?:{})\b", source);
+        }
+        let regex = RegexBuilder::new(&source)
+            .case_insensitive(!self.case_sensitive)
+            .build()
+            .map_err(|e| format!("Invalid regular expression: {}", e))?;
+
+        Ok(CompiledQuery {
+            regex,
+            use_regex: self.use_regex,
+            include: compile_globs(&self.include).map_err(|e| format!("Include: {}", e))?,
+            exclude: compile_globs(&self.exclude).map_err(|e| format!("Exclude: {}", e))?,
+        })
+    }
+}
+
+impl CompiledQuery {
+    /// Whether a file, given relative to the workspace root, passes the include and
+    /// exclude globs. Globs are matched against the whole path and the file name.
+    pub fn matches_path(&self, relative: &Path) -> bool {
+        let matches = |set: &GlobSet| {
+            set.is_match(relative)
+                || relative
+                    .file_name()
+                    .is_some_and(|name| set.is_match(Path::new(name)))
+        };
+        if let Some(exclude) = &self.exclude {
+            if matches(exclude) {
+                return false;
+            }
+        }
+        self.include.as_ref().is_none_or(matches)
+    }
+
+    /// Byte ranges of every match in `line`.
+    pub fn find_in_line(&self, line: &str) -> Vec<(usize, usize)> {
+        self.regex
+            .find_iter(line)
+            .filter(|m| !m.is_empty())
+            .map(|m| (m.start(), m.end()))
+            .collect()
+    }
+
+    /// Replaces the matches in `text` that span one of the byte `ranges` and
+    /// leaves the others. In regex mode `$1`, `${name}` and `$$` in `replacement`
+    /// refer to capture groups; otherwise it is inserted literally.
+    pub fn replace_ranges(
+        &self,
+        text: &str,
+        ranges: &[(usize, usize)],
+        replacement: &str,
+    ) -> String {
+        let mut replaced = String::with_capacity(text.len());
+        let mut last = 0;
+        for captures in self.regex.captures_iter(text) {
+            let Some(m) = captures.get(0) else {
+                continue;
+            };
+            if !ranges.contains(&(m.start(), m.end())) {
+                continue;
+            }
+            replaced.push_str(&text[last..m.start()]);
+            if self.use_regex {
+                captures.expand(replacement, &mut replaced);
+            } else {
+                replaced.push_str(replacement);
+            }
+            last = m.end();
+        }
+        replaced.push_str(&text[last..]);
+        replaced
+    }
+}
+
+/// Parses a comma-separated glob list. A bare directory name such as `src` also
+/// matches everything below it.
+fn compile_globs(list: &str) -> Result<Option<GlobSet>, String> {
+    let patterns: Vec<&str> = list
+        .split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .collect();
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let pattern = pattern.trim_end_matches('/');
+        for glob in [pattern.to_string(), format!("{}/**", pattern)] {
+            builder.add(Glob::new(&glob).map_err(|e| e.to_string())?);
+        }
+    }
+    builder.build().map(Some).map_err(|e| e.to_string())
+}
@@ -0,0 +1,116 @@
+use super::query::CompiledQuery;
+use crate::file_ops::write_atomic;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The matches to rewrite in one file, by 1-based line number: the line's content
+/// when it was found, used to detect files that changed since the search, and the
+/// byte ranges of the matches to replace in it.
+pub type FileTargets = BTreeMap<usize, (String, Vec<(usize, usize)>)>;
+
+/// What a replace-all did, kept so it can be undone.
+pub struct ReplaceOutcome {
+    pub files: Vec<ReplacedFile>,
+    pub matches: usize,
+    pub errors: Vec<String>,
+}
+
+pub struct ReplacedFile {
+    path: PathBuf,
+    original: String,
+    replaced: String,
+}
+
+/// Rewrites the given matches of each file. A file is written only if at least
+/// one line still matches what the search saw; lines that changed are skipped.
+pub fn replace_in_files(
+    query: &CompiledQuery,
+    replacement: &str,
+    targets: &BTreeMap<PathBuf, FileTargets>,
+) -> ReplaceOutcome {
+    let mut outcome = ReplaceOutcome {
+        files: Vec::new(),
+        matches: 0,
+        errors: Vec::new(),
+    };
+    for (path, lines) in targets {
+        match replace_in_file(query, replacement, path, lines) {
+            Ok(Some((file, count))) => {
+                outcome.files.push(file);
+                outcome.matches += count;
+            }
+            Ok(None) => outcome.errors.push(format!(
+                "{} changed since the search; skipped",
+                path.display()
+            )),
+            Err(e) => outcome.errors.push(format!("{:#}", e)),
+        }
+    }
+    outcome
+}
+
+fn replace_in_file(
+    query: &CompiledQuery,
+    replacement: &str,
+    path: &Path,
+    lines: &FileTargets,
+) -> anyhow::Result<Option<(ReplacedFile, usize)>> {
+    let original = fs::read_to_string(path)?;
+    let mut count = 0;
+    // Lines are replaced one at a time so that `^`, `$` and the results list agree.
+    let replaced: String = original
+        .split_inclusive('\n')
+        .enumerate()
+        .map(|(i, line)| {
+            let body = line.trim_end_matches(['\n', '\r']);
+            let ending = &line[body.len()..];
+            match lines.get(&(i + 1)) {
+                Some((content, ranges)) if content == body => {
+                    count += ranges.len();
+                    format!(
+                        "{}{}",
+                        query.replace_ranges(body, ranges, replacement),
+                        ending
+                    )
+                }
+                _ => line.to_string(),
+            }
+        })
+        .collect();
+    if count == 0 {
+        return Ok(None);
+    }
+    write_atomic(path, &replaced)?;
+    Ok(Some((
+        ReplacedFile {
+            path: path.to_path_buf(),
+            original,
+            replaced,
+        },
+        count,
+    )))
+}
+
+/// Restores the files of a replace-all. Files edited since then are left alone and
+/// reported as errors.
+pub fn undo(files: &[ReplacedFile]) -> (usize, Vec<String>) {
+    let mut restored = 0;
+    let mut errors = Vec::new();
+    for file in files {
+        match fs::read_to_string(&file.path) {
+            Ok(current) if current == file.replaced => {
+                match write_atomic(&file.path, &file.original) {
+                    Ok(()) => restored += 1,
+                    Err(e) => errors.push(format!("{:#}", e)),
+                }
+            }
+            Ok(_) => errors.push(format!(
+                "{} was modified after the replace; not restored",
+                file.path.display()
+            )),
+            Err(e) => errors.push(format!("Could not read {}: {}", file.path.display(), e)),
+        }
+    }
+    (restored, errors)
+}
@@ -0,0 +1,153 @@
+use super::{query::CompiledQuery, SearchResult};
+use crate::workspace::WorkspaceWalker;
+use ignore::WalkState;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+/// Files larger than this are not searched.
+const MAX_FILE_SIZE: u64 = 4 * 1024 * 1024;
+/// Only this many leading bytes are checked for NUL when detecting binary files.
+const BINARY_CHECK_LEN: usize = 8 * 1024;
+/// The search stops once this many matching lines were found.
+pub const MAX_RESULTS: usize = 10_000;
+
+/// Counters shared with the worker threads, read by the UI for progress display.
+#[derive(Default)]
+pub struct SearchProgress {
+    pub files_searched: AtomicUsize,
+    pub files_skipped: AtomicUsize,
+    pub results: AtomicUsize,
+}
+
+/// A search running on the walker's thread pool. Dropping it cancels the search.
+pub struct SearchHandle {
+    receiver: mpsc::Receiver<Vec<SearchResult>>,
+    cancelled: Arc<AtomicBool>,
+    done: Arc<AtomicBool>,
+    pub progress: Arc<SearchProgress>,
+}
+
+impl SearchHandle {
+    /// Walks the workspace in parallel, sending the matches of each file as soon as
+    /// the file has been searched.
+    pub fn start(walker: &WorkspaceWalker, query: CompiledQuery) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let done = Arc::new(AtomicBool::new(false));
+        let progress = Arc::new(SearchProgress::default());
+
+        let walker = walker.clone();
+        let query = Arc::new(query);
+        let (thread_cancelled, thread_done, thread_progress) =
+            (cancelled.clone(), done.clone(), progress.clone());
+        thread::spawn(move || {
+            let root = walker.root().to_path_buf();
+            walker.builder(&root).build_parallel().run(|| {
+                let tx = tx.clone();
+                let query = query.clone();
+                let cancelled = thread_cancelled.clone();
+                let progress = thread_progress.clone();
+                let root = root.clone();
+                Box::new(move |entry| {
+                    if cancelled.load(Ordering::Relaxed) {
+                        return WalkState::Quit;
+                    }
+                    let Ok(entry) = entry else {
+                        return WalkState::Continue;
+                    };
+                    if !entry.file_type().is_some_and(|t| t.is_file()) {
+                        return WalkState::Continue;
+                    }
+                    let relative = entry.path().strip_prefix(&root).unwrap_or(entry.path());
+                    if !query.matches_path(relative) {
+                        return WalkState::Continue;
+                    }
+                    match search_file(entry.path(), &query) {
+                        Some(results) => {
+                            progress.files_searched.fetch_add(1, Ordering::Relaxed);
+                            if results.is_empty() {
+                                return WalkState::Continue;
+                            }
+                            let total =
+                                progress.results.fetch_add(results.len(), Ordering::Relaxed)
+                                    + results.len();
+                            if tx.send(results).is_err() || total >= MAX_RESULTS {
+                                cancelled.store(true, Ordering::Relaxed);
+                                return WalkState::Quit;
+                            }
+                        }
+                        None => {
+                            progress.files_skipped.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                    WalkState::Continue
+                })
+            });
+            thread_done.store(true, Ordering::SeqCst);
+        });
+
+        Self {
+            receiver: rx,
+            cancelled,
+            done,
+            progress,
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Results that arrived since the last call.
+    pub fn drain(&self) -> Vec<SearchResult> {
+        self.receiver.try_iter().flatten().collect()
+    }
+
+    /// Whether all worker threads have finished. Call [`drain`](Self::drain) once
+    /// more afterwards to collect the last results.
+    pub fn is_done(&self) -> bool {
+        self.done.load(Ordering::SeqCst)
+    }
+
+    /// Whether the search stopped early because it hit [`MAX_RESULTS`].
+    pub fn hit_limit(&self) -> bool {
+        self.progress.results.load(Ordering::Relaxed) >= MAX_RESULTS
+    }
+}
+
+impl Drop for SearchHandle {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// Searches one file. Returns `None` if the file was skipped because it is too large,
+/// binary, not UTF-8 or unreadable.
+fn search_file(path: &Path, query: &CompiledQuery) -> Option<Vec<SearchResult>> {
+    let metadata = fs::metadata(path).ok()?;
+    if metadata.len() > MAX_FILE_SIZE {
+        return None;
+    }
+    let bytes = fs::read(path).ok()?;
+    if bytes[..bytes.len().min(BINARY_CHECK_LEN)].contains(&0) {
+        return None;
+    }
+    let content = String::from_utf8(bytes).ok()?;
+
+    let mut results = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        for range in query.find_in_line(line) {
+            results.push(SearchResult {
+                path: path.to_path_buf(),
+                line_number: i + 1,
+                column: line[..range.0].chars().count() + 1,
+                line_content: line.to_string(),
+                range,
+            });
+        }
+    }
+    Some(results)
+}
@@ -0,0 +1,242 @@
+//! Selecting, copying and pasting text in the PTY-backed views: the terminal
+//! panel and the external editor.
+
+use crate::event_handler::PtyInput;
+use ratatui::{
+    buffer::{Buffer, Cell},
+    layout::Rect,
+    style::Modifier,
+};
+use std::sync::MutexGuard;
+use tui_term::vt100::{Parser, Screen};
+
+/// A cell of the terminal output. `line` counts from the top of the live screen
+/// and is negative for lines in the scrollback.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Point {
+    pub line: isize,
+    pub col: u16,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Selection {
+    anchor: Point,
+    head: Point,
+    /// Still following the mouse.
+    pub dragging: bool,
+}
+
+impl Selection {
+    pub fn new(at: Point, dragging: bool) -> Self {
+        Self {
+            anchor: at,
+            head: at,
+            dragging,
+        }
+    }
+
+    pub fn head(&self) -> Point {
+        self.head
+    }
+
+    /// Moves the end of the selection to `point`.
+    pub fn extend_to(&mut self, point: Point) {
+        self.head = point;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.anchor == self.head
+    }
+
+    /// Start and end, with the end cell included.
+    fn ordered(&self) -> (Point, Point) {
+        if self.anchor <= self.head {
+            (self.anchor, self.head)
+        } else {
+            (self.head, self.anchor)
+        }
+    }
+
+    /// The selected text, with line breaks where the lines did not wrap.
+    pub fn text(&self, parser: &mut Parser) -> String {
+        let (start, end) = self.ordered();
+        // Scroll so the first selected line is on top and read the rest below it.
+        let offset = usize::try_from(-start.line).unwrap_or(0);
+        let top = start.line + offset as isize;
+        let last_row = (end.line - start.line + top) as u16;
+        with_view(parser, offset, last_row + 1, |screen| {
+            screen.contents_between(top as u16, start.col, last_row, end.col + 1)
+        })
+    }
+
+    /// Draws the selected cells of the view `area` reversed.
+    pub fn highlight(&self, buf: &mut Buffer, area: Rect, offset: usize) {
+        let (start, end) = self.ordered();
+        highlight_range(buf, area, offset, start, end, |cell| {
+            cell.modifier.toggle(Modifier::REVERSED);
+        });
+    }
+}
+
+/// Applies `style` to the cells from `start` to `end` (inclusive) that are visible
+/// in the view `area` scrolled back `offset` lines.
+pub fn highlight_range(
+    buf: &mut Buffer,
+    area: Rect,
+    offset: usize,
+    start: Point,
+    end: Point,
+    mut style: impl FnMut(&mut Cell),
+) {
+    let top = -(offset as isize);
+    let first = start.line.max(top);
+    let last = end.line.min(top + area.height as isize - 1);
+    for line in first..=last {
+        let row = (line - top) as u16;
+        let from = if line == start.line { start.col } else { 0 };
+        let to = if line == end.line {
+            end.col.min(area.width.saturating_sub(1))
+        } else {
+            area.width.saturating_sub(1)
+        };
+        for col in from..=to {
+            if col < area.width {
+                style(&mut buf[(area.x + col, area.y + row)]);
+            }
+        }
+    }
+}
+
+/// Runs `f` on the screen scrolled back `offset` lines with at least `rows` rows.
+///
+/// vt100 0.15 can only show as much scrollback as the screen has rows, so the
+/// grid grows while `f` runs. The rows added at the bottom are blank and are
+/// dropped again afterwards; the scroll position is restored as well. Like any
+/// resize in vt100, this forgets which of the live rows wrapped.
+pub fn with_view<R>(
+    parser: &mut Parser,
+    offset: usize,
+    rows: u16,
+    f: impl FnOnce(&Screen) -> R,
+) -> R {
+    let (height, width) = parser.screen().size();
+    let previous = parser.screen().scrollback();
+    parser.set_scrollback(offset);
+    let needed = rows
+        .max(height)
+        .max(u16::try_from(parser.screen().scrollback()).unwrap_or(u16::MAX));
+    if needed > height {
+        parser.set_size(needed, width);
+    }
+    let result = f(parser.screen());
+    if needed > height {
+        parser.set_size(height, width);
+    }
+    parser.set_scrollback(previous);
+    result
+}
+
+/// A view showing a vt100 screen fed by a PTY.
+pub trait PtyScreen: PtyInput {
+    fn parser(&self) -> MutexGuard<'_, Parser>;
+    /// Where the screen was last drawn.
+    fn screen_area(&self) -> Rect;
+    fn selection(&self) -> Option<&Selection>;
+    fn set_selection(&mut self, selection: Option<Selection>);
+
+    /// The output cell under the terminal position `(column, row)`, clamped to the
+    /// screen.
+    fn point_at(&self, column: u16, row: u16) -> Point {
+        let area = self.screen_area();
+        let offset = self.parser().screen().scrollback();
+        let row = row.clamp(area.y, area.bottom().saturating_sub(1)) - area.y;
+        let col = column.clamp(area.x, area.right().saturating_sub(1)) - area.x;
+        Point {
+            line: row as isize - offset as isize,
+            col,
+        }
+    }
+
+    /// Starts selecting with the keyboard at the cursor.
+    fn start_selection(&mut self) {
+        let (row, col) = self.parser().screen().cursor_position();
+        let at = Point {
+            line: row as isize,
+            col,
+        };
+        self.set_selection(Some(Selection::new(at, false)));
+    }
+
+    /// Moves the end of the selection, starting one at the cursor if there is
+    /// none, and scrolls to keep it in view.
+    fn move_selection(&mut self, lines: isize, cols: isize) {
+        let (rows, width, offset, history) = {
+            let mut parser = self.parser();
+            let offset = parser.screen().scrollback();
+            // The scrollback length is only exposed as the largest scroll offset.
+            parser.set_scrollback(usize::MAX);
+            let history = parser.screen().scrollback();
+            parser.set_scrollback(offset);
+            let (rows, width) = parser.screen().size();
+            (rows, width, offset, history)
+        };
+        let mut selection = match self.selection() {
+            Some(selection) if !selection.dragging => *selection,
+            _ => {
+                let (row, col) = self.parser().screen().cursor_position();
+                Selection::new(
+                    Point {
+                        line: row as isize,
+                        col,
+                    },
+                    false,
+                )
+            }
+        };
+        let head = selection.head;
+        let col = head.col as isize + cols;
+        let (line, col) = if col < 0 {
+            (head.line - 1, width as isize - 1)
+        } else if col >= width as isize {
+            (head.line + 1, 0)
+        } else {
+            (head.line, col)
+        };
+        let line = (line + lines).clamp(-(history as isize), rows as isize - 1);
+        selection.head = Point {
+            line,
+            col: col as u16,
+        };
+        // Scroll when the end leaves the view.
+        let top = -(offset as isize);
+        let new_offset = if line < top {
+            -line
+        } else if line >= top + rows as isize {
+            rows as isize - 1 - line
+        } else {
+            offset as isize
+        };
+        self.parser().set_scrollback(new_offset.max(0) as usize);
+        self.set_selection(Some(selection));
+    }
+
+    /// The selected text, if anything is selected.
+    fn selected_text(&self) -> Option<String> {
+        let selection = self.selection().filter(|s| !s.is_empty())?;
+        Some(selection.text(&mut self.parser()))
+    }
+
+    /// Sends pasted text to the program, bracketed if it asked for that.
+    fn paste(&self, text: &str) {
+        let bracketed = self.parser().screen().bracketed_paste();
+        // Terminals send Enter as a carriage return.
+        let text = text.replace("\r\n", "\r").replace('\n', "\r");
+        if bracketed {
+            // The end marker must not appear inside, or the paste could escape it.
+            let text = text.replace("\x1b[201~", "");
+            self.send_input(format!("\x1b[200~{}\x1b[201~", text).as_bytes());
+        } else {
+            self.send_input(text.as_bytes());
+        }
+    }
+}
@@ -27,6 +27,11 @@ impl HelpWidget {
             ListItem::new("Ctrl+Shift+Up/Down: Prev/Next Terminal Tab"), // Note: Hardcoded
             ListItem::new("Alt+J / Alt+K: Next/Prev Sidebar Tab"),
             ListItem::new("Ctrl+Alt-B: Toggle Help"), // Updated keybinding string
+            ListItem::new("Ctrl+Alt-R: Reveal Active File"),
+            ListItem::new("F4 / Shift+F4: Next/Prev Search Result"),
+            ListItem::new("Files: Right/Left Expand/Collapse, >/< Enter/Leave Root"),
+            ListItem::new("Files: a/A New File/Folder, r Rename, c Duplicate"),
+            ListItem::new("Files: y/x/p Copy/Cut/Paste, d Delete to Trash"),
         ];
 
         let help_list = List::new(help_items)
@@ -1,8 +1,10 @@
+mod file_matcher;
+
+use self::file_matcher::FileMatcher;
+use crate::fuzzy::fuzzy_match;
 use crate::theme::Theme;
-use crate::{
-    app::App,
-    components::notification::{send_notification, NotificationType},
-};
+use crate::workspace::WorkspaceWalker;
+use crate::{app::App, components::main_widget::text_editor::TextEditor, settings::FilesConfig};
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{
     prelude::*,
@@ -11,11 +13,12 @@ use ratatui::{
 use std::env::current_dir;
 use std::path::PathBuf;
 use std::{
-    borrow::Cow,
     path::Path,
     sync::{mpsc, Arc},
 };
-use walkdir::WalkDir;
+
+/// How many recently opened files are remembered for ranking.
+const MAX_RECENT_FILES: usize = 50;
 
 /// アプリケーションの状態を変更するためのアクションを定義します。
 /// `Arc`でラップすることで、複数のコマンドアイテムで共有可能になります。
@@ -28,18 +31,43 @@ pub enum CommandItem {
 }
 
 impl CommandItem {
-    /// リストに表示するためのテキストを返します。
-    /// ファイルの場合は「ファイル名 <タブ> ディレクトリパス」の形式で表示します。
-    fn display_text(&self) -> Cow<str> {
+    /// 照合に使うテキスト（コマンド名またはファイルパス）を返します。
+    fn match_text(&self) -> &str {
+        match self {
+            CommandItem::Command { name, .. } => name,
+            CommandItem::File { path, .. } => path,
+        }
+    }
+
+    /// リストに表示する行を返します。`positions` は `match_text` 内の一致位置で、強調表示されます。
+    /// ファイルの場合は「ファイル名: ディレクトリパス」の形式で表示します。
+    fn display_line(&self, positions: &[usize], highlight: Style) -> Line<'static> {
+        let highlighted = |text: &str, offset: usize| -> Vec<Span<'static>> {
+            text.chars()
+                .enumerate()
+                .map(|(i, c)| {
+                    if positions.contains(&(offset + i)) {
+                        Span::styled(c.to_string(), highlight)
+                    } else {
+                        Span::raw(c.to_string())
+                    }
+                })
+                .collect()
+        };
         match self {
-            CommandItem::Command { name, .. } => Cow::Borrowed(name),
+            CommandItem::Command { name, .. } => Line::from(highlighted(name, 0)),
             CommandItem::File { name, path } => {
-                let p = Path::new(path);
-                let parent_dir = p
+                let path_len = path.chars().count();
+                let name_len = name.chars().count();
+                let parent_dir = Path::new(path)
                     .parent()
                     .unwrap_or_else(|| Path::new(""))
-                    .to_string_lossy();
-                Cow::Owned(format!("{}: {}", name, parent_dir))
+                    .to_string_lossy()
+                    .to_string();
+                let mut spans = highlighted(name, path_len.saturating_sub(name_len));
+                spans.push(Span::raw(": "));
+                spans.extend(highlighted(&parent_dir, 0));
+                Line::from(spans)
             }
         }
     }
@@ -54,6 +82,7 @@ impl CommandItem {
 }
 
 /// パレットのモード。コマンド検索かファイル検索かを切り替えます。
+#[derive(Clone, Copy, PartialEq, Eq)]
 enum PaletteMode {
     Command,
     File,
@@ -71,11 +100,21 @@ pub struct CommandPalette {
     commands: Vec<CommandItem>,
     files: Vec<CommandItem>,
     filtered_indices: Vec<usize>,
+    /// Matched char positions for each entry of `filtered_indices`.
+    match_positions: Vec<Vec<usize>>,
     list_state: ListState,
     mode: PaletteMode,
     is_searching: bool,
     file_view_changed: bool,
     file_receiver: Option<mpsc::Receiver<CommandItem>>,
+    walker: WorkspaceWalker,
+    matcher: FileMatcher,
+    /// Generation of the file query whose results should be shown.
+    match_generation: u64,
+    /// Workspace-relative paths of recently opened files, newest first.
+    recent_files: Vec<String>,
+    /// Select the first result when the pending file query finishes.
+    reset_selection: bool,
 }
 
 impl Default for CommandPalette {
@@ -91,11 +130,20 @@ impl CommandPalette {
             commands: Self::load_commands(),
             files: Vec::new(),
             filtered_indices: Vec::new(),
+            match_positions: Vec::new(),
             list_state: ListState::default(),
             mode: PaletteMode::File, // デフォルトをファイル検索モードに変更
             is_searching: false,
             file_view_changed: true, // Start dirty to trigger initial scan
             file_receiver: None,
+            walker: WorkspaceWalker::new(
+                current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+                &FilesConfig::default(),
+            ),
+            matcher: FileMatcher::new(),
+            match_generation: 0,
+            recent_files: Vec::new(),
+            reset_selection: false,
         };
         // Don't start search on creation, wait until palette is opened.
         s.filter_items();
@@ -111,18 +159,87 @@ impl CommandPalette {
             },
             CommandItem::Command {
                 name: "File: Save".to_string(),
+                action: Arc::new(|app| app.save_active_tab()),
+            },
+            CommandItem::Command {
+                name: "File: New Untitled Buffer".to_string(),
                 action: Arc::new(|app| {
-                    if let Some(editor) = app.get_active_editor_mut() {
-                        // This requires you to implement the `save` method on your Editor struct.
-                        if let Err(e) = editor.save() {
-                            send_notification(
-                                format!("Error saving file: {}", e),
-                                NotificationType::Error,
-                            );
-                        }
+                    let title = format!("Untitled {}", app.main_tabs.len() + 1);
+                    app.add_text_editor_tab(TextEditor::new(), title);
+                }),
+            },
+            CommandItem::Command {
+                name: "Git: Show Log".to_string(),
+                action: Arc::new(|app| app.open_git_log()),
+            },
+            CommandItem::Command {
+                name: "Git: Toggle Blame".to_string(),
+                action: Arc::new(|app| app.toggle_blame()),
+            },
+            CommandItem::Command {
+                name: "Git: Show Commit of Current Line".to_string(),
+                action: Arc::new(|app| app.show_blame_commit()),
+            },
+            CommandItem::Command {
+                name: "Git: Stash Changes".to_string(),
+                action: Arc::new(|app| {
+                    if let Some(git) = app.focus_git_sidebar() {
+                        git.start_stash(false);
+                    }
+                }),
+            },
+            CommandItem::Command {
+                name: "Git: Stash Changes Including Untracked".to_string(),
+                action: Arc::new(|app| {
+                    if let Some(git) = app.focus_git_sidebar() {
+                        git.start_stash(true);
+                    }
+                }),
+            },
+            CommandItem::Command {
+                name: "Git: Show Stashes".to_string(),
+                action: Arc::new(|app| {
+                    if let Some(git) = app.focus_git_sidebar() {
+                        git.focus_stashes();
+                    }
+                }),
+            },
+            CommandItem::Command {
+                name: "Git: Apply Latest Stash".to_string(),
+                action: Arc::new(|app| {
+                    if let Some(git) = app.focus_git_sidebar() {
+                        git.apply_stash(0, false);
+                    }
+                }),
+            },
+            CommandItem::Command {
+                name: "Git: Pop Latest Stash".to_string(),
+                action: Arc::new(|app| {
+                    if let Some(git) = app.focus_git_sidebar() {
+                        git.apply_stash(0, true);
                     }
                 }),
             },
+            CommandItem::Command {
+                name: "Git: Drop Latest Stash".to_string(),
+                action: Arc::new(|app| {
+                    if let Some(git) = app.focus_git_sidebar() {
+                        git.confirm_drop_stash_at(0);
+                    }
+                }),
+            },
+            CommandItem::Command {
+                name: "File: Reveal Active File in Explorer".to_string(),
+                action: Arc::new(|app| app.reveal_active_file()),
+            },
+            CommandItem::Command {
+                name: "Files: Toggle Hidden Files".to_string(),
+                action: Arc::new(|app| app.toggle_hidden_files()),
+            },
+            CommandItem::Command {
+                name: "Files: Toggle Ignored Files".to_string(),
+                action: Arc::new(|app| app.toggle_ignored_files()),
+            },
             CommandItem::Command {
                 name: "Settings: Open".to_string(),
                 action: Arc::new(|app| app.add_settings_tab()),
@@ -139,6 +256,38 @@ impl CommandPalette {
                 name: "Terminal: Open New".to_string(),
                 action: Arc::new(|app| app.open_new_terminal()),
             },
+            CommandItem::Command {
+                name: "Terminal: Find".to_string(),
+                action: Arc::new(|app| app.find_in_terminal()),
+            },
+            CommandItem::Command {
+                name: "Terminal: Select Text".to_string(),
+                action: Arc::new(|app| app.select_text()),
+            },
+            CommandItem::Command {
+                name: "Terminal: Split Right".to_string(),
+                action: Arc::new(|app| app.split_terminal(Direction::Horizontal)),
+            },
+            CommandItem::Command {
+                name: "Terminal: Split Down".to_string(),
+                action: Arc::new(|app| app.split_terminal(Direction::Vertical)),
+            },
+            CommandItem::Command {
+                name: "Terminal: Close Pane".to_string(),
+                action: Arc::new(|app| app.close_terminal_pane()),
+            },
+            CommandItem::Command {
+                name: "Terminal: Toggle Maximized Pane".to_string(),
+                action: Arc::new(|app| app.toggle_maximized_pane()),
+            },
+            CommandItem::Command {
+                name: "Session: Restore".to_string(),
+                action: Arc::new(|app| app.restore_session()),
+            },
+            CommandItem::Command {
+                name: "Session: Clear".to_string(),
+                action: Arc::new(|app| app.clear_session()),
+            },
             CommandItem::Command {
                 name: "Application: Quit".to_string(),
                 action: Arc::new(|app| app.show_quit_popup()),
@@ -148,28 +297,31 @@ impl CommandPalette {
 
     /// ファイル検索モードに切り替え、ファイルリストを（必要なら）読み込みます。
     pub fn enter_file_mode(&mut self) {
-        self.mode = PaletteMode::File;
+        self.set_mode(PaletteMode::File);
         self.input.clear();
         // Only start a new search if the file view has changed, or if there are no files and no search is running.
         if self.file_view_changed || (self.files.is_empty() && !self.is_searching) {
             self.files.clear(); // Clear stale results
+                                // The old matches index into the files just cleared.
+            self.set_matches(Vec::new());
+            self.matcher.clear();
             let (tx, rx) = mpsc::channel();
             self.file_receiver = Some(rx);
             self.is_searching = true;
 
+            let walker = self.walker.clone();
             std::thread::spawn(move || {
-                for entry in WalkDir::new(".")
-                    .into_iter()
-                    .filter_map(|e| e.ok())
-                    .filter(|e| e.file_type().is_file())
-                {
-                    let path_str = entry.path().to_string_lossy();
-                    // A more robust ignore mechanism would be better (e.g., using .gitignore)
-                    if path_str.contains(".git") || path_str.contains("target") {
-                        continue;
-                    }
-                    let name = entry.file_name().to_string_lossy().to_string();
-                    let path = path_str.to_string();
+                for entry in walker.files() {
+                    let name = entry
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .to_string();
+                    let path = entry
+                        .strip_prefix(walker.root())
+                        .unwrap_or(&entry)
+                        .to_string_lossy()
+                        .to_string();
                     if tx.send(CommandItem::File { name, path }).is_err() {
                         // Receiver has been dropped, so the palette was closed.
                         break; // Receiver has been dropped
@@ -181,37 +333,59 @@ impl CommandPalette {
     }
 
     /// 入力に基づいてアイテムをフィルタリングします。
+    /// コマンドはその場で照合し、ファイルはバックグラウンドの照合スレッドに依頼します。
     fn filter_items(&mut self) {
-        let (source_items, filter_text) = match self.mode {
+        match self.mode {
             PaletteMode::Command => {
                 // コマンドモードでは、">" の後のテキストでフィルタリングする
                 let filter = self.input.strip_prefix('>').unwrap_or(&self.input);
-                (&self.commands, filter)
+                let mut scored: Vec<(i64, usize, Vec<usize>)> = self
+                    .commands
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, item)| {
+                        fuzzy_match(filter, item.match_text()).map(|m| (m.score, i, m.positions))
+                    })
+                    .collect();
+                scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+                self.set_matches(scored.into_iter().map(|(_, i, p)| (i, p)).collect());
             }
-            PaletteMode::File => (&self.files, self.input.as_str()),
-        };
-        let input_lower = filter_text.to_lowercase();
-
-        self.filtered_indices = source_items
-            .iter()
-            .enumerate()
-            .filter(|(_, item)| {
-                let text_to_search = match item {
-                    CommandItem::Command { name, .. } => name.as_str(), // コマンド名で検索
-                    CommandItem::File { path, .. } => path.as_str(),
-                };
-                text_to_search.to_lowercase().contains(&input_lower)
-            })
-            .map(|(i, _)| i)
-            .collect();
-
-        if !self.filtered_indices.is_empty() {
-            self.list_state.select(Some(0));
+            PaletteMode::File => {
+                self.match_generation = self.matcher.query(&self.input, &self.recent_files);
+                self.reset_selection = true;
+            }
+        }
+        self.list_state.select(if self.filtered_indices.is_empty() {
+            None
         } else {
-            self.list_state.select(None);
+            Some(0)
+        });
+    }
+
+    /// Switches mode, dropping results that index into the other mode's items.
+    fn set_mode(&mut self, mode: PaletteMode) {
+        if self.mode != mode {
+            self.mode = mode;
+            self.set_matches(Vec::new());
         }
     }
 
+    fn set_matches(&mut self, matches: Vec<(usize, Vec<usize>)>) {
+        (self.filtered_indices, self.match_positions) = matches.into_iter().unzip();
+    }
+
+    /// Records that `path` was opened, so it ranks higher in later file searches.
+    pub fn note_recent_file(&mut self, path: &Path) {
+        let relative = path
+            .strip_prefix(self.walker.root())
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string();
+        self.recent_files.retain(|p| *p != relative);
+        self.recent_files.insert(0, relative);
+        self.recent_files.truncate(MAX_RECENT_FILES);
+    }
+
     /// キー入力を処理します。
     pub fn handle_key(&mut self, key: KeyEvent) -> CommandPaletteEvent {
         match key.code {
@@ -221,7 +395,7 @@ impl CommandPalette {
                 self.input.push(c);
                 // 入力が ">" で始まっていればコマンドモードに切り替える
                 if self.input.starts_with('>') {
-                    self.mode = PaletteMode::Command;
+                    self.set_mode(PaletteMode::Command);
                 }
                 self.filter_items();
             }
@@ -233,7 +407,7 @@ impl CommandPalette {
                 self.input.pop();
                 // 入力が ">" で始まらなくなったら（または空になったら）ファイルモードに戻す
                 if !self.input.starts_with('>') {
-                    self.mode = PaletteMode::File;
+                    self.set_mode(PaletteMode::File);
                 }
                 self.filter_items();
             }
@@ -247,38 +421,46 @@ impl CommandPalette {
     /// 選択されているアイテムのアクションを返します。
     pub fn get_selected_action(&self) -> Option<Action> {
         self.list_state.selected().and_then(|selected_idx| {
-            self.filtered_indices.get(selected_idx).map(|&item_idx| {
-                let item = match self.mode {
-                    PaletteMode::Command => &self.commands[item_idx],
-                    PaletteMode::File => &self.files[item_idx],
-                };
-                match item {
-                    CommandItem::Command { action, .. } => action.clone(),
-                    CommandItem::File { path, .. } => {
-                        let path_clone = path.clone();
-                        Arc::new(move |app: &mut App| {
-                            app.open_editor(
-                                current_dir()
-                                    .unwrap_or(PathBuf::from("./"))
-                                    .join(&path_clone)
-                                    .canonicalize()
-                                    .unwrap_or_default()
-                                    .as_path(),
-                            );
-                        })
-                    }
-                }
-            })
+            self.filtered_indices
+                .get(selected_idx)
+                .and_then(|&item_idx| {
+                    let item = match self.mode {
+                        PaletteMode::Command => self.commands.get(item_idx)?,
+                        PaletteMode::File => self.files.get(item_idx)?,
+                    };
+                    Some(match item {
+                        CommandItem::Command { action, .. } => action.clone(),
+                        CommandItem::File { path, .. } => {
+                            let path_clone = path.clone();
+                            Arc::new(move |app: &mut App| {
+                                app.open_editor(
+                                    current_dir()
+                                        .unwrap_or(PathBuf::from("./"))
+                                        .join(&path_clone)
+                                        .canonicalize()
+                                        .unwrap_or_default()
+                                        .as_path(),
+                                );
+                            })
+                        }
+                    })
+                })
         })
     }
 
     /// パレットの状態を初期状態（コマンドモード）にリセットします。
     pub fn reset(&mut self) {
-        self.mode = PaletteMode::File; // Reset to file mode
+        self.set_mode(PaletteMode::File); // Reset to file mode
         self.input.clear();
         self.filter_items();
     }
 
+    /// Uses new ignore and exclude settings for the file list.
+    pub fn set_walker(&mut self, walker: WorkspaceWalker) {
+        self.walker = walker;
+        self.file_view_changed = true;
+    }
+
     /// Marks the file list as dirty, forcing a re-scan on the next opportunity.
     pub fn set_file_view_changed(&mut self) {
         self.file_view_changed = true;
@@ -286,13 +468,13 @@ impl CommandPalette {
 
     /// Polls for asynchronously found files and updates the list.
     pub fn poll_files(&mut self) {
-        let mut received_any = false;
         let mut disconnected = false;
+        let mut new_paths = Vec::new();
 
         if let Some(rx) = &self.file_receiver {
             while let Ok(file_item) = rx.try_recv() {
+                new_paths.push(file_item.match_text().to_string());
                 self.files.push(file_item);
-                received_any = true;
             }
             // If the channel is disconnected, the search is over.
             if let Err(mpsc::TryRecvError::Disconnected) = rx.try_recv() {
@@ -305,8 +487,32 @@ impl CommandPalette {
             self.file_receiver = None;
         }
 
-        if received_any {
-            self.filter_items();
+        if !new_paths.is_empty() {
+            self.matcher.add_files(new_paths);
+            if self.mode == PaletteMode::File {
+                // Re-run the current query over the grown list without moving the selection.
+                self.match_generation = self.matcher.query(&self.input, &self.recent_files);
+            }
+        }
+
+        if let Some(results) = self.matcher.try_recv() {
+            if results.generation == self.match_generation && self.mode == PaletteMode::File {
+                self.set_matches(results.matches);
+                let selected = if self.filtered_indices.is_empty() {
+                    None
+                } else if self.reset_selection {
+                    Some(0)
+                } else {
+                    Some(
+                        self.list_state
+                            .selected()
+                            .unwrap_or(0)
+                            .min(self.filtered_indices.len() - 1),
+                    )
+                };
+                self.list_state.select(selected);
+                self.reset_selection = false;
+            }
         }
     }
 
@@ -343,10 +549,15 @@ impl CommandPalette {
             PaletteMode::File => &self.files,
         };
 
+        let highlight = Style::default()
+            .fg(theme.highlight_fg)
+            .add_modifier(Modifier::BOLD);
         let list_items: Vec<ListItem> = self
             .filtered_indices
             .iter()
-            .map(|&i| ListItem::new(source_items[i].display_text().into_owned()))
+            .zip(&self.match_positions)
+            .filter_map(|(&i, positions)| source_items.get(i).map(|item| (item, positions)))
+            .map(|(item, positions)| ListItem::new(item.display_line(positions, highlight)))
             .collect();
 
         // If searching, show a loading indicator until the first results arrive.
@@ -0,0 +1,164 @@
+use crate::fuzzy::fuzzy_match;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+/// At most this many matches are sent back per query.
+const MAX_RESULTS: usize = 500;
+/// How often the worker checks whether its query has been superseded.
+const CANCEL_CHECK_INTERVAL: usize = 1024;
+/// Bonus for the most recently opened file; older entries get linearly less.
+const RECENCY_BONUS: i64 = 60;
+
+enum Request {
+    Clear,
+    AddFiles(Vec<String>),
+    Query {
+        generation: u64,
+        pattern: String,
+        recent: Vec<String>,
+    },
+}
+
+/// Matches returned for one query, best first.
+pub struct MatchResults {
+    pub generation: u64,
+    /// (index into the file list, matched char positions in the path)
+    pub matches: Vec<(usize, Vec<usize>)>,
+}
+
+/// Scores file paths against the palette input on a background thread so typing
+/// stays responsive on large repositories. Queries are identified by a generation
+/// counter; a running query is abandoned as soon as a newer one is issued.
+pub struct FileMatcher {
+    requests: mpsc::Sender<Request>,
+    results: mpsc::Receiver<MatchResults>,
+    generation: Arc<AtomicU64>,
+}
+
+impl FileMatcher {
+    pub fn new() -> Self {
+        let (request_tx, request_rx) = mpsc::channel();
+        let (result_tx, result_rx) = mpsc::channel();
+        let generation = Arc::new(AtomicU64::new(0));
+        let latest = Arc::clone(&generation);
+        thread::spawn(move || run(request_rx, result_tx, latest));
+        Self {
+            requests: request_tx,
+            results: result_rx,
+            generation,
+        }
+    }
+
+    pub fn clear(&self) {
+        let _ = self.requests.send(Request::Clear);
+    }
+
+    pub fn add_files(&self, paths: Vec<String>) {
+        let _ = self.requests.send(Request::AddFiles(paths));
+    }
+
+    /// Starts matching `pattern`; `recent` lists recently opened paths, newest first.
+    pub fn query(&self, pattern: &str, recent: &[String]) -> u64 {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let _ = self.requests.send(Request::Query {
+            generation,
+            pattern: pattern.to_string(),
+            recent: recent.to_vec(),
+        });
+        generation
+    }
+
+    /// Returns the newest finished result, if any arrived since the last call.
+    pub fn try_recv(&self) -> Option<MatchResults> {
+        let mut latest = None;
+        while let Ok(results) = self.results.try_recv() {
+            latest = Some(results);
+        }
+        latest
+    }
+}
+
+impl Default for FileMatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn run(
+    requests: mpsc::Receiver<Request>,
+    results: mpsc::Sender<MatchResults>,
+    latest: Arc<AtomicU64>,
+) {
+    let mut files: Vec<String> = Vec::new();
+    while let Ok(request) = requests.recv() {
+        let (generation, pattern, recent) = match request {
+            Request::Clear => {
+                files.clear();
+                continue;
+            }
+            Request::AddFiles(paths) => {
+                files.extend(paths);
+                continue;
+            }
+            Request::Query {
+                generation,
+                pattern,
+                recent,
+            } => (generation, pattern, recent),
+        };
+        if latest.load(Ordering::SeqCst) != generation {
+            continue;
+        }
+
+        let recency: HashMap<&str, i64> = recent
+            .iter()
+            .enumerate()
+            .map(|(rank, path)| (path.as_str(), RECENCY_BONUS - rank as i64))
+            .filter(|&(_, bonus)| bonus > 0)
+            .collect();
+
+        let mut scored: Vec<(i64, usize, Vec<usize>)> = Vec::new();
+        let mut cancelled = false;
+        for (i, path) in files.iter().enumerate() {
+            if i % CANCEL_CHECK_INTERVAL == 0 && latest.load(Ordering::SeqCst) != generation {
+                cancelled = true;
+                break;
+            }
+            if let Some(m) = fuzzy_match(&pattern, path) {
+                let bonus = recency.get(path.as_str()).copied().unwrap_or(0);
+                scored.push((m.score + bonus, i, m.positions));
+            }
+        }
+        if cancelled {
+            continue;
+        }
+
+        // Best score first; shorter paths win ties, then walk order. Without a
+        // pattern only recency reorders the list.
+        let has_pattern = !pattern.trim().is_empty();
+        scored.sort_by(|a, b| {
+            b.0.cmp(&a.0)
+                .then_with(|| {
+                    if has_pattern {
+                        files[a.1].len().cmp(&files[b.1].len())
+                    } else {
+                        std::cmp::Ordering::Equal
+                    }
+                })
+                .then_with(|| a.1.cmp(&b.1))
+        });
+        scored.truncate(MAX_RESULTS);
+        let matches = scored.into_iter().map(|(_, i, p)| (i, p)).collect();
+        if results
+            .send(MatchResults {
+                generation,
+                matches,
+            })
+            .is_err()
+        {
+            break;
+        }
+    }
+}
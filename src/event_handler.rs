@@ -1,8 +1,9 @@
 use anyhow::Result;
-use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use crossterm::event::{self, Event};
 use std::time::Duration;
 
 use crate::components::popup::PopupResult;
+use crate::components::pty_screen::PtyScreen;
 use crate::{
     app::App,
     components::notification::{send_notification, NotificationType},
@@ -10,6 +11,7 @@ use crate::{
 use crate::{ActiveTarget, MainWidgetContent};
 mod component;
 mod global;
+mod mouse;
 mod palette;
 mod util;
 
@@ -20,6 +22,36 @@ pub enum AppEvent {
     Continue,
 }
 
+/// Hands text pasted into the hosting terminal to the focused editor or terminal.
+fn handle_paste(text: &str, app: &mut App) {
+    if app.quit_popup.is_some() || app.save_prompt.is_some() || app.show_command_palette {
+        return;
+    }
+    match app.active_target {
+        ActiveTarget::Panel => {
+            if let Some(tab) = app.terminals.get_mut(app.active_terminal_tab) {
+                let term = tab.content.active_mut();
+                term.set_selection(None);
+                term.paste(text);
+            }
+        }
+        ActiveTarget::Editor => {
+            let Some(tab) = app.main_tabs.get_mut(app.active_main_tab) else {
+                return;
+            };
+            match &mut tab.content {
+                MainWidgetContent::Editor(editor) => {
+                    editor.set_selection(None);
+                    editor.paste(text);
+                }
+                MainWidgetContent::TextEditor(editor) => editor.insert_text(text),
+                _ => {}
+            }
+        }
+        _ => {}
+    }
+}
+
 pub fn handle_events(app: &mut App) -> Result<AppEvent> {
     // Poll for any async results before checking for blocking input
     app.poll_command_palette_files();
@@ -32,16 +64,43 @@ pub fn handle_events(app: &mut App) -> Result<AppEvent> {
 
     // Read the event ONCE and dispatch it based on application state.
     // This prevents bugs from multiple `event::read()` calls.
-    if let Event::Key(key) = event::read()? {
+    let event = event::read()?;
+    if let Event::Mouse(mouse) = event {
+        mouse::handle_mouse_event(mouse, app);
+    }
+    if let Event::Paste(text) = &event {
+        handle_paste(text, app);
+    }
+    if let Event::Key(key) = event {
         // 1. Highest priority: Popups are modal and consume all input
+        if let Some((popup, _)) = &mut app.save_prompt {
+            let answer = popup.handle_key(key);
+            if app.answer_save_prompt(answer) {
+                return Ok(AppEvent::Quit);
+            }
+            return Ok(AppEvent::Continue);
+        }
         if let Some(popup) = &mut app.quit_popup {
             match popup.handle_key(key) {
-                PopupResult::Confirm => return Ok(AppEvent::Quit),
-                PopupResult::Cancel => app.quit_popup = None,
+                PopupResult::Confirm => {
+                    app.quit_popup = None;
+                    if app.request_quit() {
+                        return Ok(AppEvent::Quit);
+                    }
+                }
+                PopupResult::Cancel | PopupResult::Discard => app.quit_popup = None,
                 PopupResult::None => {}
             }
             return Ok(AppEvent::Continue);
         }
+        if let Some(tab) = app
+            .primary_sidebar_components
+            .iter_mut()
+            .find(|tab| tab.content.has_popup())
+        {
+            tab.content.handle_popup_key(key);
+            return Ok(AppEvent::Continue);
+        }
 
         // 2. Command Palette is also modal
         if app.show_command_palette {
@@ -50,8 +109,8 @@ pub fn handle_events(app: &mut App) -> Result<AppEvent> {
 
         // 3. Terminal gets priority for most keys when active
         if app.active_target == ActiveTarget::Panel {
-            // Allow only Ctrl-J (toggle panel) to be handled globally.
-            if key.code == KeyCode::Char('j') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            // Allow only the panel's own actions to be handled globally.
+            if global::bound_action(key, app).is_some_and(|a| global::is_panel_action(&a)) {
                 if let Some(app_event) = global::handle_global_keys(key, app)? {
                     return Ok(app_event);
                 }
@@ -71,12 +130,17 @@ pub fn handle_events(app: &mut App) -> Result<AppEvent> {
     }
 
     // --- Post-event processing: check for dead processes ---
-    // This logic runs regardless of whether there was an event.
+    // This logic runs regardless of whether there was an event. Tabs stay put
+    // while the unsaved changes popup refers to one by index.
     let initial_editor_len = app.main_tabs.len();
+    let keep_tabs = app.save_prompt.is_some();
     app.main_tabs.retain(|tab| match &tab.content {
-        MainWidgetContent::Editor(editor) => !editor.is_dead(),
+        MainWidgetContent::Editor(editor) => keep_tabs || !editor.is_dead(),
+        MainWidgetContent::TextEditor(_) => true, // Lives until its tab is closed
         MainWidgetContent::SettingsEditor(_) => true, // Settings editor can't die
-        MainWidgetContent::Welcome(_) => true,        // Welcome screen can't die
+        MainWidgetContent::GitLog(_) => true,
+        MainWidgetContent::Conflicts(_) => true,
+        MainWidgetContent::Welcome(_) => true, // Welcome screen can't die
     });
     if app.main_tabs.len() < initial_editor_len {
         if app.main_tabs.is_empty() {
@@ -90,15 +154,17 @@ pub fn handle_events(app: &mut App) -> Result<AppEvent> {
 
     // Terminals
     if app.show_panel {
-        let initial_term_len = app.terminals.len();
-        app.terminals.retain(|term_tab| !term_tab.content.is_dead());
+        let ended: usize = app
+            .terminals
+            .iter_mut()
+            .map(|term_tab| term_tab.content.remove_dead())
+            .sum();
+        app.terminals
+            .retain(|term_tab| !term_tab.content.is_empty());
 
-        if app.terminals.len() < initial_term_len {
+        if ended > 0 {
             send_notification(
-                format!(
-                    "{} terminal session(s) ended.",
-                    initial_term_len - app.terminals.len()
-                ),
+                format!("{} terminal session(s) ended.", ended),
                 NotificationType::Info,
             );
         }
@@ -1,13 +1,14 @@
-use super::util::send_key_to_terminal;
+use super::util::{handle_selection_key, send_key_to_terminal};
 use crate::{
     app::App,
     components::{
-        main_widget::editor::Editor, primary_sidebar::component::PrimarySidebarComponent,
+        main_widget::welcome_widget::WelcomeWidget,
+        primary_sidebar::component::PrimarySidebarComponent,
     },
     ActiveTarget, MainWidgetContent,
 };
 use anyhow::Result;
-use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
 pub fn handle_component_keys(key: KeyEvent, app: &mut App) -> Result<()> {
     match app.active_target {
@@ -22,7 +23,7 @@ pub fn handle_component_keys(key: KeyEvent, app: &mut App) -> Result<()> {
                 if is_settings_editor {
                     let content_placeholder = std::mem::replace(
                         &mut app.main_tabs[active_tab_idx].content,
-                        MainWidgetContent::Editor(Editor::new()),
+                        MainWidgetContent::Welcome(WelcomeWidget::new()),
                     );
 
                     if let MainWidgetContent::SettingsEditor(mut settings_editor) =
@@ -32,43 +33,90 @@ pub fn handle_component_keys(key: KeyEvent, app: &mut App) -> Result<()> {
                         app.main_tabs[active_tab_idx].content =
                             MainWidgetContent::SettingsEditor(settings_editor);
                     }
-                } else if let Some(editor) =
-                    app.main_tabs
-                        .get_mut(active_tab_idx)
-                        .and_then(|t| match &mut t.content {
-                            MainWidgetContent::Editor(e) => Some(e),
-                            _ => None,
-                        })
-                {
-                    send_key_to_terminal(editor, key);
+                } else {
+                    let mut refresh_git = false;
+                    let mut save_as = false;
+                    match &mut app.main_tabs[active_tab_idx].content {
+                        MainWidgetContent::Editor(editor) => {
+                            let selecting = handle_selection_key(editor, key, &app.config);
+                            if !selecting {
+                                send_key_to_terminal(editor, key);
+                            }
+                        }
+                        // A new buffer needs a file name before it can be saved.
+                        MainWidgetContent::TextEditor(editor)
+                            if editor.path().is_none()
+                                && key.modifiers.contains(KeyModifiers::CONTROL)
+                                && key.code == KeyCode::Char('s') =>
+                        {
+                            save_as = true;
+                        }
+                        MainWidgetContent::TextEditor(editor) => {
+                            editor.handle_key(key);
+                        }
+                        MainWidgetContent::GitLog(log) => {
+                            log.handle_key(key);
+                        }
+                        MainWidgetContent::Conflicts(view) => {
+                            // Resolving changes what the Git sidebar shows.
+                            refresh_git = view.handle_key(key);
+                        }
+                        _ => {}
+                    }
+                    if refresh_git {
+                        app.refresh_git();
+                    }
+                    if save_as {
+                        app.save_active_tab();
+                    }
                 }
             }
         }
         ActiveTarget::Panel => {
             if let Some(tab) = app.terminals.get_mut(app.active_terminal_tab) {
-                send_key_to_terminal(&tab.content, key);
+                let term = tab.content.active_mut();
+                let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+                match key.code {
+                    KeyCode::PageUp if shift => term.scroll_page(true),
+                    KeyCode::PageDown if shift => term.scroll_page(false),
+                    _ if term.handle_find_key(key) => {}
+                    _ if handle_selection_key(term, key, &app.config) => {}
+                    _ => send_key_to_terminal(term, key),
+                }
             }
         }
         ActiveTarget::PrimarySideBar => {
             // To avoid multiple mutable borrows of `app`, we first check if we need to open a file
             // without holding a mutable reference to the sidebar component.
             let mut file_to_open = None;
+            let mut location_to_show = None;
+            let mut conflict_to_open = None;
             if key.code == KeyCode::Enter {
                 if let Some(tab) = app
                     .primary_sidebar_components
                     .get(app.active_primary_sidebar_tab)
                 {
-                    if let PrimarySidebarComponent::FileView(f_view) = &tab.content {
-                        file_to_open = f_view.selected_file();
+                    match &tab.content {
+                        PrimarySidebarComponent::FileView(f_view) => {
+                            file_to_open = f_view.selected_file();
+                        }
+                        PrimarySidebarComponent::Search(search) => {
+                            location_to_show = search.selected_location();
+                        }
+                        PrimarySidebarComponent::Git(git) => {
+                            conflict_to_open = git.selected_conflict();
+                        }
                     }
                 }
             }
 
             if let Some(path) = file_to_open {
                 // If a file was selected, open it in a new tab.
-                let editor = Editor::with_file(path.to_path_buf());
-                let title = path.to_string_lossy().to_string(); // PathBuf to String for title
-                app.add_editor_tab(editor, title);
+                app.open_editor(&path);
+            } else if let Some((path, line, column)) = location_to_show {
+                app.show_location(&path, line, column);
+            } else if let Some(path) = conflict_to_open {
+                app.open_conflict(&path);
             } else {
                 // Otherwise, pass the key event to the active sidebar component.
                 if let Some(tab) = app
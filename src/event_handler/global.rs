@@ -1,18 +1,69 @@
 use super::{util::key_event_to_string, AppEvent};
 use crate::{
     app::App,
-    components::{panel::term::Term, primary_sidebar::component::PrimarySidebarComponent},
+    components::{panel::panes::Side, primary_sidebar::component::PrimarySidebarComponent},
     ActiveTarget,
 };
 use anyhow::Result;
 use crossterm::event::KeyEvent;
+use ratatui::layout::Direction;
 use std::env;
 
+/// Actions that still run while the terminal panel has focus. Every other key
+/// goes to the shell.
+const PANEL_ACTIONS: &[&str] = &[
+    "toggle_panel",
+    "find_in_terminal",
+    "select_text",
+    "split_pane_right",
+    "split_pane_down",
+    "close_pane",
+    "toggle_maximize_pane",
+    "focus_pane_left",
+    "focus_pane_right",
+    "focus_pane_up",
+    "focus_pane_down",
+    "resize_pane_left",
+    "resize_pane_right",
+    "resize_pane_up",
+    "resize_pane_down",
+];
+
+/// Actions on the focused terminal pane. Elsewhere their keys are left to the
+/// focused view.
+const PANE_ACTIONS: &[&str] = &[
+    "close_pane",
+    "toggle_maximize_pane",
+    "focus_pane_left",
+    "focus_pane_right",
+    "focus_pane_up",
+    "focus_pane_down",
+    "resize_pane_left",
+    "resize_pane_right",
+    "resize_pane_up",
+    "resize_pane_down",
+];
+
+/// Whether `action` is handled globally while the terminal panel has focus.
+pub fn is_panel_action(action: &str) -> bool {
+    PANEL_ACTIONS.contains(&action)
+}
+
+/// The action `key` is bound to in the global keybindings.
+pub fn bound_action(key: KeyEvent, app: &App) -> Option<String> {
+    let key_str = key_event_to_string(key)?;
+    app.config.keybindings.global.get(&key_str).cloned()
+}
+
 pub fn handle_global_keys(key: KeyEvent, app: &mut App) -> Result<Option<AppEvent>> {
     if let Some(key_str) = key_event_to_string(key) {
         if let Some(action) = app.config.keybindings.global.get(&key_str).cloned() {
+            if app.active_target != ActiveTarget::Panel && PANE_ACTIONS.contains(&action.as_str()) {
+                return Ok(None);
+            }
             match action.as_str() {
-                "quit" => return Ok(Some(AppEvent::Quit)),
+                // `request_quit` asks about unsaved files first and returns false.
+                "quit" if app.request_quit() => return Ok(Some(AppEvent::Quit)),
                 "toggle_primary_sidebar" => {
                     if !app.show_primary_sidebar {
                         app.show_primary_sidebar = true;
@@ -38,11 +89,12 @@ pub fn handle_global_keys(key: KeyEvent, app: &mut App) -> Result<Option<AppEven
 
                     if !app.show_panel {
                         if app.terminals.is_empty() {
-                            let term = Term::new(cwd_for_new_term)?;
-                            app.add_terminal_tab(term, format!("Term {}", app.terminals.len() + 1));
+                            app.open_terminal_in(cwd_for_new_term);
+                        }
+                        if !app.terminals.is_empty() {
+                            app.show_panel = true;
+                            app.active_target = ActiveTarget::Panel;
                         }
-                        app.show_panel = true;
-                        app.active_target = ActiveTarget::Panel;
                     } else if app.active_target == ActiveTarget::Panel {
                         app.show_panel = false;
                         app.active_target = ActiveTarget::Editor;
@@ -89,44 +141,18 @@ pub fn handle_global_keys(key: KeyEvent, app: &mut App) -> Result<Option<AppEven
                     }
                 }
                 "new_tab" => match app.active_target {
-                    ActiveTarget::Editor => {
-                        let editor = crate::components::main_widget::editor::Editor::new();
-                        app.add_editor_tab(editor, format!("Editor {}", app.main_tabs.len() + 1));
-                    }
-                    ActiveTarget::Panel => {
-                        let cwd = env::current_dir().ok();
-                        let term = Term::new(cwd)?;
-                        app.add_terminal_tab(term, format!("Term {}", app.terminals.len() + 1));
-                    }
-                    _ => {
-                        let editor = crate::components::main_widget::editor::Editor::new();
-                        app.add_editor_tab(editor, format!("Editor {}", app.main_tabs.len() + 1));
-                        app.active_target = ActiveTarget::Editor;
-                    }
+                    ActiveTarget::Panel => app.open_new_terminal(),
+                    _ => app.new_editor_tab(),
                 },
                 "close_tab" => match app.active_target {
-                    ActiveTarget::Editor => {
-                        if !app.main_tabs.is_empty() {
-                            app.main_tabs.remove(app.active_main_tab);
-                            if app.main_tabs.is_empty() {
-                                app.add_editor_tab(
-                                    crate::components::main_widget::editor::Editor::new(),
-                                    "Editor 1".to_string(),
-                                );
-                            } else if app.active_main_tab >= app.main_tabs.len() {
-                                app.active_main_tab = app.main_tabs.len().saturating_sub(1);
-                            }
-                        }
-                    }
-                    ActiveTarget::Panel => {
-                        if !app.terminals.is_empty() {
-                            app.terminals.remove(app.active_terminal_tab);
-                            if app.terminals.is_empty() {
-                                app.show_panel = false;
-                                app.active_target = ActiveTarget::Editor;
-                            } else if app.active_terminal_tab >= app.terminals.len() {
-                                app.active_terminal_tab = app.terminals.len().saturating_sub(1);
-                            }
+                    ActiveTarget::Editor => app.request_close_main_tab(),
+                    ActiveTarget::Panel if !app.terminals.is_empty() => {
+                        app.terminals.remove(app.active_terminal_tab);
+                        if app.terminals.is_empty() {
+                            app.show_panel = false;
+                            app.active_target = ActiveTarget::Editor;
+                        } else if app.active_terminal_tab >= app.terminals.len() {
+                            app.active_terminal_tab = app.terminals.len().saturating_sub(1);
                         }
                     }
                     _ => {}
@@ -166,6 +192,24 @@ pub fn handle_global_keys(key: KeyEvent, app: &mut App) -> Result<Option<AppEven
                     }
                     _ => {}
                 },
+                "reveal_active_file" => app.reveal_active_file(),
+                "toggle_blame" => app.toggle_blame(),
+                "next_search_result" => app.step_search_result(true),
+                "prev_search_result" => app.step_search_result(false),
+                "find_in_terminal" => app.find_in_terminal(),
+                "select_text" => app.select_text(),
+                "split_pane_right" => app.split_terminal(Direction::Horizontal),
+                "split_pane_down" => app.split_terminal(Direction::Vertical),
+                "close_pane" => app.close_terminal_pane(),
+                "toggle_maximize_pane" => app.toggle_maximized_pane(),
+                "focus_pane_left" => app.focus_terminal_pane(Side::Left),
+                "focus_pane_right" => app.focus_terminal_pane(Side::Right),
+                "focus_pane_up" => app.focus_terminal_pane(Side::Up),
+                "focus_pane_down" => app.focus_terminal_pane(Side::Down),
+                "resize_pane_left" => app.resize_terminal_pane(Side::Left),
+                "resize_pane_right" => app.resize_terminal_pane(Side::Right),
+                "resize_pane_up" => app.resize_terminal_pane(Side::Up),
+                "resize_pane_down" => app.resize_terminal_pane(Side::Down),
                 _ => { /* Unhandled action */ }
             }
             return Ok(Some(AppEvent::Continue));
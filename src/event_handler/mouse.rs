@@ -0,0 +1,111 @@
+use super::util::{copy_to_clipboard, send_key_to_terminal};
+use crate::{
+    app::App,
+    components::pty_screen::{PtyScreen, Selection},
+    ActiveTarget, MainWidgetContent,
+};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::layout::Position;
+
+/// Lines scrolled per mouse wheel step.
+const WHEEL_LINES: isize = 3;
+
+pub fn handle_mouse_event(mouse: MouseEvent, app: &mut App) {
+    match mouse.kind {
+        MouseEventKind::ScrollUp => scroll_terminal(mouse, WHEEL_LINES, app),
+        MouseEventKind::ScrollDown => scroll_terminal(mouse, -WHEEL_LINES, app),
+        MouseEventKind::Down(MouseButton::Left) => start_selection(mouse, app),
+        MouseEventKind::Drag(MouseButton::Left) => extend_selection(mouse, app),
+        MouseEventKind::Up(MouseButton::Left) => finish_selection(app),
+        _ => {}
+    }
+}
+
+/// The PTY views on screen: the panes of the active terminal tab and the active
+/// editor tab.
+fn visible_views(app: &mut App) -> Vec<(ActiveTarget, &mut dyn PtyScreen)> {
+    let mut views: Vec<(ActiveTarget, &mut dyn PtyScreen)> = Vec::new();
+    if app.show_panel {
+        if let Some(tab) = app.terminals.get_mut(app.active_terminal_tab) {
+            for term in tab.content.visible_mut() {
+                views.push((ActiveTarget::Panel, term));
+            }
+        }
+    }
+    if let Some(tab) = app.main_tabs.get_mut(app.active_main_tab) {
+        if let MainWidgetContent::Editor(editor) = &mut tab.content {
+            views.push((ActiveTarget::Editor, editor));
+        }
+    }
+    views
+}
+
+/// Starts selecting in the view under the mouse and focuses it. Any other
+/// selection is dropped.
+fn start_selection(mouse: MouseEvent, app: &mut App) {
+    let position = Position::new(mouse.column, mouse.row);
+    let mut focus = None;
+    for (target, view) in visible_views(app) {
+        if view.screen_area().contains(position) {
+            let point = view.point_at(mouse.column, mouse.row);
+            view.set_selection(Some(Selection::new(point, true)));
+            focus = Some(target);
+        } else {
+            view.set_selection(None);
+        }
+    }
+    if let Some(target) = focus {
+        app.active_target = target;
+        if let Some(panes) = app.active_panes() {
+            panes.focus_at(mouse.column, mouse.row);
+        }
+    }
+}
+
+fn extend_selection(mouse: MouseEvent, app: &mut App) {
+    for (_, view) in visible_views(app) {
+        if let Some(mut selection) = view.selection().copied().filter(|s| s.dragging) {
+            selection.extend_to(view.point_at(mouse.column, mouse.row));
+            view.set_selection(Some(selection));
+        }
+    }
+}
+
+/// Copies the dragged selection when the button is released, like most
+/// terminals, and drops it so that the next keys go to the program again.
+fn finish_selection(app: &mut App) {
+    let mut copied = None;
+    for (_, view) in visible_views(app) {
+        if view.selection().is_some_and(|s| s.dragging) {
+            copied = view.selected_text();
+            view.set_selection(None);
+        }
+    }
+    if let Some(text) = copied {
+        copy_to_clipboard(&text, &app.config);
+    }
+}
+
+/// Scrolls the terminal pane under the mouse through its scrollback.
+fn scroll_terminal(mouse: MouseEvent, lines: isize, app: &mut App) {
+    let Some(term) = app
+        .active_panes()
+        .and_then(|panes| panes.term_at(mouse.column, mouse.row))
+    else {
+        return;
+    };
+    if term.alternate_screen() {
+        // Full-screen programs have no scrollback; like most terminals, send them
+        // arrow keys instead.
+        let code = if lines > 0 {
+            KeyCode::Up
+        } else {
+            KeyCode::Down
+        };
+        for _ in 0..lines.abs() {
+            send_key_to_terminal(term, KeyEvent::new(code, KeyModifiers::NONE));
+        }
+    } else {
+        term.scroll_by(lines);
+    }
+}
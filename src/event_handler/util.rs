@@ -1,3 +1,11 @@
+use crate::{
+    clipboard,
+    components::{
+        notification::{send_notification, NotificationType},
+        pty_screen::PtyScreen,
+    },
+    settings::Config,
+};
 use crossterm::event::{self, KeyCode, KeyEvent, KeyModifiers};
 
 /// Converts a `KeyEvent` to a string representation like "Ctrl-S" or "Alt-J".
@@ -130,6 +138,58 @@ where
     }
 }
 
+/// Handles keys while selecting text with the keyboard, which the `select_text`
+/// action starts: arrows move the end of the selection, Enter or Ctrl+Shift+C
+/// copies and Esc cancels. Returns `true` when the key was used; without a
+/// selection every key is left for the program, and any other key ends the
+/// selection and is passed on too.
+pub fn handle_selection_key<T>(target: &mut T, key: KeyEvent, config: &Config) -> bool
+where
+    T: PtyScreen + ?Sized,
+{
+    if target.selection().is_none() {
+        return false;
+    }
+    let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    match key.code {
+        KeyCode::Left => target.move_selection(0, -1),
+        KeyCode::Right => target.move_selection(0, 1),
+        KeyCode::Up => target.move_selection(-1, 0),
+        KeyCode::Down => target.move_selection(1, 0),
+        KeyCode::Enter => copy_selection(target, config),
+        KeyCode::Char('c' | 'C') if ctrl && shift => copy_selection(target, config),
+        KeyCode::Esc => target.set_selection(None),
+        _ => {
+            target.set_selection(None);
+            return false;
+        }
+    }
+    true
+}
+
+/// Copies the selected text to the clipboard and clears the selection.
+fn copy_selection<T>(target: &mut T, config: &Config)
+where
+    T: PtyScreen + ?Sized,
+{
+    if let Some(text) = target.selected_text() {
+        copy_to_clipboard(&text, config);
+    }
+    target.set_selection(None);
+}
+
+/// Copies `text` to the clipboard and reports how it went.
+pub fn copy_to_clipboard(text: &str, config: &Config) {
+    match clipboard::copy(text, config) {
+        Ok(()) => send_notification(
+            format!("Copied {} characters.", text.chars().count()),
+            NotificationType::Info,
+        ),
+        Err(e) => send_notification(format!("Copy failed: {}", e), NotificationType::Error),
+    }
+}
+
 pub trait PtyInput {
     fn send_input(&self, bytes: &[u8]);
 }
@@ -0,0 +1,328 @@
+use anyhow::{anyhow, Context, Result};
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Creates an empty file, along with any missing parent directories.
+pub fn create_file(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .with_context(|| format!("Could not create {}", path.display()))?;
+    Ok(())
+}
+
+pub fn create_dir(path: &Path) -> Result<()> {
+    if path.exists() {
+        return Err(anyhow!("{} already exists", path.display()));
+    }
+    fs::create_dir_all(path).with_context(|| format!("Could not create {}", path.display()))
+}
+
+/// Copies a file or a whole directory tree to `to`, which must not exist yet.
+pub fn copy_path(from: &Path, to: &Path) -> Result<()> {
+    if to.exists() {
+        return Err(anyhow!("{} already exists", to.display()));
+    }
+    if to.starts_with(from) {
+        return Err(anyhow!("Cannot copy {} into itself", from.display()));
+    }
+    copy_recursively(from, to).with_context(|| format!("Could not copy {}", from.display()))
+}
+
+fn copy_recursively(from: &Path, to: &Path) -> io::Result<()> {
+    let meta = fs::symlink_metadata(from)?;
+    if meta.file_type().is_symlink() {
+        copy_symlink(from, to)
+    } else if meta.is_dir() {
+        fs::create_dir(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_recursively(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        fs::copy(from, to).map(|_| ())
+    }
+}
+
+#[cfg(unix)]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(from)?, to)
+}
+
+#[cfg(windows)]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    use std::os::windows::fs::{symlink_dir, symlink_file};
+    let target = fs::read_link(from)?;
+    // Windows links record whether they point at a directory.
+    if fs::metadata(from).is_ok_and(|m| m.is_dir()) {
+        symlink_dir(target, to)
+    } else {
+        symlink_file(target, to)
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn copy_symlink(from: &Path, _to: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("Cannot copy the symbolic link {}", from.display()),
+    ))
+}
+
+/// Renames or moves `from` to `to`, falling back to copy and delete across file systems.
+pub fn move_path(from: &Path, to: &Path) -> Result<()> {
+    if to.exists() {
+        return Err(anyhow!("{} already exists", to.display()));
+    }
+    if to.starts_with(from) {
+        return Err(anyhow!("Cannot move {} into itself", from.display()));
+    }
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    match fs::rename(from, to) {
+        Ok(()) => return Ok(()),
+        Err(e) if crosses_devices(&e) => {}
+        Err(e) => return Err(e).with_context(|| format!("Could not move {}", from.display())),
+    }
+    copy_recursively(from, to).with_context(|| format!("Could not move {}", from.display()))?;
+    remove_path(from)
+}
+
+/// Whether a rename failed only because `from` and `to` are on different file
+/// systems. `EXDEV` is checked too in case the platform does not map it.
+fn crosses_devices(e: &io::Error) -> bool {
+    const EXDEV: i32 = 18;
+    e.kind() == io::ErrorKind::CrossesDevices || (cfg!(unix) && e.raw_os_error() == Some(EXDEV))
+}
+
+fn remove_path(path: &Path) -> Result<()> {
+    let meta = fs::symlink_metadata(path)?;
+    if meta.is_dir() {
+        fs::remove_dir_all(path)?;
+    } else {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Replaces the contents of `path` by writing a temporary file next to it and
+/// renaming it over the original, so readers never see a half-written file. The
+/// original file's permissions and, where allowed, owner are kept. A symlink is
+/// followed so the file it points to is replaced rather than the link itself.
+pub fn write_atomic(path: &Path, contents: &str) -> Result<()> {
+    let is_link = fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink());
+    let resolved;
+    let path = if is_link {
+        resolved = fs::canonicalize(path)
+            .with_context(|| format!("Could not resolve {}", path.display()))?;
+        resolved.as_path()
+    } else {
+        path
+    };
+    let dir = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("Cannot write {}", path.display()))?
+        .to_string_lossy();
+    let temp = dir.join(format!(".{}.{}.tmp", name, std::process::id()));
+    let result = (|| -> io::Result<()> {
+        let mut file = fs::File::create(&temp)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+        if let Ok(meta) = fs::metadata(path) {
+            fs::set_permissions(&temp, meta.permissions())?;
+            keep_owner(&temp, &meta);
+        }
+        fs::rename(&temp, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result.with_context(|| format!("Could not write {}", path.display()))
+}
+
+/// Gives `temp` the owner and group in `meta`. Only root may hand a file to
+/// another user, so failing is fine: the file then belongs to whoever saved it.
+#[cfg(unix)]
+fn keep_owner(temp: &Path, meta: &fs::Metadata) {
+    use std::os::unix::fs::MetadataExt;
+    if std::os::unix::fs::chown(temp, Some(meta.uid()), Some(meta.gid())).is_err() {
+        // Keeping the group alone is allowed for groups the user is in.
+        let _ = std::os::unix::fs::chown(temp, None, Some(meta.gid()));
+    }
+}
+
+#[cfg(not(unix))]
+fn keep_owner(_temp: &Path, _meta: &fs::Metadata) {}
+
+/// Returns `dir/name`, or `dir/<stem> copy.<ext>`, `dir/<stem> copy 2.<ext>`, ...
+/// if that is taken.
+pub fn unique_destination(dir: &Path, name: &str) -> PathBuf {
+    let candidate = dir.join(name);
+    if !candidate.exists() {
+        return candidate;
+    }
+    let (stem, ext) = split_name(name);
+    (1..)
+        .map(|n| {
+            let suffix = if n == 1 {
+                " copy".to_string()
+            } else {
+                format!(" copy {}", n)
+            };
+            dir.join(format!("{}{}{}", stem, suffix, ext))
+        })
+        .find(|p| !p.exists())
+        .unwrap_or(candidate)
+}
+
+/// Splits `name.ext` into (`name`, `.ext`); dotfiles and directories have no extension.
+fn split_name(name: &str) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(i) if i > 0 => name.split_at(i),
+        _ => (name, ""),
+    }
+}
+
+/// Moves `path` to the user's trash following the XDG trash specification, so it can
+/// be restored from a file manager. Returns the location inside the trash.
+pub fn move_to_trash(path: &Path) -> Result<PathBuf> {
+    // Resolve the parent only, so a symlink is trashed itself rather than its target.
+    let name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("Cannot trash {}", path.display()))?
+        .to_string_lossy()
+        .to_string();
+    let parent = match path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        Some(parent) => parent.canonicalize(),
+        None => env::current_dir(),
+    }
+    .with_context(|| format!("Could not resolve {}", path.display()))?;
+    let path = parent.join(&name);
+    let trash = trash_dir()?;
+    let files_dir = trash.join("files");
+    let info_dir = trash.join("info");
+    fs::create_dir_all(&files_dir)?;
+    fs::create_dir_all(&info_dir)?;
+
+    let (stem, ext) = split_name(&name);
+
+    // The .trashinfo file is created exclusively first to reserve the name.
+    let mut n = 0;
+    loop {
+        n += 1;
+        let trashed_name = if n == 1 {
+            name.clone()
+        } else {
+            format!("{}.{}{}", stem, n, ext)
+        };
+        let info_path = info_dir.join(format!("{}.trashinfo", trashed_name));
+        let mut info_file = match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&info_path)
+        {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        };
+        let target = files_dir.join(&trashed_name);
+        if target.exists() {
+            drop(info_file);
+            let _ = fs::remove_file(&info_path);
+            continue;
+        }
+
+        let info = format!(
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            percent_encode(&path.to_string_lossy()),
+            chrono::Local::now().format("%Y-%m-%dT%H:%M:%S")
+        );
+        info_file.write_all(info.as_bytes())?;
+        if let Err(e) = move_path(&path, &target) {
+            let _ = fs::remove_file(&info_path);
+            return Err(e);
+        }
+        return Ok(target);
+    }
+}
+
+/// `$XDG_DATA_HOME/Trash`, defaulting to `~/.local/share/Trash`.
+fn trash_dir() -> Result<PathBuf> {
+    if let Some(data_home) = env::var_os("XDG_DATA_HOME").filter(|v| !v.is_empty()) {
+        return Ok(PathBuf::from(data_home).join("Trash"));
+    }
+    let home = env::var_os("HOME").ok_or_else(|| anyhow!("Could not find the home directory"))?;
+    Ok(PathBuf::from(home).join(".local/share/Trash"))
+}
+
+/// Percent-encodes a path for the `Path=` key of a `.trashinfo` file.
+fn percent_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || b"/-_.~".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn write_atomic_replaces_the_target_of_a_symlink() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("real.txt");
+        let link = dir.path().join("link.txt");
+        fs::write(&target, "old").unwrap();
+        std::os::unix::fs::symlink("real.txt", &link).unwrap();
+
+        write_atomic(&link, "new").unwrap();
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read_link(&link).unwrap(), Path::new("real.txt"));
+        assert_eq!(fs::read_to_string(&target).unwrap(), "new");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_atomic_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("script.sh");
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o750)).unwrap();
+
+        write_atomic(&path, "new").unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o750);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn write_atomic_creates_missing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("new.txt");
+        write_atomic(&path, "text").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "text");
+    }
+}
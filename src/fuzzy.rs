@@ -0,0 +1,210 @@
+//! Subsequence fuzzy matching with fzf-style scoring.
+//!
+//! Every pattern character must appear in the candidate in order. Among all such
+//! alignments the best-scoring one is chosen: matches at word starts, after path
+//! separators, on camelCase humps, inside the file name and in consecutive runs
+//! score higher, while gaps between matched characters cost points.
+
+const SCORE_MATCH: i64 = 16;
+const PENALTY_GAP_START: i64 = -3;
+const PENALTY_GAP_EXTENSION: i64 = -1;
+const BONUS_SEPARATOR: i64 = 9;
+const BONUS_BOUNDARY: i64 = 8;
+const BONUS_CAMEL: i64 = 7;
+const BONUS_CONSECUTIVE: i64 = 5;
+const BONUS_FILENAME: i64 = 3;
+/// The first pattern character's position bonus counts this many times.
+const FIRST_CHAR_MULTIPLIER: i64 = 2;
+
+/// A successful match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    /// Char indices of the matched characters in the candidate, ascending.
+    pub positions: Vec<usize>,
+}
+
+/// Matches `pattern` against `candidate`. Whitespace in the pattern is ignored and
+/// matching is case-insensitive unless the pattern contains an uppercase letter.
+/// An empty pattern matches everything with a score of 0.
+pub fn fuzzy_match(pattern: &str, candidate: &str) -> Option<FuzzyMatch> {
+    let pattern: Vec<char> = pattern.chars().filter(|c| !c.is_whitespace()).collect();
+    if pattern.is_empty() {
+        return Some(FuzzyMatch {
+            score: 0,
+            positions: Vec::new(),
+        });
+    }
+    let case_sensitive = pattern.iter().any(|c| c.is_uppercase());
+    let fold = |c: char| {
+        if case_sensitive {
+            c
+        } else {
+            c.to_lowercase().next().unwrap_or(c)
+        }
+    };
+    let pattern: Vec<char> = pattern.into_iter().map(fold).collect();
+    let original: Vec<char> = candidate.chars().collect();
+    let text: Vec<char> = original.iter().copied().map(fold).collect();
+
+    // Cheap rejection before the quadratic pass.
+    let mut pi = 0;
+    for &c in &text {
+        if pi < pattern.len() && c == pattern[pi] {
+            pi += 1;
+        }
+    }
+    if pi < pattern.len() {
+        return None;
+    }
+
+    let filename_start = original
+        .iter()
+        .rposition(|&c| c == '/' || c == '\\')
+        .map_or(0, |i| i + 1);
+    let bonus: Vec<i64> = (0..original.len())
+        .map(|j| {
+            let prev = j.checked_sub(1).map(|p| original[p]);
+            let mut b = position_bonus(prev, original[j]);
+            if j >= filename_start {
+                b += BONUS_FILENAME;
+            }
+            b
+        })
+        .collect();
+
+    let (m, n) = (pattern.len(), text.len());
+    // score[i][j]: best score with pattern[i] matched at text[j]; `from` stores where
+    // pattern[i - 1] was matched for backtracking.
+    let mut score = vec![vec![None::<i64>; n]; m];
+    let mut from = vec![vec![0usize; n]; m];
+
+    for j in 0..n {
+        if text[j] == pattern[0] {
+            score[0][j] = Some(SCORE_MATCH + bonus[j] * FIRST_CHAR_MULTIPLIER);
+        }
+    }
+    for i in 1..m {
+        // Best predecessor that leaves a gap before the current column.
+        let mut gap_best: Option<(i64, usize)> = None;
+        for j in i..n {
+            if j >= 2 {
+                if let Some(prev) = score[i - 1][j - 2] {
+                    let opened = prev + PENALTY_GAP_START;
+                    let extended = gap_best.map(|(s, _)| s + PENALTY_GAP_EXTENSION);
+                    gap_best = match extended {
+                        Some(s) if s >= opened => gap_best.map(|(_, k)| (s, k)),
+                        _ => Some((opened, j - 2)),
+                    };
+                } else if let Some((s, k)) = gap_best {
+                    gap_best = Some((s + PENALTY_GAP_EXTENSION, k));
+                }
+            }
+            if text[j] != pattern[i] {
+                continue;
+            }
+            let consecutive = score[i - 1][j - 1].map(|s| (s + BONUS_CONSECUTIVE, j - 1));
+            let best = match (consecutive, gap_best) {
+                (Some(a), Some(b)) => Some(if a.0 >= b.0 { a } else { b }),
+                (a, b) => a.or(b),
+            };
+            if let Some((s, k)) = best {
+                score[i][j] = Some(s + SCORE_MATCH + bonus[j]);
+                from[i][j] = k;
+            }
+        }
+    }
+
+    let (mut j, best) = score[m - 1]
+        .iter()
+        .enumerate()
+        .filter_map(|(j, s)| s.map(|s| (j, s)))
+        .max_by_key(|&(j, s)| (s, std::cmp::Reverse(j)))?;
+    let mut positions = vec![0; m];
+    for i in (0..m).rev() {
+        positions[i] = j;
+        j = from[i][j];
+    }
+    Some(FuzzyMatch {
+        score: best,
+        positions,
+    })
+}
+
+fn position_bonus(prev: Option<char>, current: char) -> i64 {
+    match prev {
+        None => BONUS_BOUNDARY,
+        Some('/') | Some('\\') => BONUS_SEPARATOR,
+        Some('_' | '-' | '.' | ' ' | ':') => BONUS_BOUNDARY,
+        Some(p) if p.is_lowercase() && current.is_uppercase() => BONUS_CAMEL,
+        Some(p) if !p.is_alphanumeric() && current.is_alphanumeric() => BONUS_BOUNDARY,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(pattern: &str, candidate: &str) -> i64 {
+        fuzzy_match(pattern, candidate).unwrap().score
+    }
+
+    #[test]
+    fn matches_word_starts_across_a_path() {
+        let m = fuzzy_match("mwed", "main_widget/editor.rs").unwrap();
+        assert_eq!(m.positions, [0, 5, 12, 13]);
+        assert!(fuzzy_match("mwdex", "main_widget/editor.rs").is_none());
+    }
+
+    #[test]
+    fn empty_and_whitespace_patterns_match_everything() {
+        let empty = FuzzyMatch {
+            score: 0,
+            positions: Vec::new(),
+        };
+        assert_eq!(fuzzy_match("", "anything"), Some(empty.clone()));
+        assert_eq!(fuzzy_match("  ", "anything"), Some(empty));
+        assert_eq!(
+            fuzzy_match("e d", "main_widget/editor.rs")
+                .unwrap()
+                .positions,
+            [12, 13]
+        );
+    }
+
+    #[test]
+    fn uppercase_in_the_pattern_makes_it_case_sensitive() {
+        assert!(fuzzy_match("readme", "README.md").is_some());
+        assert!(fuzzy_match("Readme", "README.md").is_none());
+    }
+
+    #[test]
+    fn matches_in_the_file_name_rank_higher() {
+        assert!(score("main", "src/main.rs") > score("main", "main/lib.rs"));
+        assert!(score("app", "src/components/app.rs") > score("app", "app/src/lib.rs"));
+    }
+
+    #[test]
+    fn separators_beat_word_boundaries_beat_camel_humps() {
+        assert!(position_bonus(Some('/'), 'e') > position_bonus(Some('_'), 'e'));
+        assert!(position_bonus(Some('_'), 'e') > position_bonus(Some('a'), 'E'));
+        assert!(position_bonus(Some('a'), 'E') > position_bonus(Some('a'), 'e'));
+        assert_eq!(position_bonus(None, 'e'), BONUS_BOUNDARY);
+        assert!(score("ed", "ui/editor") > score("ed", "ui_editor"));
+        assert!(score("ed", "ui_editor") > score("ed", "uiEditor"));
+        assert!(score("ed", "uiEditor") > score("ed", "uieditor"));
+    }
+
+    #[test]
+    fn prefers_the_alignment_on_a_word_start() {
+        // The "e" of "model" comes first, but the match lands on "editor".
+        let m = fuzzy_match("ed", "model_editor").unwrap();
+        assert_eq!(m.positions, [6, 7]);
+    }
+
+    #[test]
+    fn consecutive_matches_beat_scattered_ones() {
+        assert!(score("abc", "xabcx") > score("abc", "xaxbxcx"));
+    }
+}
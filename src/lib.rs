@@ -1,13 +1,21 @@
 use crate::components::main_widget::{
-    editor::Editor, settings_editor::SettingsEditor, welcome_widget::WelcomeWidget,
+    conflict_view::ConflictView, editor::Editor, git_log::GitLog, settings_editor::SettingsEditor,
+    text_editor::TextEditor, welcome_widget::WelcomeWidget,
 };
 
 pub mod app;
+pub mod cli;
+pub mod clipboard;
+pub mod command_line;
 pub mod components;
 pub mod event_handler;
+pub mod file_ops;
+pub mod fuzzy;
+pub mod session;
 pub mod settings;
 pub mod theme;
 pub mod ui;
+pub mod workspace;
 
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum ActiveTarget {
@@ -19,9 +27,23 @@ pub enum ActiveTarget {
 
 pub enum MainWidgetContent {
     Editor(Editor),
+    TextEditor(TextEditor),
     SettingsEditor(SettingsEditor),
+    GitLog(Box<GitLog>),
+    Conflicts(Box<ConflictView>),
     Welcome(WelcomeWidget),
 }
 
+impl MainWidgetContent {
+    /// The file shown in this tab, if it is an editor with a file.
+    pub fn file_path(&self) -> Option<&std::path::Path> {
+        match self {
+            MainWidgetContent::Editor(e) => e.path(),
+            MainWidgetContent::TextEditor(e) => e.path(),
+            _ => None,
+        }
+    }
+}
+
 pub use components::popup::Popup;
 pub use components::popup::PopupResult;
@@ -1,34 +1,66 @@
 use anyhow::Result;
+use clap::Parser;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture},
+    event::{
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 // Updated import path
 use inf_edit::app::App;
+use inf_edit::cli::Cli;
 use inf_edit::event_handler;
 use inf_edit::ui;
 use ratatui::{backend::CrosstermBackend, Terminal};
+use std::env;
 use std::io;
 use std::time::Duration;
 
 fn main() -> Result<()> {
-    // Changed to anyhow::Result
+    // Parse arguments before touching the terminal so `--help` and errors print normally.
+    let options = Cli::parse().startup_options()?;
+    if let Some(workspace) = &options.workspace {
+        // Every component resolves the workspace from the current directory.
+        env::set_current_dir(workspace)?;
+    }
+    let mut app = App::new(&options)?;
+
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(
+        stdout,
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste
+    )?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut app = App::new()?;
+    let result = run(&mut terminal, &mut app);
+    // Saved even when the loop failed, so an I/O error does not lose the open tabs.
+    app.save_session();
+
+    disable_raw_mode()?;
+    execute!(
+        std::io::stdout(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        DisableBracketedPaste
+    )?;
+    terminal.show_cursor()?;
+    result
+}
+
+fn run(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut App) -> Result<()> {
     loop {
-        terminal.draw(|f| ui::draw(f, &mut app))?;
+        terminal.draw(|f| ui::draw(f, app))?;
 
         // Poll for events with a timeout to keep the UI responsive and run ticks.
         if event::poll(Duration::from_millis(100))? {
             // If an event is available, handle it.
-            match event_handler::handle_events(&mut app)? {
-                event_handler::AppEvent::Quit => break,
+            match event_handler::handle_events(app)? {
+                event_handler::AppEvent::Quit => return Ok(()),
                 event_handler::AppEvent::Continue => {}
             }
         }
@@ -36,9 +68,4 @@ fn main() -> Result<()> {
         // Run periodic tasks like checking for exited terminals.
         app.tick();
     }
-
-    disable_raw_mode()?;
-    execute!(std::io::stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
-    terminal.show_cursor()?;
-    Ok(())
 }
@@ -0,0 +1,94 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Layout and open tabs of a workspace, persisted between runs.
+///
+/// Scalar fields must come before the tab lists so the TOML serializer can
+/// emit them ahead of the arrays of tables.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct Session {
+    pub active_main_tab: usize,
+    pub active_terminal_tab: usize,
+    pub active_primary_sidebar_tab: usize,
+    pub show_primary_sidebar: bool,
+    pub show_secondary_sidebar: bool,
+    pub show_panel: bool,
+    pub tabs: Vec<SessionTab>,
+    pub terminals: Vec<SessionTerminal>,
+}
+
+/// A restorable main tab. Welcome tabs are not recorded.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SessionTab {
+    Editor {
+        path: PathBuf,
+        builtin: bool,
+        line: Option<usize>,
+        column: Option<usize>,
+    },
+    Settings,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct SessionTerminal {
+    pub title: String,
+    pub cwd: Option<PathBuf>,
+}
+
+impl Session {
+    /// Loads the saved session for `workspace`, if there is one.
+    pub fn load(workspace: &Path) -> Result<Option<Self>> {
+        let path = Self::session_path(workspace)?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(path)?;
+        Ok(Some(toml::from_str(&content)?))
+    }
+
+    pub fn save(&self, workspace: &Path) -> Result<()> {
+        let path = Self::session_path(workspace)?;
+        fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Deletes the saved session for `workspace`.
+    pub fn clear(workspace: &Path) -> Result<()> {
+        let path = Self::session_path(workspace)?;
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// Session files live in the data dir, one per workspace root. The file name
+    /// keeps the directory name readable and disambiguates it with a path hash.
+    fn session_path(workspace: &Path) -> Result<PathBuf> {
+        let proj_dirs = directories_next::ProjectDirs::from("com", "inf-edit", "inf-edit")
+            .ok_or_else(|| anyhow!("Could not find a valid home directory"))?;
+        let sessions_dir = proj_dirs.data_dir().join("sessions");
+        fs::create_dir_all(&sessions_dir)?;
+
+        let name: String = workspace
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "root".to_string())
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect();
+        let hash = fnv1a(workspace.to_string_lossy().as_bytes());
+        Ok(sessions_dir.join(format!("{}-{:016x}.toml", name, hash)))
+    }
+}
+
+/// A stable 64-bit FNV-1a hash; `DefaultHasher` is not stable across releases.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x100000001b3)
+    })
+}
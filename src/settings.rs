@@ -1,5 +1,5 @@
 use anyhow::{anyhow, Result};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
@@ -9,20 +9,51 @@ use toml;
 #[serde(default)]
 pub struct Config {
     pub editor: String,
+    /// Open files in the built-in text editor instead of spawning `editor` in a PTY.
+    pub use_builtin_editor: bool,
     pub terminal: Option<String>,
+    /// Working directory for the editor process; supports the same `{...}` templates.
+    pub editor_cwd: Option<String>,
+    /// Working directory for new terminals; supports the same `{...}` templates.
+    pub terminal_cwd: Option<String>,
+    /// Restore open tabs and layout of a workspace on the next launch.
+    pub restore_session: bool,
+    /// Lines of terminal output kept for scrolling back.
+    pub terminal_scrollback: usize,
+    /// Command that receives copied text on stdin, e.g. `wl-copy` or
+    /// `xclip -selection clipboard`. Without one, OSC 52 asks the terminal to copy.
+    pub clipboard_command: Option<String>,
+    pub files: FilesConfig,
     pub keybindings: Keybindings,
     pub theme: Theme,
+    /// Where this config was loaded from and will be saved to.
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+}
+
+/// Which files the file view, search and quick open list.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct FilesConfig {
+    /// Glob patterns to hide, e.g. `node_modules` or `**/*.log`.
+    pub exclude: Vec<String>,
+    /// Show dotfiles and dot-directories.
+    pub show_hidden: bool,
+    /// Show files matched by `.gitignore`, `.ignore` and global git excludes.
+    pub show_ignored: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
 #[serde(default)]
 pub struct Keybindings {
     // Example: "Ctrl+Q" -> "quit"
+    /// Loaded on top of the defaults; bind a key to `""` to free it.
+    #[serde(deserialize_with = "over_default_bindings")]
     pub global: HashMap<String, String>,
     // You can add other contexts like "editor", "file_view"
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(default)]
 pub struct Theme {
     pub preset: String,
@@ -37,9 +68,27 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             editor: std::env::var("EDITOR").unwrap_or_else(|_| "vi".to_string()),
+            use_builtin_editor: false,
             terminal: None, // None means use default shell
+            editor_cwd: None,
+            terminal_cwd: None,
+            restore_session: true,
+            terminal_scrollback: 10_000,
+            clipboard_command: None,
+            files: FilesConfig::default(),
             keybindings: Keybindings::default(),
             theme: Theme::default(),
+            path: None,
+        }
+    }
+}
+
+impl Default for FilesConfig {
+    fn default() -> Self {
+        Self {
+            exclude: vec![".git".to_string()],
+            show_hidden: true,
+            show_ignored: false,
         }
     }
 }
@@ -60,10 +109,38 @@ impl Default for Keybindings {
             "Ctrl-Alt-B".to_string(),
             "toggle_secondary_sidebar".to_string(),
         );
+        global.insert("Ctrl-Alt-R".to_string(), "reveal_active_file".to_string());
+        global.insert("Ctrl-Alt-G".to_string(), "toggle_blame".to_string());
+        global.insert("F4".to_string(), "next_search_result".to_string());
+        global.insert("Shift-F4".to_string(), "prev_search_result".to_string());
+        global.insert("Ctrl-Alt-F".to_string(), "find_in_terminal".to_string());
+        global.insert("Ctrl-Alt-C".to_string(), "select_text".to_string());
+        global.insert("Ctrl-Alt-V".to_string(), "split_pane_right".to_string());
+        global.insert("Ctrl-Alt-S".to_string(), "split_pane_down".to_string());
+        global.insert("Ctrl-Alt-W".to_string(), "close_pane".to_string());
+        global.insert("Ctrl-Alt-M".to_string(), "toggle_maximize_pane".to_string());
+        for side in ["Left", "Right", "Up", "Down"] {
+            let name = side.to_lowercase();
+            global.insert(format!("Ctrl-Alt-{}", side), format!("focus_pane_{}", name));
+            global.insert(
+                format!("Ctrl-Alt-Shift-{}", side),
+                format!("resize_pane_{}", name),
+            );
+        }
         Self { global }
     }
 }
 
+/// Reads the configured global bindings over the default ones, so keys added in
+/// newer versions work without editing an existing `settings.toml`.
+fn over_default_bindings<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<HashMap<String, String>, D::Error> {
+    let mut global = Keybindings::default().global;
+    global.extend(HashMap::<String, String>::deserialize(deserializer)?);
+    Ok(global)
+}
+
 impl Default for Theme {
     fn default() -> Self {
         Self {
@@ -79,17 +156,34 @@ impl Default for Theme {
 
 impl Config {
     pub fn load() -> Result<Self> {
-        let path = Self::get_config_path()?;
-        if path.exists() {
-            let content = fs::read_to_string(path)?;
-            Ok(toml::from_str(&content)?)
+        Self::load_from(None)
+    }
+
+    /// Loads the config from `path`, or from the default location if `None`.
+    /// Only the default location may be missing; it is created on first save.
+    pub fn load_from(path: Option<PathBuf>) -> Result<Self> {
+        let path = match path {
+            Some(path) if !path.exists() => {
+                return Err(anyhow!("Config file {} does not exist", path.display()));
+            }
+            Some(path) => path,
+            None => Self::get_config_path()?,
+        };
+        let mut config = if path.exists() {
+            let content = fs::read_to_string(&path)?;
+            toml::from_str(&content)?
         } else {
-            Ok(Config::default())
-        }
+            Config::default()
+        };
+        config.path = Some(path);
+        Ok(config)
     }
 
     pub fn save(&self) -> Result<()> {
-        let path = Self::get_config_path()?;
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => Self::get_config_path()?,
+        };
         let content = toml::to_string_pretty(self)?;
         fs::write(path, content)?;
         Ok(())
@@ -103,3 +197,48 @@ impl Config {
         Ok(config_dir.join("settings.toml"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn configured_bindings_override_and_extend_the_defaults() {
+        let config: Config = toml::from_str(
+            r#"
+            [keybindings.global]
+            "Ctrl-W" = "quit"
+            "Ctrl-Alt-Q" = "close_tab"
+            "Ctrl-B" = ""
+            "#,
+        )
+        .unwrap();
+        let global = &config.keybindings.global;
+        let defaults = Keybindings::default().global;
+        assert_eq!(global["Ctrl-W"], "quit");
+        assert_eq!(global["Ctrl-Alt-Q"], "close_tab");
+        assert_eq!(global["Ctrl-B"], "");
+        assert_eq!(global["Ctrl-P"], defaults["Ctrl-P"]);
+        assert_eq!(global.len(), defaults.len() + 1);
+    }
+
+    #[test]
+    fn an_explicit_config_path_must_exist() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.toml");
+        assert!(Config::load_from(Some(missing.clone())).is_err());
+        assert!(!missing.exists());
+
+        let existing = dir.path().join("settings.toml");
+        fs::write(&existing, "editor = \"vi\"").unwrap();
+        let config = Config::load_from(Some(existing.clone())).unwrap();
+        assert_eq!(config.editor, "vi");
+        assert_eq!(config.path, Some(existing));
+    }
+
+    #[test]
+    fn missing_keybindings_table_uses_the_defaults() {
+        let config: Config = toml::from_str("editor = \"vi\"").unwrap();
+        assert_eq!(config.keybindings, Keybindings::default());
+    }
+}
@@ -23,7 +23,7 @@ pub fn draw(f: &mut Frame, app: &mut App) {
     f.render_widget(top_bar.get_title_widget(app), main_chunks[0]);
 
     let bottom_bar = BottomBar::new();
-    f.render_widget(bottom_bar.get_status_widget(app), main_chunks[2]);
+    bottom_bar.render(f, main_chunks[2], app);
 
     // Main Content Area
     let sidebar_width = if app.show_primary_sidebar {
@@ -90,9 +90,20 @@ pub fn draw(f: &mut Frame, app: &mut App) {
         Panel::new().render(f, main_area_chunks[1], app);
     }
 
+    if let Some(tab) = app
+        .primary_sidebar_components
+        .iter()
+        .find(|tab| tab.content.has_popup())
+    {
+        tab.content.render_popup(f, f.area(), &app.theme);
+    }
+
     if let Some(popup) = &app.quit_popup {
         popup.render(f, f.area(), &app.theme);
     }
+    if let Some((popup, _)) = &app.save_prompt {
+        popup.render(f, f.area(), &app.theme);
+    }
 
     // Render Command Palette if active (must be before notifications)
     if app.show_command_palette {
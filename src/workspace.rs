@@ -0,0 +1,108 @@
+use crate::{
+    components::notification::{send_notification, NotificationType},
+    settings::FilesConfig,
+};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::{DirEntry, WalkBuilder};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Lists workspace files the same way everywhere: honours `.gitignore`, `.ignore` and
+/// global git excludes, plus the `files.exclude` globs from `settings.toml`.
+///
+/// Cheap to clone, so it can be handed to background threads.
+#[derive(Clone)]
+pub struct WorkspaceWalker {
+    root: PathBuf,
+    config: FilesConfig,
+    exclude: Arc<GlobSet>,
+}
+
+impl WorkspaceWalker {
+    pub fn new(root: PathBuf, config: &FilesConfig) -> Self {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &config.exclude {
+            match Glob::new(pattern) {
+                Ok(glob) => {
+                    builder.add(glob);
+                }
+                Err(e) => send_notification(
+                    format!("Invalid files.exclude pattern `{}`: {}", pattern, e),
+                    NotificationType::Warning,
+                ),
+            }
+        }
+        let exclude = builder.build().unwrap_or_else(|_| GlobSet::empty());
+        Self {
+            root,
+            config: config.clone(),
+            exclude: Arc::new(exclude),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn config(&self) -> &FilesConfig {
+        &self.config
+    }
+
+    /// Whether `path` matches one of the exclude globs. Globs are matched against the
+    /// path relative to the root and against the file name alone, so `node_modules`
+    /// and `**/node_modules` both hide every `node_modules` directory.
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        if self.exclude.is_empty() {
+            return false;
+        }
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        self.exclude.is_match(relative)
+            || path
+                .file_name()
+                .is_some_and(|name| self.exclude.is_match(Path::new(name)))
+    }
+
+    /// A walker over `path` with the ignore rules and exclude globs applied.
+    pub fn builder(&self, path: &Path) -> WalkBuilder {
+        let show_ignored = self.config.show_ignored;
+        let mut builder = WalkBuilder::new(path);
+        builder
+            .hidden(!self.config.show_hidden)
+            .ignore(!show_ignored)
+            .git_ignore(!show_ignored)
+            .git_global(!show_ignored)
+            .git_exclude(!show_ignored)
+            .parents(!show_ignored)
+            .require_git(false);
+        let walker = self.clone();
+        builder.filter_entry(move |entry: &DirEntry| !walker.is_excluded(entry.path()));
+        builder
+    }
+
+    /// Every visible file below the root, recursively.
+    pub fn files(&self) -> impl Iterator<Item = PathBuf> {
+        self.builder(&self.root)
+            .build()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_some_and(|t| t.is_file()))
+            .map(DirEntry::into_path)
+    }
+
+    /// The visible direct children of `dir`, directories first.
+    pub fn children(&self, dir: &Path) -> Vec<PathBuf> {
+        let mut children: Vec<PathBuf> = self
+            .builder(dir)
+            .max_depth(Some(1))
+            .build()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.depth() == 1)
+            .map(DirEntry::into_path)
+            .collect();
+        children.sort_by(|a, b| {
+            b.is_dir()
+                .cmp(&a.is_dir())
+                .then_with(|| a.file_name().cmp(&b.file_name()))
+        });
+        children
+    }
+}